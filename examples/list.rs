@@ -1,13 +1,13 @@
 extern crate libc;
 extern crate libparted;
 
-use libparted::{Device, Disk};
+use libparted::{format_size, Device, Disk, Standard};
 
 use std::io::Result;
 use std::process;
 
 fn list() -> Result<()> {
-    for (dev_i, mut device) in Device::devices(true).enumerate() {
+    for (dev_i, device) in Device::devices(true).enumerate() {
         let hw_geom = device.hw_geom();
         let bios_geom = device.bios_geom();
 
@@ -16,6 +16,10 @@ fn list() -> Result<()> {
         println!("    Path:          {:?}", device.path());
         println!("    Sectors:       {}", device.length());
         println!("    Sector Size:   {}", device.sector_size());
+        println!(
+            "    Size:          {}",
+            format_size(device.length() * device.sector_size(), Standard::IEC)
+        );
         println!("    Type:          {:?}", device.type_());
         println!("    Open Count:    {}", device.open_count());
         println!("    Read Only:     {}", device.read_only());
@@ -33,10 +37,10 @@ fn list() -> Result<()> {
         println!("    Host:          {}", device.host());
         println!("    Did:           {}", device.did());
 
-        let disk = Disk::new(&mut device)?;
+        let disk = Disk::new(&device)?;
         eprintln!("    Disk Type:    {:?}", disk.get_disk_type_name());
 
-        for part in disk.parts() {
+        for part in disk.partitions() {
             println!("    Part {}", part.num());
             println!("        Type Name: {:?}", part.type_get_name());
             println!("        Name:      {:?}", part.name());
@@ -44,9 +48,10 @@ fn list() -> Result<()> {
             println!("        Active:    {}", part.is_active());
             println!("        Busy:      {}", part.is_busy());
             println!("        FS:        {:?}", part.fs_type_name());
-            println!("        Start:     {}", part.geom_start());
-            println!("        End:       {}", part.geom_end());
-            println!("        Length:    {}", part.geom_length());
+            let geom = part.geom();
+            println!("        Start:     {}", geom.start);
+            println!("        End:       {}", geom.end);
+            println!("        Length:    {}", geom.length);
         }
     }
 
@@ -0,0 +1,67 @@
+extern crate libparted;
+
+use libparted::*;
+use std::env;
+use std::process::exit;
+
+fn main() {
+    let path = match env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: mkimage <image_path> [<size>]");
+            exit(1);
+        }
+    };
+
+    let size = env::args()
+        .nth(2)
+        .map_or(Ok(64 * MIB), |size| parse_size(&size))
+        .unwrap_or_else(|why| {
+            eprintln!("invalid size: {}", why);
+            exit(1);
+        });
+
+    let dev = match Device::create_image(&path, Bytes(size), 512) {
+        Ok(dev) => dev,
+        Err(why) => {
+            eprintln!("unable to create image at {}: {}", path, why);
+            exit(1);
+        }
+    };
+
+    let mut disk = match Disk::new_with_partition_table(&dev, PartitionTableType::GPT) {
+        Ok(disk) => disk,
+        Err(why) => {
+            eprintln!("unable to create partition table: {}", why);
+            exit(1);
+        }
+    };
+
+    // Give the entire image to a single partition, leaving the label to reserve its metadata.
+    let largest = match disk.largest_free_region() {
+        Ok(Some(region)) => region.view(),
+        Ok(None) => {
+            eprintln!("no free space in {}", path);
+            exit(1);
+        }
+        Err(why) => {
+            eprintln!("unable to find free space in {}: {}", path, why);
+            exit(1);
+        }
+    };
+
+    let spec = PartitionSpec::with_sectors(largest.start, largest.end)
+        .fs_type("ext4")
+        .name("data");
+    if let Err(why) = disk.create_partition(&spec) {
+        eprintln!("unable to create partition: {}", why);
+        exit(1);
+    }
+
+    if let Err(why) = disk.commit_to_dev() {
+        eprintln!("unable to write partition table to {}: {}", path, why);
+        exit(1);
+    }
+
+    print!("{}", disk.dump());
+}
@@ -6,37 +6,30 @@ extern crate libparted;
 use libparted::*;
 use std::env;
 use std::io;
-use std::num::ParseIntError;
 use std::process::{exit, Command, Stdio};
 use std::str::{self, FromStr};
 
 enum Unit {
     Sectors(u64),
-    Mebibytes(u64),
-    Megabytes(u64),
+    Bytes(u64),
 }
 
 impl Unit {
-    pub fn into_sectors(self, sector_size: u64) -> u64 {
+    pub fn into_sectors(self, device: &Device) -> u64 {
         match self {
             Unit::Sectors(sectors) => sectors,
-            Unit::Megabytes(mb) => mb * 1000 * 1000 / sector_size,
-            Unit::Mebibytes(mib) => mib * 1024 * 1024 / sector_size,
+            Unit::Bytes(bytes) => device.bytes_to_sectors(Bytes(bytes), RoundMode::Down) as u64,
         }
     }
 }
 
 impl FromStr for Unit {
-    type Err = ParseIntError;
+    type Err = io::Error;
     fn from_str(string: &str) -> Result<Self, Self::Err> {
-        if let Some(stripped) = string.strip_suffix("MB") {
-            stripped.parse::<u64>().map(Unit::Megabytes)
-        } else if let Some(stripped) = string.strip_suffix("MiB") {
-            stripped.parse::<u64>().map(Unit::Mebibytes)
-        } else if let Some(stripped) = string.strip_suffix('M') {
-            stripped.parse::<u64>().map(Unit::Megabytes)
-        } else {
-            string.parse::<u64>().map(Unit::Sectors)
+        // A bare number is a count of sectors, rather than bytes.
+        match string.parse::<u64>() {
+            Ok(sectors) => Ok(Unit::Sectors(sectors)),
+            Err(_) => parse_size(string).map(Unit::Bytes),
         }
     }
 }
@@ -138,51 +131,34 @@ fn create_partition(
     let mut dev = Device::new(&device).map_err(|why| PartedError::OpenDevice { why })?;
 
     // Get the sector start / length of the new partition.
-    let sector_size = dev.sector_size();
-    let start = start.into_sectors(sector_size);
-    let length = length.into_sectors(sector_size);
-
-    let geometry = Geometry::new(&dev, start as i64, length as i64)
-        .map_err(|why| PartedError::CreateGeometry { why })?;
+    let start = start.into_sectors(&dev);
+    let length = length.into_sectors(&dev);
 
     // Create a new partition with the following file system type.
     let fs = fs.unwrap_or_else(|| "ext2".into());
-    let fs_type = match FileSystemType::get(&fs) {
-        Some(fs) => fs,
-        None => {
-            eprintln!("invalid fs provided: {}", fs);
-            exit(1);
-        }
-    };
-
-    {
-        let mut disk = Disk::new(&mut dev).map_err(|why| PartedError::CreateDisk { why })?;
-
-        let part_type = PartitionType::PED_PARTITION_NORMAL;
-
-        // Create a new partition from the disk, geometry, and the type.
-        let mut partition = Partition::new(
-            &disk,
-            part_type,
-            Some(&fs_type),
-            geometry.start(),
-            geometry.start() + geometry.length(),
-        )
-        .map_err(|why| PartedError::CreatePartition { why })?;
+    if FileSystemType::get(&fs).is_none() {
+        eprintln!("invalid fs provided: {}", fs);
+        exit(1);
+    }
 
-        let constraint = geometry.exact().unwrap();
+    let num = {
+        let mut disk = Disk::new(&dev).map_err(|why| PartedError::CreateDisk { why })?;
 
-        // Add the partition to the disk, and set the corresponding partition flag.
-        if let Err(why) = disk.add_partition(&mut partition, &constraint) {
-            return Err(PartedError::AddPartition { why });
-        }
+        // Create the partition, aligning its boundaries to the device, and add it to the disk.
+        let spec = PartitionSpec::with_sectors(start as i64, (start + length) as i64 - 1)
+            .fs_type(fs.as_str());
+        let num = disk
+            .create_partition(&spec)
+            .map_err(|why| PartedError::AddPartition { why })?;
 
         // Commit changes to the disk, and exit the function, which will clean up
         // the constructed objects from libparted automatically.
         if let Err(why) = disk.commit() {
             return Err(PartedError::CommitChanges { why });
         }
-    }
+
+        num
+    };
 
     if let Err(why) = dev.sync() {
         return Err(PartedError::SyncErr { why });
@@ -191,12 +167,10 @@ fn create_partition(
     let device_path = dev.path().to_path_buf();
 
     {
-        let disk = Disk::new(&mut dev).map_err(|why| PartedError::CreateDisk { why })?;
+        let disk = Disk::new(&dev).map_err(|why| PartedError::CreateDisk { why })?;
 
         {
-            let new_part = disk
-                .get_partition_by_sector(start as i64)
-                .ok_or(PartedError::FindPartition)?;
+            let new_part = disk.partition(num).ok_or(PartedError::FindPartition)?;
 
             let device_path = format!("{}{}", device_path.display(), new_part.num());
             eprintln!("mkpart: formatting '{}' with '{}'", device_path, fs);
@@ -206,12 +180,12 @@ fn create_partition(
 
     // Drop and re-open the device to obtain updated partition information.
     drop(dev);
-    let mut dev = Device::get(&device).map_err(|why| PartedError::OpenDevice { why })?;
-    let disk = Disk::new(&mut dev).map_err(|why| PartedError::CreateDisk { why })?;
+    let dev = Device::get(&device).map_err(|why| PartedError::OpenDevice { why })?;
+    let disk = Disk::new(&dev).map_err(|why| PartedError::CreateDisk { why })?;
 
     // Displays the new partition layout to the user.
     println!("New Partition Scheme:");
-    for (part_i, part) in disk.parts().enumerate() {
+    for (part_i, part) in disk.partitions().enumerate() {
         let name = part.type_get_name();
         if name == "metadata" || name == "free" {
             continue;
@@ -219,9 +193,10 @@ fn create_partition(
         println!("Part: {}", part_i);
         println!("    Path:   {:?}", part.get_path());
         println!("    FS:     {:?}", part.fs_type_name());
-        println!("    Start:  {}", part.geom_start());
-        println!("    End:    {}", part.geom_end());
-        println!("    Length: {}", part.geom_length());
+        let geom = part.geom();
+        println!("    Start:  {}", geom.start);
+        println!("    End:    {}", geom.end);
+        println!("    Length: {}", geom.length);
     }
 
     Ok(())
@@ -234,7 +209,7 @@ fn main() {
             eprintln!("mkpart error: {}", why);
             eprintln!("\tUsage: mkpart <device_path> <start_sector> <length_in_sectors> [<fs>]");
             eprintln!(
-                "\t       mkpart <device_path< <start_sector> <length_in_units>[M | MB] [<fs>]"
+                "\t       mkpart <device_path< <start_sector> <length>[MB | MiB | GiB ...] [<fs>]"
             );
             exit(1);
         }
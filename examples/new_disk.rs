@@ -9,8 +9,8 @@ fn create_and_append<'a>(
     ptype: PartitionType,
     ftype: Option<&FileSystemType>,
     constraint: &Constraint,
-    start: i64,
-    end: i64,
+    start: Sector,
+    end: Sector,
 ) -> Partition<'a> {
     let mut partition = match Partition::new(disk, ptype, ftype, start, end) {
         Ok(partition) => partition,
@@ -35,7 +35,7 @@ fn main() {
         exit(1);
     };
 
-    let mut dev = match Device::new(&device) {
+    let dev = match Device::new(&device) {
         Ok(dev) => dev,
         Err(why) => {
             eprintln!("unable to create device: {}", why);
@@ -44,7 +44,7 @@ fn main() {
     };
 
     // Creates a new partition table on the device while opening the disk.
-    let mut disk = match Disk::new_fresh(&mut dev, DiskType::get("msdos").unwrap()) {
+    let mut disk = match Disk::new_fresh(&dev, DiskType::get("msdos").unwrap()) {
         Ok(disk) => disk,
         Err(why) => {
             eprintln!("unable to create partiton table on device: {}", why);
@@ -59,8 +59,8 @@ fn main() {
         PartitionType::PED_PARTITION_EXTENDED,
         None,
         &constraint,
-        32,
-        29311,
+        Sector(32),
+        Sector(29311),
     );
 
     let _second_part = create_and_append(
@@ -68,8 +68,8 @@ fn main() {
         PartitionType::PED_PARTITION_LOGICAL,
         Some(&FileSystemType::get("ext2").unwrap()),
         &constraint,
-        19584,
-        29311,
+        Sector(19584),
+        Sector(29311),
     );
 
     let _third_part = create_and_append(
@@ -77,8 +77,8 @@ fn main() {
         PartitionType::PED_PARTITION_LOGICAL,
         Some(&FileSystemType::get("ext2").unwrap()),
         &constraint,
-        2048,
-        9727,
+        Sector(2048),
+        Sector(9727),
     );
 
     if let Err(why) = disk.commit() {
@@ -89,10 +89,8 @@ fn main() {
     let disk_dup = disk.duplicate().unwrap();
 
     // Checks if both partitions match
-    for (src_part, dup_part) in disk.parts().zip(disk_dup.parts()) {
-        let failed = src_part.geom_start() != dup_part.geom_start()
-            || src_part.geom_end() != dup_part.geom_end();
-        if failed {
+    for (src_part, dup_part) in disk.partitions().zip(disk_dup.partitions()) {
+        if src_part.geom() != dup_part.geom() {
             eprintln!("duplicated partition doesn't match");
             exit(1);
         }
@@ -11,7 +11,7 @@ fn main() {
         exit(1);
     }
 
-    let mut dev = match Device::get(&args[1]) {
+    let dev = match Device::get(&args[1]) {
         Ok(dev) => dev,
         Err(why) => {
             eprintln!("unable to get {} device: {}", args[1], why);
@@ -19,7 +19,7 @@ fn main() {
         }
     };
 
-    let disk = match Disk::new(&mut dev) {
+    let disk = match Disk::new(&dev) {
         Ok(disk) => disk,
         Err(why) => {
             eprintln!("unable to get {} disk: {}", args[1], why);
@@ -22,7 +22,7 @@ fn main() {
         }
     };
 
-    let geom = match Geometry::new(&dev, 0, dev.length() as i64) {
+    let geom = match Geometry::new(&dev, Sector(0), Sector(dev.length() as i64)) {
         Ok(geom) => geom,
         Err(why) => {
             eprintln!("cannot create geometry: {}", why);
@@ -24,7 +24,7 @@ fn main() {
 
     {
         // Open the disk to make our destructive changes.
-        let mut disk = match Disk::new(&mut device) {
+        let mut disk = match Disk::new(&device) {
             Ok(disk) => disk,
             Err(why) => {
                 eprintln!("rmpart: unable to open disk: {}", why);
@@ -35,12 +35,17 @@ fn main() {
         // Remove every partition ID specified.
         for arg in args {
             match arg.parse::<u32>().ok() {
-                Some(partition_id) => {
-                    if let Err(why) = disk.remove_partition_by_number(partition_id) {
+                Some(partition_id) => match disk.remove_partition_by_number_returning(partition_id)
+                {
+                    Ok(removed) => println!(
+                        "rmpart: removed partition {}; sectors {} to {} are now free",
+                        partition_id, removed.free.start, removed.free.end
+                    ),
+                    Err(why) => {
                         eprintln!("rmpart: unable to add partition to removal queue: {}", why);
                         continue;
                     }
-                }
+                },
                 None => eprintln!("rmpart: invalid partition id: {}", arg),
             }
         }
@@ -0,0 +1,119 @@
+use super::{Device, DiskType, PartitionTableType, SectorRange};
+use std::io::Result;
+
+/// The number of sectors an msdos label can address, as it stores sectors as 32-bit numbers.
+const MSDOS_ADDRESSABLE_SECTORS: u64 = 1 << 32;
+
+/// Why a label is unable to represent a layout, as reported by `label_advisor()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LabelIssue {
+    /// The layout has more partitions than the label supports.
+    TooManyPartitions { requested: u32, max: u32 },
+    /// The partition at `index` in the layout is longer than the label can represent.
+    PartitionTooLong { index: usize, max: i64 },
+    /// The partition at `index` in the layout starts beyond the last sector the label can
+    /// represent.
+    StartTooLarge { index: usize, max: i64 },
+    /// The device is larger than the label can address, so sectors beyond `last_usable`
+    /// cannot be partitioned.
+    DeviceTooLarge { last_usable: i64 },
+}
+
+/// Whether a label can represent a layout, as returned by `label_advisor()`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LabelAdvice {
+    /// The name of the label type, such as `gpt` or `msdos`.
+    pub label: String,
+    /// Each reason the label is unable to represent the layout.
+    pub issues: Vec<LabelIssue>,
+}
+
+impl LabelAdvice {
+    /// Whether the label can represent the layout, and address the whole device.
+    pub fn is_usable(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Determines whether each of the GPT and msdos labels can represent `layout` on `device`,
+/// and why not, before a label is created.
+///
+/// The msdos label stores sectors as 32-bit numbers, so it can address at most 2^32 sectors
+/// of the device's sector size: 2 TiB with 512-byte sectors, or 16 TiB with 4096-byte
+/// sectors. On larger devices, it cannot address the whole device, and partitions which end
+/// beyond its limit would otherwise be truncated.
+///
+/// ```rust,no_run
+/// # use libparted::{label_advisor, Device, SectorRange};
+/// let device = Device::new("/dev/sda").unwrap();
+/// let layout = [SectorRange::new(2048, 1050623), SectorRange::new(1050624, 7814035455)];
+/// for advice in label_advisor(&device, &layout).unwrap() {
+///     println!("{}: {:?}", advice.label, advice.issues);
+/// }
+/// ```
+pub fn label_advisor(device: &Device, layout: &[SectorRange]) -> Result<Vec<LabelAdvice>> {
+    let mut advice = Vec::new();
+    for table in &[PartitionTableType::GPT, PartitionTableType::MSDOS] {
+        let type_ = match DiskType::from_table_type(table.clone()) {
+            Some(type_) => type_,
+            None => continue,
+        };
+
+        let issues = type_.with_fresh_disk(device, |disk| {
+            let mut issues = Vec::new();
+            let max_count = disk.max_supported_partition_count().ok();
+            let max_length = disk.max_partition_length();
+            let max_start = disk.max_partition_start_sector();
+
+            if let Some(max) = max_count {
+                if layout.len() as u64 > u64::from(max) {
+                    issues.push(LabelIssue::TooManyPartitions {
+                        requested: layout.len() as u32,
+                        max,
+                    });
+                }
+            }
+
+            for (index, range) in layout.iter().enumerate() {
+                if range.start > max_start {
+                    issues.push(LabelIssue::StartTooLarge {
+                        index,
+                        max: max_start,
+                    });
+                }
+
+                if range.length() > max_length {
+                    issues.push(LabelIssue::PartitionTooLong {
+                        index,
+                        max: max_length,
+                    });
+                }
+            }
+
+            let sector_size = device.sector_size();
+            let device_bytes = device.length().saturating_mul(sector_size);
+            if let PartitionTableType::MSDOS = *table {
+                let addressable = MSDOS_ADDRESSABLE_SECTORS.saturating_mul(sector_size);
+                if device_bytes > addressable {
+                    issues.push(LabelIssue::DeviceTooLarge {
+                        last_usable: MSDOS_ADDRESSABLE_SECTORS as i64 - 1,
+                    });
+                }
+            } else {
+                let last_usable = max_start.saturating_add(max_length) - 1;
+                if last_usable < device.length() as i64 - 1 {
+                    issues.push(LabelIssue::DeviceTooLarge { last_usable });
+                }
+            }
+
+            issues
+        })?;
+
+        advice.push(LabelAdvice {
+            label: table.to_string(),
+            issues,
+        });
+    }
+
+    Ok(advice)
+}
@@ -4,10 +4,21 @@ use libparted_sys::{
     ped_alignment_is_aligned, ped_alignment_new, PedAlignment,
 };
 
-use super::{cvt, get_optional, Geometry};
+use super::{cvt, get_optional, Device, Geometry};
 use std::io;
 use std::marker::PhantomData;
 
+/// Which alignment of the device a partition's boundaries should respect.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AlignPolicy {
+    /// Only the requirements of the disk label.
+    None,
+    /// The minimum alignment the device requires.
+    Minimal,
+    /// The alignment which gives the device optimal performance.
+    Optimal,
+}
+
 pub struct Alignment<'a> {
     pub(crate) alignment: *mut PedAlignment,
     pub(crate) phantom: PhantomData<&'a PedAlignment>,
@@ -27,6 +38,26 @@ impl<'a> Alignment<'a> {
         cvt(unsafe { ped_alignment_new(offset, grain_size) }).map(Alignment::from_raw)
     }
 
+    /// Returns an alignment which every sector satisfies.
+    pub fn any() -> io::Result<Alignment<'a>> {
+        Alignment::new(0, 1)
+    }
+
+    /// Returns an alignment to the cylinder boundaries of the BIOS geometry of `device`, as
+    /// required for partitions on legacy msdos disks.
+    pub fn cylinder(device: &Device) -> io::Result<Alignment<'a>> {
+        let geom = device.bios_geom();
+        let grain_size = i64::from(geom.heads) * i64::from(geom.sectors);
+        if grain_size < 1 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "device does not report a BIOS geometry",
+            ));
+        }
+
+        Alignment::new(0, grain_size)
+    }
+
     /// Initializes a preallocated piece of memory for an alignment object.
     ///
     /// The object will represent all sectors for which the equation
@@ -71,6 +102,7 @@ impl<'a> Alignment<'a> {
         })
     }
 
+    /// The distance, in sectors, between each aligned sector.
     pub fn grain_size(&self) -> i64 {
         unsafe { (*self.alignment).grain_size }
     }
@@ -88,6 +120,7 @@ impl<'a> Alignment<'a> {
         unsafe { ped_alignment_is_aligned(self.alignment, geom.geometry, sector) == 1 }
     }
 
+    /// The offset, in sectors, of each aligned sector from a multiple of `grain_size()`.
     pub fn offset(&self) -> i64 {
         unsafe { (*self.alignment).offset }
     }
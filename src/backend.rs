@@ -0,0 +1,427 @@
+//! Sector-level I/O, abstracted over where the sectors are stored.
+//!
+//! The byte streams returned by `Geometry::reader()` and `Geometry::writer()`, and the
+//! imaging behind `Geometry::dump_to()` and friends, are written against **SectorIo** rather
+//! than libparted. **MemoryDevice** implements it over a buffer in memory, so that logic
+//! built on these may be exercised without libparted, a real device, or root privileges.
+//!
+//! ```rust
+//! # use libparted::{MemoryDevice, SectorReader, SectorWriter};
+//! # use std::io::{Read, Write};
+//! let mut device = MemoryDevice::new(2048, 512);
+//! SectorWriter::new(&mut device).write_all(b"hello").unwrap();
+//!
+//! let mut data = [0; 5];
+//! SectorReader::new(&device).read_exact(&mut data).unwrap();
+//! assert_eq!(&data, b"hello");
+//! ```
+
+use super::{Device, Geometry};
+use std::error::Error as StdError;
+use std::fmt::{self, Display, Formatter};
+use std::io;
+
+/// Reads and writes whole sectors of a device, or a region of one.
+///
+/// Offsets are in sectors, from the start of the device or region.
+pub trait SectorIo {
+    /// The size of each sector, in bytes.
+    fn sector_size(&self) -> u64;
+
+    /// The number of sectors which may be read and written.
+    fn length(&self) -> i64;
+
+    /// Reads `count` sectors, starting at `offset`, into the beginning of `buffer`, which
+    /// must be large enough to hold them.
+    fn read_sectors(&self, buffer: &mut [u8], offset: i64, count: i64) -> io::Result<()>;
+
+    /// Writes `count` sectors from `buffer`, starting at `offset`.
+    fn write_sectors(&mut self, buffer: &[u8], offset: i64, count: i64) -> io::Result<()>;
+
+    /// Flushes any writes which have been cached.
+    fn sync(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> SectorIo for Geometry<'a> {
+    fn sector_size(&self) -> u64 {
+        Geometry::sector_size(self)
+    }
+
+    fn length(&self) -> i64 {
+        Geometry::length(self)
+    }
+
+    fn read_sectors(&self, buffer: &mut [u8], offset: i64, count: i64) -> io::Result<()> {
+        self.read_into(buffer, offset, count)
+    }
+
+    fn write_sectors(&mut self, buffer: &[u8], offset: i64, count: i64) -> io::Result<()> {
+        self.write_to_sectors(buffer, offset, count)
+    }
+
+    fn sync(&mut self) -> io::Result<()> {
+        Geometry::sync(self)
+    }
+}
+
+impl<'a> SectorIo for Device<'a> {
+    fn sector_size(&self) -> u64 {
+        Device::sector_size(self)
+    }
+
+    fn length(&self) -> i64 {
+        Device::length(self) as i64
+    }
+
+    fn read_sectors(&self, buffer: &mut [u8], offset: i64, count: i64) -> io::Result<()> {
+        self.read_from_sectors(buffer, offset, count)
+    }
+
+    fn write_sectors(&mut self, buffer: &[u8], offset: i64, count: i64) -> io::Result<()> {
+        self.write_to_sectors(buffer, offset, count)
+    }
+
+    fn sync(&mut self) -> io::Result<()> {
+        Device::sync(self)
+    }
+}
+
+/// The error of a sector read or write whose buffer does not match the number of sectors
+/// requested, given the logical sector size of the device.
+///
+/// It is the inner error of the `io::Error` returned by the read or write. A buffer to read
+/// into must hold every sector requested, while a buffer to write must not be larger than the
+/// sectors it is written to. Where sectors are transferred with direct I/O, the address of
+/// the buffer must also be a multiple of the sector size, and a buffer to write must fill
+/// every sector.
+///
+/// ```rust
+/// # use libparted::{BufferSizeError, MemoryDevice, SectorIo};
+/// let device = MemoryDevice::new(16, 4096);
+/// let mut buffer = vec![0; 512];
+/// let why = device.read_sectors(&mut buffer, 0, 1).unwrap_err();
+///
+/// let inner = why.get_ref().and_then(|inner| inner.downcast_ref::<BufferSizeError>());
+/// assert_eq!(inner.map(|inner| inner.sector_size), Some(4096));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BufferSizeError {
+    /// The length of the buffer, in bytes.
+    pub len: usize,
+    /// The number of sectors requested.
+    pub sectors: i64,
+    /// The logical sector size of the device, in bytes.
+    pub sector_size: u64,
+    /// Whether the address of the buffer is not a multiple of the sector size, as direct I/O
+    /// requires.
+    pub misaligned: bool,
+}
+
+impl BufferSizeError {
+    /// Checks that a buffer of `len` bytes can hold `sectors` sectors.
+    pub(crate) fn check_read(len: usize, sectors: i64, sector_size: u64) -> io::Result<()> {
+        BufferSizeError::check(len, sectors, sector_size, false, |len, total| len >= total)
+    }
+
+    /// Checks that a buffer of `len` bytes fits within `sectors` sectors.
+    pub(crate) fn check_write(len: usize, sectors: i64, sector_size: u64) -> io::Result<()> {
+        BufferSizeError::check(len, sectors, sector_size, false, |len, total| len <= total)
+    }
+
+    /// Checks that `buffer` can hold `sectors` sectors, and may be read into directly.
+    pub(crate) fn check_direct_read(
+        buffer: &[u8],
+        sectors: i64,
+        sector_size: u64,
+    ) -> io::Result<()> {
+        let misaligned = is_misaligned(buffer, sector_size);
+        BufferSizeError::check(
+            buffer.len(),
+            sectors,
+            sector_size,
+            misaligned,
+            |len, total| len >= total,
+        )
+    }
+
+    /// Checks that `buffer` fills exactly `sectors` sectors, and may be written directly.
+    pub(crate) fn check_direct_write(
+        buffer: &[u8],
+        sectors: i64,
+        sector_size: u64,
+    ) -> io::Result<()> {
+        let misaligned = is_misaligned(buffer, sector_size);
+        BufferSizeError::check(
+            buffer.len(),
+            sectors,
+            sector_size,
+            misaligned,
+            |len, total| len == total,
+        )
+    }
+
+    fn check<F>(
+        len: usize,
+        sectors: i64,
+        sector_size: u64,
+        misaligned: bool,
+        fits: F,
+    ) -> io::Result<()>
+    where
+        F: Fn(u64, u64) -> bool,
+    {
+        if !misaligned && sectors >= 0 && fits(len as u64, sectors as u64 * sector_size) {
+            return Ok(());
+        }
+
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            BufferSizeError {
+                len,
+                sectors,
+                sector_size,
+                misaligned,
+            },
+        ))
+    }
+}
+
+/// Whether the address of `buffer` is not a multiple of `sector_size`.
+fn is_misaligned(buffer: &[u8], sector_size: u64) -> bool {
+    sector_size == 0 || buffer.as_ptr() as u64 % sector_size != 0
+}
+
+impl Display for BufferSizeError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if self.misaligned {
+            return write!(
+                f,
+                "a buffer not aligned to {} bytes cannot be used for direct I/O",
+                self.sector_size
+            );
+        }
+
+        write!(
+            f,
+            "a buffer of {} bytes does not match {} sectors of {} bytes",
+            self.len, self.sectors, self.sector_size
+        )
+    }
+}
+
+impl StdError for BufferSizeError {}
+
+/// A device whose sectors are held in memory, for testing code written against **SectorIo**.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MemoryDevice {
+    data: Vec<u8>,
+    sector_size: u64,
+    direct: bool,
+}
+
+impl MemoryDevice {
+    /// Creates a zeroed device of `sectors` sectors of `sector_size` bytes each.
+    pub fn new(sectors: u64, sector_size: u64) -> MemoryDevice {
+        MemoryDevice {
+            data: vec![0; (sectors * sector_size) as usize],
+            sector_size,
+            direct: false,
+        }
+    }
+
+    /// Creates a device holding `data`, which must be a whole number of sectors.
+    pub fn from_bytes(data: Vec<u8>, sector_size: u64) -> io::Result<MemoryDevice> {
+        if sector_size == 0 || data.len() as u64 % sector_size != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("data is not a multiple of {} bytes", sector_size),
+            ));
+        }
+
+        Ok(MemoryDevice {
+            data,
+            sector_size,
+            direct: false,
+        })
+    }
+
+    /// Refuses buffers which could not be used for direct I/O, as with a device opened with
+    /// `O_DIRECT`: their address must be a multiple of the sector size, and buffers which are
+    /// written must fill every sector.
+    pub fn with_direct_io(mut self) -> MemoryDevice {
+        self.direct = true;
+        self
+    }
+
+    /// The contents of the device.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Consumes the device, returning its contents.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// The range of bytes covered by `count` sectors at `offset`, if they are all within the
+    /// device.
+    fn span(&self, offset: i64, count: i64) -> io::Result<(usize, usize)> {
+        if offset < 0 || count < 0 || offset + count > self.length() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "sectors {}..{} are not within the device",
+                    offset,
+                    offset + count
+                ),
+            ));
+        }
+
+        let sector_size = self.sector_size as usize;
+        Ok((
+            offset as usize * sector_size,
+            (offset + count) as usize * sector_size,
+        ))
+    }
+}
+
+impl SectorIo for MemoryDevice {
+    fn sector_size(&self) -> u64 {
+        self.sector_size
+    }
+
+    fn length(&self) -> i64 {
+        (self.data.len() as u64 / self.sector_size) as i64
+    }
+
+    fn read_sectors(&self, buffer: &mut [u8], offset: i64, count: i64) -> io::Result<()> {
+        if self.direct {
+            BufferSizeError::check_direct_read(buffer, count, self.sector_size)?;
+        } else {
+            BufferSizeError::check_read(buffer.len(), count, self.sector_size)?;
+        }
+        let (start, end) = self.span(offset, count)?;
+
+        buffer[..end - start].copy_from_slice(&self.data[start..end]);
+        Ok(())
+    }
+
+    /// As with libparted, a buffer which does not fill every sector leaves the remainder of
+    /// the sectors filled with ASCII zeros.
+    fn write_sectors(&mut self, buffer: &[u8], offset: i64, count: i64) -> io::Result<()> {
+        if self.direct {
+            BufferSizeError::check_direct_write(buffer, count, self.sector_size)?;
+        } else {
+            BufferSizeError::check_write(buffer.len(), count, self.sector_size)?;
+        }
+        let (start, end) = self.span(offset, count)?;
+        let written = buffer.len();
+        self.data[start..start + written].copy_from_slice(&buffer[..written]);
+        for byte in &mut self.data[start + written..end] {
+            *byte = b'0';
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_writes_fill_the_sector_with_ascii_zeros() {
+        let mut device = MemoryDevice::new(2, 512);
+        device.write_sectors(b"abc", 1, 1).unwrap();
+
+        let mut sector = vec![0; 512];
+        device.read_sectors(&mut sector, 1, 1).unwrap();
+        assert_eq!(&sector[..3], b"abc");
+        assert!(sector[3..].iter().all(|&byte| byte == b'0'));
+        assert!(device.as_bytes()[..512].iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn refuses_sectors_beyond_the_device() {
+        let mut device = MemoryDevice::new(2, 512);
+        let mut buffer = vec![0; 1024];
+        assert!(device.read_sectors(&mut buffer, 1, 2).is_err());
+        assert!(device.write_sectors(&buffer, 2, 1).is_err());
+        assert!(device.read_sectors(&mut buffer, -1, 1).is_err());
+    }
+
+    #[test]
+    fn checks_buffer_sizes() {
+        let mut device = MemoryDevice::new(2, 512);
+        let size_error = |why: io::Error| {
+            why.get_ref()
+                .and_then(|inner| inner.downcast_ref::<BufferSizeError>())
+                .cloned()
+        };
+
+        let mut small = vec![0; 511];
+        let why = device.read_sectors(&mut small, 0, 1).unwrap_err();
+        assert_eq!(
+            size_error(why),
+            Some(BufferSizeError {
+                len: 511,
+                sectors: 1,
+                sector_size: 512,
+                misaligned: false,
+            })
+        );
+
+        let large = vec![0; 513];
+        assert!(size_error(device.write_sectors(&large, 0, 1).unwrap_err()).is_some());
+    }
+
+    /// The offset into `buffer` of its first byte whose address is a multiple of `align`.
+    fn aligned_offset(buffer: &[u8], align: usize) -> usize {
+        (align - buffer.as_ptr() as usize % align) % align
+    }
+
+    #[test]
+    fn direct_io_requires_aligned_buffers() {
+        let mut device = MemoryDevice::new(4, 4096).with_direct_io();
+        let mut buffer = vec![0x11; 4 * 4096];
+        let offset = aligned_offset(&buffer, 4096);
+
+        let why = device
+            .write_sectors(&buffer[offset + 512..offset + 512 + 4096], 0, 1)
+            .unwrap_err();
+        let inner = why
+            .get_ref()
+            .and_then(|inner| inner.downcast_ref::<BufferSizeError>())
+            .cloned();
+        assert_eq!(inner.map(|inner| inner.misaligned), Some(true));
+
+        assert!(device
+            .write_sectors(&buffer[offset..offset + 2048], 0, 1)
+            .is_err());
+        device
+            .write_sectors(&buffer[offset..offset + 4096], 2, 1)
+            .unwrap();
+
+        let aligned = &mut buffer[offset..offset + 2 * 4096];
+        for byte in aligned.iter_mut() {
+            *byte = 0;
+        }
+        device.read_sectors(aligned, 1, 2).unwrap();
+        assert!(aligned[..4096].iter().all(|&byte| byte == 0));
+        assert!(aligned[4096..].iter().all(|&byte| byte == 0x11));
+    }
+
+    #[test]
+    fn from_bytes_requires_whole_sectors() {
+        assert!(MemoryDevice::from_bytes(vec![0; 1000], 512).is_err());
+        assert!(MemoryDevice::from_bytes(vec![0; 1024], 0).is_err());
+        assert_eq!(
+            MemoryDevice::from_bytes(vec![0; 1024], 512)
+                .unwrap()
+                .length(),
+            2
+        );
+    }
+}
@@ -0,0 +1,30 @@
+use super::PartitionSpec;
+use std::fmt::{self, Display, Formatter};
+
+/// A change to a partition table, to be applied with `Disk::apply_batch()`.
+#[derive(Clone, Debug)]
+pub enum DiskOp {
+    /// Creates a partition, as with `Disk::create_partition()`.
+    Create(PartitionSpec),
+    /// Removes the partition with the given number, as with
+    /// `Disk::remove_partition_by_number_returning()`.
+    Remove(u32),
+    /// Moves or resizes partition `num` to occupy exactly the sectors from `start` to `end`.
+    ///
+    /// Only the partition table is changed; any file system on the partition is left as it is.
+    Resize { num: u32, start: i64, end: i64 },
+}
+
+impl Display for DiskOp {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            DiskOp::Create(ref spec) => write!(f, "create partition at {:?}", spec.extent),
+            DiskOp::Remove(num) => write!(f, "remove partition {}", num),
+            DiskOp::Resize { num, start, end } => write!(
+                f,
+                "resize partition {} to sectors {} to {}",
+                num, start, end
+            ),
+        }
+    }
+}
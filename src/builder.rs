@@ -0,0 +1,182 @@
+use super::{
+    Bytes, Device, Disk, DiskType, DiskTypeFeature, PartitionFlag, PartitionSpec,
+    PartitionTableType, RoundMode,
+};
+use std::io::{Error, ErrorKind, Result};
+
+struct Planned {
+    /// The size in bytes, or `None` to fill the space left by the other partitions.
+    size: Option<u64>,
+    fs_type: Option<String>,
+    name: Option<String>,
+    flags: Vec<PartitionFlag>,
+}
+
+/// Creates a fresh partition table with a standard layout in one chained call.
+///
+/// Partitions are laid out in the order they are added, from the start of the disk, with
+/// each boundary snapped to the optimal alignment of the device. When there are more
+/// partitions than the label allows primary partitions (ie: more than four on msdos), the
+/// layout is chained with `Disk::chain_logical()`, so that the last primary partition is an
+/// extended partition covering the rest of the disk, and the remaining partitions are created
+/// as logical partitions within it. Each logical partition then gives up one alignment grain
+/// at its start to the extended boot record before it.
+///
+/// Partition names are only applied on labels which support them (ie: GPT), and are otherwise
+/// left out, so that the same layout may be built on an msdos label.
+///
+/// ```rust,no_run
+/// # use libparted::{Device, DiskBuilder, GIB, MIB};
+/// let mut device = Device::new("/dev/sda").unwrap();
+/// let disk = DiskBuilder::new(&mut device)
+///     .label("gpt")
+///     .esp(512 * MIB)
+///     .partition_fill("ext4", "root")
+///     .swap(4 * GIB)
+///     .build_and_commit()
+///     .unwrap();
+/// ```
+pub struct DiskBuilder<'a, 'b> {
+    device: &'a mut Device<'b>,
+    label: String,
+    parts: Vec<Planned>,
+}
+
+impl<'a, 'b> DiskBuilder<'a, 'b> {
+    /// Begins a layout for `device`, with a GPT label.
+    pub fn new(device: &'a mut Device<'b>) -> DiskBuilder<'a, 'b> {
+        DiskBuilder {
+            device,
+            label: "gpt".into(),
+            parts: Vec::new(),
+        }
+    }
+
+    /// Sets the type of label to create, such as `gpt` or `msdos`.
+    pub fn label<S: Into<String>>(mut self, label: S) -> DiskBuilder<'a, 'b> {
+        self.label = label.into();
+        self
+    }
+
+    /// Adds an EFI system partition of `size` bytes, formatted as FAT32.
+    pub fn esp(mut self, size: u64) -> DiskBuilder<'a, 'b> {
+        self.parts.push(Planned {
+            size: Some(size),
+            fs_type: Some("fat32".into()),
+            name: Some("EFI System Partition".into()),
+            flags: vec![PartitionFlag::PED_PARTITION_ESP],
+        });
+        self
+    }
+
+    /// Adds a swap partition of `size` bytes.
+    pub fn swap(mut self, size: u64) -> DiskBuilder<'a, 'b> {
+        self.parts.push(Planned {
+            size: Some(size),
+            fs_type: Some("linux-swap(v1)".into()),
+            name: Some("swap".into()),
+            flags: Vec::new(),
+        });
+        self
+    }
+
+    /// Adds a partition of `size` bytes, with the given file system type and name.
+    pub fn partition<F, N>(mut self, size: u64, fs_type: F, name: N) -> DiskBuilder<'a, 'b>
+    where
+        F: Into<String>,
+        N: Into<String>,
+    {
+        self.parts.push(Planned {
+            size: Some(size),
+            fs_type: Some(fs_type.into()),
+            name: Some(name.into()),
+            flags: Vec::new(),
+        });
+        self
+    }
+
+    /// Adds a partition which occupies all of the space left by the other partitions.
+    ///
+    /// Only one such partition may be added.
+    pub fn partition_fill<F, N>(mut self, fs_type: F, name: N) -> DiskBuilder<'a, 'b>
+    where
+        F: Into<String>,
+        N: Into<String>,
+    {
+        self.parts.push(Planned {
+            size: None,
+            fs_type: Some(fs_type.into()),
+            name: Some(name.into()),
+            flags: Vec::new(),
+        });
+        self
+    }
+
+    /// Creates the partition table and its partitions in-memory, without writing anything to
+    /// the device.
+    pub fn build(self) -> Result<Disk<'a>> {
+        if self.parts.iter().filter(|part| part.size.is_none()).count() > 1 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "only one partition may fill the remaining space",
+            ));
+        }
+
+        let sectors = |bytes: u64| self.device.bytes_to_sectors(Bytes(bytes), RoundMode::Up);
+        let sizes = self
+            .parts
+            .iter()
+            .map(|part| part.size.map(sectors))
+            .collect::<Vec<_>>();
+
+        let type_ = DiskType::from_table_type(PartitionTableType::Other(self.label.clone()))
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::NotFound,
+                    format!("unknown disk label type: {}", self.label),
+                )
+            })?;
+
+        let mut disk = Disk::new_fresh(self.device, type_)?;
+        let region = disk
+            .largest_free_region()?
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, "no free space on the device"))?
+            .view();
+
+        let has_names = disk.supports(DiskTypeFeature::PED_DISK_TYPE_PARTITION_NAME);
+        let fixed = sizes.iter().filter_map(|&size| size).sum::<i64>();
+        let mut next = region.start;
+        let mut layout = Vec::with_capacity(self.parts.len());
+        for (part, size) in self.parts.iter().zip(sizes) {
+            let length = size.unwrap_or(region.length - fixed);
+            if length < 1 || next + length - 1 > region.end {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "the partitions do not fit on the device",
+                ));
+            }
+
+            let mut spec = PartitionSpec::with_sectors(next, next + length - 1);
+            spec.fs_type = part.fs_type.clone();
+            if has_names {
+                spec.name = part.name.clone();
+            }
+            spec.flags = part.flags.clone();
+            layout.push(spec);
+            next += length;
+        }
+
+        for spec in disk.chain_logical(&layout)? {
+            disk.create_partition(&spec)?;
+        }
+
+        Ok(disk)
+    }
+
+    /// Creates the partition table and its partitions, and writes them to the device.
+    pub fn build_and_commit(self) -> Result<Disk<'a>> {
+        let mut disk = self.build()?;
+        disk.commit()?;
+        Ok(disk)
+    }
+}
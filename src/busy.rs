@@ -0,0 +1,50 @@
+#[cfg(feature = "proc")]
+use super::mounts;
+#[cfg(feature = "proc")]
+use std::path::PathBuf;
+
+/// Why a partition reported by `Disk::busy_partitions()` is in use.
+#[cfg(feature = "proc")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BusyReason {
+    /// The partition is mounted.
+    Mounted,
+    /// The partition is in use as swap.
+    Swap,
+    /// The partition is held by something else, such as device-mapper, LVM, or RAID.
+    Held,
+}
+
+/// A partition which is in use, and which the kernel will refuse to update.
+#[cfg(feature = "proc")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BusyPartition {
+    pub num: u32,
+    pub path: Option<PathBuf>,
+    pub reason: BusyReason,
+}
+
+#[cfg(feature = "proc")]
+impl BusyPartition {
+    pub(crate) fn new(num: u32, path: Option<PathBuf>) -> BusyPartition {
+        let reason = match path {
+            Some(ref path) if mounts::is_swap(path) => BusyReason::Swap,
+            Some(ref path) if mounts::is_mounted(path) => BusyReason::Mounted,
+            _ => BusyReason::Held,
+        };
+
+        BusyPartition { num, path, reason }
+    }
+}
+
+/// Parses the partition numbers from libparted's warning that the kernel could not be
+/// informed of changes to some partitions: "Partition(s) 1, 2 on /dev/sda have been
+/// written, but we have been unable to inform the kernel of the change...".
+///
+/// Only the untranslated English message is recognised, so `None` is returned when libparted
+/// has translated it for the current locale.
+pub(crate) fn refused_partitions(message: &str) -> Option<Vec<u32>> {
+    let list = message.strip_prefix("Partition(s) ")?;
+    let list = &list[..list.find(" on ")?];
+    list.split(',').map(|num| num.trim().parse().ok()).collect()
+}
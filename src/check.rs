@@ -0,0 +1,69 @@
+use super::{Exception, ExceptionType};
+
+/// What kind of inconsistency a **DiskCheckIssue** describes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DiskCheckIssueKind {
+    /// Two partitions occupy some of the same sectors.
+    Overlap,
+    /// A partition extends beyond the end of the device.
+    OutsideDisk,
+    /// A partition does not begin or end on a boundary required by the label.
+    Misaligned,
+    /// The size of a partition does not match the size of the file system within it.
+    FileSystemMismatch,
+    /// Any other problem reported by libparted.
+    Other,
+}
+
+/// An inconsistency found by `Disk::check_issues()`.
+#[derive(Clone, Debug)]
+pub struct DiskCheckIssue {
+    pub kind: DiskCheckIssueKind,
+    /// Whether libparted considered this a warning or an error.
+    pub severity: ExceptionType,
+    /// The partition the issue concerns, if the message names one.
+    pub partition: Option<u32>,
+    /// The message as reported by libparted.
+    pub message: String,
+}
+
+impl DiskCheckIssue {
+    /// Classifies an exception raised by libparted during a check.
+    ///
+    /// # Note:
+    ///
+    /// libparted only describes problems through its messages, so classification relies on
+    /// the untranslated English text, and issues reported in another locale will be `Other`.
+    pub(crate) fn from_exception(exception: &Exception) -> DiskCheckIssue {
+        let message = exception.message.to_lowercase();
+        let kind = if message.contains("overlap") {
+            DiskCheckIssueKind::Overlap
+        } else if message.contains("outside") || message.contains("beyond the end") {
+            DiskCheckIssueKind::OutsideDisk
+        } else if message.contains("align") || message.contains("cylinder boundary") {
+            DiskCheckIssueKind::Misaligned
+        } else if message.contains("file system") {
+            DiskCheckIssueKind::FileSystemMismatch
+        } else {
+            DiskCheckIssueKind::Other
+        };
+
+        DiskCheckIssue {
+            kind,
+            severity: exception.type_,
+            partition: partition_number(&message),
+            message: exception.message.clone(),
+        }
+    }
+}
+
+/// Finds the number following the first occurrence of "partition" in `message`.
+fn partition_number(message: &str) -> Option<u32> {
+    let rest = &message[message.find("partition")? + "partition".len()..];
+    let digits = rest
+        .trim_start()
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect::<String>();
+    digits.parse().ok()
+}
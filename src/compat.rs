@@ -0,0 +1,63 @@
+//! Migration guide from the legacy APIs to their redesigned replacements.
+//!
+//! # Stability Tiers
+//!
+//! - **Stable**: The default API surface. Items are never removed or changed in a breaking way
+//!   without first being deprecated behind the `v2-api` feature for at least one release.
+//! - **v2**: Enabling the `v2-api` feature opts in to the redesigned ownership model. The new
+//!   APIs are always available; the feature only marks the legacy items they replace as
+//!   `#[deprecated]`, so the compiler points out every call site which needs migrating. Once
+//!   a crate builds without deprecation warnings under `v2-api`, it is ready for the next
+//!   major release.
+//!
+//! The legacy items remain as shims which delegate to the new implementations, so both styles
+//! may be mixed while migrating incrementally.
+//!
+//! # Migration Table
+//!
+//! | Legacy | Replacement |
+//! | ------ | ----------- |
+//! | `Partition::geom_start()` | `Partition::geom().start` |
+//! | `Partition::geom_end()` | `Partition::geom().end` |
+//! | `Partition::geom_length()` | `Partition::geom().length` |
+//! | `Disk::get_device()` | `Disk::device()`, or `Disk::handle()` to keep the device open |
+//! | `Disk::parts()` | `Disk::partitions()` |
+//! | `Disk::get_partition()` | `Disk::partition()` |
+//! | `Disk::get_partition_by_sector()` | `Disk::partition_by_sector()` |
+//! | `Disk::get_last_partition_num()` | `Disk::last_partition_num()` |
+//! | `Disk::get_max_supported_partition_count()` | `Disk::max_supported_partition_count()` |
+//! | `Geometry::dev()` | `Geometry::device()`, or `Geometry::handle()` to keep the device open |
+//! | `Geometry::dev_mut()` | `Geometry::device()`, or `Geometry::write_to_sectors()` for writing |
+//! | `Device::check()` | `Device::check_range()` |
+//! | `Geometry::read()` | `Geometry::read_to_vec()`, or `Geometry::read_into()` to reuse a buffer |
+//! | `Disk::remove_partition()` | `Disk::remove_partition_returning()` |
+//! | `Disk::remove_partition_by_number()` | `Disk::remove_partition_by_number_returning()` |
+//! | `Disk::remove_partition_by_sector()` | `Disk::remove_partition_by_sector_returning()` |
+//!
+//! # Sector Arguments
+//!
+//! APIs which took a sector as an `i64`, such as `Geometry::new()`, now take any
+//! `S: Into<Sector>`, and still accept an `i64` whether or not the `int-units` feature is
+//! enabled. Integer literals keep compiling, as `i64` is the only integer type which converts
+//! into a **Sector**, so the compiler infers it from the bound.
+//!
+//! As the parameter is generic, it no longer determines the type of an argument which is
+//! itself inferred, such as `input.parse().unwrap()` or `Default::default()`, which then
+//! fails to compile. Name the type, as in `input.parse::<i64>()`, or construct a `Sector`.
+//!
+//! # Partition Specs
+//!
+//! `PartitionSpec` has a private field for its type GUID, so it can no longer be built with a
+//! struct literal. Start from `PartitionSpec::new()`, `PartitionSpec::with_sectors()`, or
+//! `PartitionSpec::with_percent()`, and set the type GUID with `PartitionSpec::type_guid()`.
+//! The remaining fields are still public, and may be read or assigned directly.
+
+/// The major version of the public API selected at compile time: `2` when the `v2-api`
+/// feature is enabled, and `1` otherwise.
+#[cfg(feature = "v2-api")]
+pub const API_VERSION: u32 = 2;
+
+/// The major version of the public API selected at compile time: `2` when the `v2-api`
+/// feature is enabled, and `1` otherwise.
+#[cfg(not(feature = "v2-api"))]
+pub const API_VERSION: u32 = 1;
@@ -1,4 +1,4 @@
-use super::{cvt, get_optional, Alignment, Geometry};
+use super::{cvt, get_optional, Alignment, Geometry, Sector};
 use std::io;
 use std::marker::PhantomData;
 
@@ -32,14 +32,19 @@ impl<'a> Constraint<'a> {
     /// A convenience wrapper for `Constraint::init()`.
     ///
     /// Allocates a new piece of memory and initializes the constraint.
-    pub fn new(
+    pub fn new<S, M>(
         start_align: &Alignment,
         end_align: &Alignment,
         start_range: &Geometry,
         end_range: &Geometry,
-        min_size: i64,
-        max_size: i64,
-    ) -> io::Result<Constraint<'a>> {
+        min_size: S,
+        max_size: M,
+    ) -> io::Result<Constraint<'a>>
+    where
+        S: Into<Sector>,
+        M: Into<Sector>,
+    {
+        let (min_size, max_size) = (min_size.into().0, max_size.into().0);
         cvt(unsafe {
             ped_constraint_new(
                 start_align.alignment,
@@ -73,15 +78,20 @@ impl<'a> Constraint<'a> {
 
     /// Initializes a pre-allocated piece of memory to contain a constraint with the supplied
     /// default values.
-    pub fn init(
+    pub fn init<S, M>(
         &mut self,
         start_align: &Alignment,
         end_align: &Alignment,
         start_range: &Geometry,
         end_range: &Geometry,
-        min_size: i64,
-        max_size: i64,
-    ) -> io::Result<()> {
+        min_size: S,
+        max_size: M,
+    ) -> io::Result<()>
+    where
+        S: Into<Sector>,
+        M: Into<Sector>,
+    {
+        let (min_size, max_size) = (min_size.into().0, max_size.into().0);
         cvt(unsafe {
             ped_constraint_init(
                 self.constraint,
@@ -0,0 +1,139 @@
+//! Copying of sectors in large chunks, used to move and clone partitions.
+
+use super::{Device, Geometry, Timer};
+use std::io;
+
+/// The number of bytes transferred by each read and write.
+pub(crate) const CHUNK_SIZE: i64 = 1024 * 1024;
+
+/// Copies `count` sectors on `device` from `from` to `to`.
+///
+/// The source and destination may overlap: when moving data towards the end of the device,
+/// chunks are copied starting from the end of the region, so that no sector is overwritten
+/// before it has been read.
+pub(crate) fn move_sectors(
+    device: &mut Device,
+    from: i64,
+    to: i64,
+    count: i64,
+    mut timer: Option<&mut Timer>,
+) -> io::Result<()> {
+    if let Some(timer) = timer.as_mut() {
+        timer.reset();
+    }
+
+    if from != to {
+        let sector_size = device.sector_size() as i64;
+        let chunk = (CHUNK_SIZE / sector_size).max(1);
+        let mut buffer = vec![0u8; (chunk * sector_size) as usize];
+        let backwards = to > from;
+
+        let mut copied = 0;
+        while copied < count {
+            let length = chunk.min(count - copied);
+            let offset = if backwards {
+                count - copied - length
+            } else {
+                copied
+            };
+
+            let buffer = &mut buffer[..(length * sector_size) as usize];
+            device.read_from_sectors(buffer, from + offset, length)?;
+            device.write_to_sectors(buffer, to + offset, length)?;
+
+            copied += length;
+            if let Some(timer) = timer.as_mut() {
+                timer.update(copied as f32 / count as f32);
+            }
+        }
+
+        device.sync()?;
+    }
+
+    if let Some(timer) = timer {
+        timer.update(1.0);
+    }
+
+    Ok(())
+}
+
+/// Copies the contents of `src` to the start of `dst`, which may be on a different device.
+///
+/// Data is streamed in large chunks, and the devices may have different sector sizes. If the
+/// last sector written to `dst` is only partially filled by `src`, the remainder of that
+/// sector is zeroed. Returns the number of bytes copied. If `timer` is not `None`, it will be
+/// used as the progress meter.
+///
+/// `dst` must be at least as large as `src`, in bytes. Overlapping regions on the same device
+/// are not supported; use `Disk::move_partition()` instead.
+pub fn copy_partition(
+    src: &Geometry,
+    dst: &mut Geometry,
+    timer: Option<&mut Timer>,
+) -> io::Result<u64> {
+    copy_partition_throttled(src, dst, timer, |_| ())
+}
+
+/// Similar to `copy_partition()`, but calls `throttle` after each chunk has been written,
+/// with the total number of bytes copied so far.
+///
+/// The hook may sleep to limit the throughput of the copy, or simply report progress.
+pub fn copy_partition_throttled<F: FnMut(u64)>(
+    src: &Geometry,
+    dst: &mut Geometry,
+    mut timer: Option<&mut Timer>,
+    mut throttle: F,
+) -> io::Result<u64> {
+    let src_sector = src.sector_size() as i64;
+    let dst_sector = dst.sector_size() as i64;
+    let total = src.length() * src_sector;
+    if dst.length() * dst_sector < total {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "the destination is smaller than the source",
+        ));
+    }
+
+    if let Some(timer) = timer.as_mut() {
+        timer.reset();
+    }
+
+    // Each chunk must hold a whole number of sectors on both devices.
+    let unit = lcm(src_sector, dst_sector);
+    let chunk = (CHUNK_SIZE / unit).max(1) * unit;
+    let mut buffer = vec![0u8; chunk as usize];
+
+    let mut copied = 0;
+    while copied < total {
+        let length = chunk.min(total - copied);
+        let src_count = length / src_sector;
+        let dst_count = (length + dst_sector - 1) / dst_sector;
+
+        let buffer = &mut buffer[..(dst_count * dst_sector) as usize];
+        src.read_into(buffer, copied / src_sector, src_count)?;
+        for byte in &mut buffer[length as usize..] {
+            *byte = 0;
+        }
+        dst.write_to_sectors(buffer, copied / dst_sector, dst_count)?;
+
+        copied += length;
+        if let Some(timer) = timer.as_mut() {
+            timer.update(copied as f32 / total as f32);
+        }
+        throttle(copied as u64);
+    }
+
+    dst.sync()?;
+    Ok(copied as u64)
+}
+
+fn lcm(a: i64, b: i64) -> i64 {
+    let (mut x, mut y) = (a, b);
+    while y != 0 {
+        let t = x % y;
+        x = y;
+        y = t;
+    }
+
+    a / x * b
+}
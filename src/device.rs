@@ -1,26 +1,75 @@
 use std::ffi::{CStr, CString, OsStr};
+use std::fs::{self, OpenOptions};
 use std::io::{Error, ErrorKind, Result};
 use std::marker::PhantomData;
+use std::mem;
+use std::ops::Deref;
 use std::os::raw::c_void;
 use std::os::unix::ffi::OsStrExt;
-use std::path::Path;
+use std::os::unix::fs::{FileTypeExt, MetadataExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
 use std::ptr;
 use std::str;
 
 use libparted_sys::{
-    ped_constraint_any, ped_device_begin_external_access, ped_device_check, ped_device_close,
-    ped_device_end_external_access, ped_device_get, ped_device_get_constraint,
+    ped_constraint_any, ped_device_begin_external_access, ped_device_cache_remove,
+    ped_device_check, ped_device_close, ped_device_destroy, ped_device_end_external_access,
+    ped_device_free_all, ped_device_get, ped_device_get_constraint,
     ped_device_get_minimal_aligned_constraint, ped_device_get_minimum_alignment,
     ped_device_get_next, ped_device_get_optimal_aligned_constraint,
     ped_device_get_optimum_alignment, ped_device_is_busy, ped_device_open, ped_device_probe_all,
-    ped_device_sync, ped_device_sync_fast, ped_device_write, ped_disk_clobber, ped_disk_probe,
-    PedDevice,
+    ped_device_read, ped_device_sync, ped_device_sync_fast, ped_device_write, ped_disk_clobber,
+    ped_disk_probe, PedDevice,
 };
 
 pub use libparted_sys::PedDeviceType as DeviceType;
 pub use libparted_sys::_PedCHSGeometry as CHSGeometry;
 
-use super::{cvt, Alignment, Constraint, ConstraintSource, DiskType, Geometry};
+#[cfg(feature = "proc")]
+use super::mounts;
+#[cfg(feature = "sysfs")]
+use super::{SysfsInfo, ZoneModel, ZonedInfo};
+
+use super::{
+    cvt, exception, instrument, kernel, units, Alignment, BufferSizeError, Bytes, Constraint,
+    ConstraintSource, DeviceHandle, DiskType, ExceptionOption, Geometry, RetryPolicy, RoundMode,
+    Sector, Timer, Topology,
+};
+
+/// The number of sectors `Device::check_range()` asks libparted to check at a time.
+const CHECK_CHUNK_SECTORS: i64 = 256;
+
+/// The result of checking a range of sectors with `Device::check_range()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CheckOutcome {
+    /// Every sector in the range could be read.
+    Clean,
+    /// The first sector in the range which could not be read.
+    BadSector(i64),
+    /// The device could not be checked in its current state.
+    Unsupported,
+}
+
+/// The result of `Device::preflight()`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PreflightReport {
+    /// Whether the device may only be read from.
+    pub read_only: bool,
+    /// Whether the device reports no sectors, as card readers without a card and some phantom
+    /// devices do.
+    pub empty: bool,
+    pub first_sector_readable: bool,
+    pub last_sector_readable: bool,
+    /// The first error libparted reported while opening or reading the device.
+    pub error: Option<String>,
+}
+
+impl PreflightReport {
+    /// Whether the device is readable, writable, and has a size, and so may be partitioned.
+    pub fn is_usable(&self) -> bool {
+        !self.read_only && !self.empty && self.first_sector_readable && self.last_sector_readable
+    }
+}
 
 pub struct Device<'a> {
     pub(crate) device: *mut PedDevice,
@@ -28,6 +77,13 @@ pub struct Device<'a> {
     pub(crate) is_droppable: bool,
 }
 
+/// A borrowed view of a device which is owned elsewhere, as returned by `Disk::device()`,
+/// `Partition::device()`, and `Geometry::device()`.
+///
+/// Unlike a **Device**, it never closes the device when dropped, and only permits access
+/// through `&Device`.
+pub struct DeviceRef<'a>(Device<'a>);
+
 pub struct DeviceIter<'a>(*mut PedDevice, PhantomData<&'a PedDevice>);
 
 pub struct DeviceExternalAccess<'a, 'b: 'a>(&'a mut Device<'b>);
@@ -59,20 +115,153 @@ impl<'a> Device<'a> {
 
     /// Returns the first bad sector if a bad sector was found.
     ///
-    /// # Binding Note
-    ///
-    /// Not 100% sure if this is what this method does, as libparted's source
-    /// code did not document the behavior of the function. Am basing this
-    /// off the `check()` method that was documented for **Geometry**.
+    /// `None` is also returned if the range is invalid, or if the device could not be
+    /// checked. Use `Device::check_range()` to distinguish those from a clean range.
+    #[cfg_attr(
+        feature = "v2-api",
+        deprecated(since = "0.1.6", note = "use `check_range()` instead")
+    )]
     pub fn check(&self, start: i64, count: i64) -> Option<u64> {
-        let mut buffer: Vec<u8> = Vec::with_capacity(8192);
-        let buffer_ptr = buffer.as_mut_slice().as_mut_ptr() as *mut c_void;
-        match unsafe { ped_device_check(self.device, buffer_ptr, start, count) } {
-            -1 => None,
-            bad_sector => Some(bad_sector as u64),
+        match self.check_range(start, count) {
+            Ok(CheckOutcome::BadSector(sector)) => Some(sector as u64),
+            _ => None,
         }
     }
 
+    /// Reads `count` sectors from the device, starting at `start`, to find the first bad
+    /// sector within that range.
+    ///
+    /// # Note:
+    ///
+    /// libparted cannot check a device which is not open, or which is in external access
+    /// mode, and so `CheckOutcome::Unsupported` is returned for those.
+    pub fn check_range(&self, start: i64, count: i64) -> Result<CheckOutcome> {
+        if start < 0 || count < 1 || (start + count) as u64 > self.length() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "sectors {} to {} are outside of the device, which has {} sectors",
+                    start,
+                    start + count - 1,
+                    self.length()
+                ),
+            ));
+        }
+
+        let (open, external) = unsafe { ((*self.device).open_count, (*self.device).external_mode) };
+        if open == 0 || external != 0 {
+            return Ok(CheckOutcome::Unsupported);
+        }
+
+        // libparted reads every sector being checked into the buffer, so check in chunks to
+        // bound its size.
+        let chunk = CHECK_CHUNK_SECTORS.min(count);
+        let mut buffer = vec![0u8; chunk as usize * self.sector_size() as usize];
+        let buffer_ptr = buffer.as_mut_ptr() as *mut c_void;
+
+        let mut checked = 0;
+        while checked < count {
+            let sectors = chunk.min(count - checked);
+            let good =
+                unsafe { ped_device_check(self.device, buffer_ptr, start + checked, sectors) };
+            if good < sectors {
+                return Ok(CheckOutcome::BadSector(start + checked + good.max(0)));
+            }
+            checked += sectors;
+        }
+
+        Ok(CheckOutcome::Clean)
+    }
+
+    /// Verifies that the first and last sectors of the device can be read, so that devices
+    /// which would fail part way through an operation may be rejected up front.
+    ///
+    /// The device is opened for the duration of the check if it is not open already. Errors
+    /// which libparted raises are captured in the report, rather than being shown to the user.
+    pub fn preflight(&mut self) -> PreflightReport {
+        let mut report = PreflightReport {
+            read_only: self.read_only(),
+            empty: self.length() == 0 || self.sector_size() == 0,
+            first_sector_readable: false,
+            last_sector_readable: false,
+            error: None,
+        };
+
+        if report.empty {
+            return report;
+        }
+
+        let device = self.device;
+        let last = self.length() as i64 - 1;
+        let mut buffer = vec![0u8; self.sector_size() as usize];
+        let buffer_ptr = buffer.as_mut_ptr() as *mut c_void;
+
+        let mut error = None;
+        let (first, last, read_only) = exception::with_handler(
+            |ex| {
+                if error.is_none() {
+                    error = Some(ex.message.clone());
+                }
+
+                if ex.allows(ExceptionOption::PED_EXCEPTION_CANCEL) {
+                    ExceptionOption::PED_EXCEPTION_CANCEL
+                } else {
+                    ExceptionOption::PED_EXCEPTION_UNHANDLED
+                }
+            },
+            || unsafe {
+                let opened = (*device).open_count == 0;
+                if opened && ped_device_open(device) == 0 {
+                    return (false, false, (*device).read_only != 0);
+                }
+
+                let first = ped_device_read(device, buffer_ptr, 0, 1) != 0;
+                let last = ped_device_read(device, buffer_ptr, last, 1) != 0;
+                // libparted falls back to opening the device read-only when it is not writable.
+                let read_only = (*device).read_only != 0;
+
+                if opened {
+                    ped_device_close(device);
+                }
+
+                (first, last, read_only)
+            },
+        );
+
+        report.first_sector_readable = first;
+        report.last_sector_readable = last;
+        report.read_only = read_only;
+        report.error = error;
+        report
+    }
+
+    /// Obtains a shared handle to the device, which keeps it open until every handle to it has
+    /// been dropped.
+    pub fn handle(&self) -> Result<DeviceHandle> {
+        unsafe { DeviceHandle::from_ped_device(self.device) }
+    }
+
+    /// Writes the raw contents of the entire device to a new image file at `path`, returning
+    /// the number of bytes written. If `timer` is not `None`, it will be used as the progress
+    /// meter.
+    ///
+    /// The device must be open. See `Geometry::dump_to()` for imaging a single region.
+    pub fn dump_to<P: AsRef<Path>>(&self, path: P, timer: Option<&mut Timer>) -> Result<u64> {
+        Geometry::new(self, Sector(0), Sector(self.length() as i64))?.dump_to(path, timer)
+    }
+
+    /// Writes the image file at `path` to the start of the device, returning the number of
+    /// bytes restored. If `timer` is not `None`, it will be used as the progress meter.
+    ///
+    /// The device must be open, and the image must not be larger than the device.
+    pub fn restore_from<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        timer: Option<&mut Timer>,
+    ) -> Result<u64> {
+        Geometry::new(self, Sector(0), Sector(self.length() as i64))?.restore_from(path, timer)
+    }
+
     /// Return the type of partition table detected on `dev`
     pub fn probe(&self) -> Option<DiskType> {
         let disk_type = unsafe { ped_disk_probe(self.device) };
@@ -98,6 +287,14 @@ impl<'a> Device<'a> {
         DeviceIter(ptr::null_mut(), PhantomData)
     }
 
+    /// Like `Device::devices()`, but skips virtual devices (loop devices, device-mapper, md
+    /// arrays, and zram), which are rarely the target of an installation.
+    ///
+    /// Use **DeviceFilter** to include some of these device types.
+    pub fn physical_devices<'b>(probe: bool) -> impl Iterator<Item = Device<'b>> {
+        DeviceFilter::new().devices(probe)
+    }
+
     /// Obtains a handle to the device, but does not open it.
     pub fn get<P: AsRef<Path>>(path: P) -> Result<Device<'a>> {
         // Convert the supplied path into a C-compatible string.
@@ -113,11 +310,191 @@ impl<'a> Device<'a> {
 
     /// Attempts to open the device.
     pub fn open(&mut self) -> Result<()> {
-        cvt(unsafe { ped_device_open(self.device) })?;
+        let device = self.device;
+        instrument::record("device_open", || cvt(unsafe { ped_device_open(device) }))?;
         self.is_droppable = true;
         Ok(())
     }
 
+    /// Attempts to open the device, retrying according to `policy` while it is busy.
+    pub fn open_with_retry(&mut self, policy: &RetryPolicy) -> Result<()> {
+        policy.run(|| self.open())
+    }
+
+    /// Creates a sparse image file at `path` which is `size` bytes long, and opens it as a
+    /// device with logical sectors of `sector_size` bytes, so that a partition table may be
+    /// written to it without root privileges.
+    ///
+    /// # Note:
+    ///
+    /// `path` must not already exist. `size` must be a multiple of `sector_size`, which must
+    /// be a power of two of at least 512.
+    pub fn create_image<P: AsRef<Path>, B: Into<Bytes>>(
+        path: P,
+        size: B,
+        sector_size: u64,
+    ) -> Result<Device<'a>> {
+        let size = size.into().0;
+        if sector_size < 512 || !sector_size.is_power_of_two() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid sector size: {}", sector_size),
+            ));
+        }
+
+        if size == 0 || size % sector_size != 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("image size is not a multiple of {} bytes", sector_size),
+            ));
+        }
+
+        let path = path.as_ref();
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)?
+            .set_len(size)?;
+
+        let mut device = Device::get(path)?;
+
+        // libparted always probes regular files as having 512-byte sectors.
+        unsafe {
+            let dev = device.device;
+            (*dev).sector_size = sector_size as _;
+            (*dev).phys_sector_size = sector_size as _;
+            (*dev).length = (size / sector_size) as i64;
+            for geom in &mut [&mut (*dev).bios_geom, &mut (*dev).hw_geom] {
+                let per_cylinder = i64::from(geom.heads) * i64::from(geom.sectors);
+                if per_cylinder > 0 {
+                    geom.cylinders = ((*dev).length / per_cylinder) as _;
+                }
+            }
+        }
+
+        device.open()?;
+        Ok(device)
+    }
+
+    /// Re-reads the size, sector sizes, and topology of the device, such as after it has been
+    /// resized underneath us. Getters, including the alignment getters, reflect the new values
+    /// afterwards.
+    ///
+    /// If the device is open, it is closed and reopened.
+    ///
+    /// # Note:
+    ///
+    /// libparted only probes a device when it is first obtained, so the path is probed again
+    /// and the results moved into this device, which takes the place of the probed device in
+    /// libparted's cache. The stale results are freed, and other handles to this device see
+    /// the new values too.
+    ///
+    /// If the path can no longer be probed, such as when the device has been unplugged, the
+    /// device is left out of libparted's cache, as with `Device::remove_from_cache()`.
+    pub fn refresh(&mut self) -> Result<()> {
+        let path = CString::new(self.path().as_os_str().as_bytes())
+            .map_err(|err| Error::new(ErrorKind::InvalidData, format!("Inavlid data: {}", err)))?;
+
+        let opens = self.open_count();
+        for _ in 0..opens {
+            cvt(unsafe { ped_device_close(self.device) })?;
+        }
+
+        let result = unsafe {
+            ped_device_cache_remove(self.device);
+            cvt(ped_device_get(path.as_ptr())).map(|fresh| {
+                let (old, new) = (&mut *self.device, &mut *fresh);
+                mem::swap(&mut old.model, &mut new.model);
+                mem::swap(&mut old.type_, &mut new.type_);
+                mem::swap(&mut old.sector_size, &mut new.sector_size);
+                mem::swap(&mut old.phys_sector_size, &mut new.phys_sector_size);
+                mem::swap(&mut old.length, &mut new.length);
+                mem::swap(&mut old.hw_geom, &mut new.hw_geom);
+                mem::swap(&mut old.bios_geom, &mut new.bios_geom);
+                mem::swap(&mut old.host, &mut new.host);
+                mem::swap(&mut old.did, &mut new.did);
+                mem::swap(&mut old.arch_specific, &mut new.arch_specific);
+
+                // The fresh device now holds the stale state, which is freed with it, so this
+                // device takes its place in the cache to be freed with the other devices.
+                (*self.device).next = (*fresh).next;
+                (*fresh).next = self.device;
+                ped_device_cache_remove(fresh);
+                ped_device_destroy(fresh);
+            })
+        };
+
+        for _ in 0..opens {
+            cvt(unsafe { ped_device_open(self.device) })?;
+        }
+
+        result
+    }
+
+    /// Whether the device can still be found at its path, such as after a USB disk has been
+    /// unplugged or a card removed from its reader.
+    ///
+    /// The path is opened without libparted to check that it still refers to a device with
+    /// media present.
+    pub fn is_present(&self) -> bool {
+        OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(self.path())
+            .and_then(|file| file.metadata())
+            .map_or(false, |metadata| {
+                let kind = metadata.file_type();
+                if self.is_file() {
+                    kind.is_file()
+                } else {
+                    kind.is_block_device()
+                }
+            })
+    }
+
+    /// Removes the device from libparted's cache, so that it is no longer listed by
+    /// `Device::devices()`, and a later `Device::get()` for the same path probes it afresh.
+    ///
+    /// The device itself is not freed, so this and any other handles to it remain valid,
+    /// though they should no longer be used for I/O if the device has gone.
+    pub fn remove_from_cache(&mut self) {
+        unsafe { ped_device_cache_remove(self.device) }
+    }
+
+    /// Removes every cached device which is no longer present from libparted's cache and
+    /// frees it, returning their paths. Daemons should call this when devices are removed from
+    /// the system, so that stale devices are not listed or used afterwards.
+    ///
+    /// # Safety
+    ///
+    /// Every **Device**, **Disk**, **Partition**, and **Geometry** referring to a departed
+    /// device becomes dangling, and must not be used or dropped afterwards.
+    pub unsafe fn evict_missing() -> Vec<PathBuf> {
+        let missing = Device::devices(false)
+            .filter(|device| !device.is_present())
+            .map(|device| (device.device, device.path().to_owned()))
+            .collect::<Vec<_>>();
+
+        missing
+            .into_iter()
+            .map(|(device, path)| {
+                ped_device_cache_remove(device);
+                ped_device_destroy(device);
+                path
+            })
+            .collect()
+    }
+
+    /// Closes and frees every device known to libparted, emptying its cache.
+    ///
+    /// # Safety
+    ///
+    /// Every **Device**, **Disk**, **Partition**, and **Geometry** referring to a device
+    /// becomes dangling, and must not be used or dropped afterwards.
+    pub unsafe fn free_all() {
+        ped_device_free_all()
+    }
+
     /// Attempts to get the device of the given `path`, then attempts to open that device.
     pub fn new<P: AsRef<Path>>(path: P) -> Result<Device<'a>> {
         let mut device = Device::get(path)?;
@@ -130,6 +507,18 @@ impl<'a> Device<'a> {
         Device::new_(device)
     }
 
+    /// Wraps `device`, which is owned elsewhere, without taking ownership of any of its
+    /// opens, so that it is never closed when dropped.
+    ///
+    /// Every view of a device obtained from a disk, partition, or region is created this way,
+    /// so that none of them can unbalance the open count. A **DeviceHandle** should be used
+    /// where the device must be kept open.
+    pub(crate) unsafe fn borrowed(device: *mut PedDevice) -> Device<'a> {
+        let mut device = Device::new_(device);
+        device.is_droppable = false;
+        device
+    }
+
     #[allow(clippy::missing_safety_doc)]
     pub unsafe fn ped_device(&self) -> *mut PedDevice {
         self.device
@@ -151,14 +540,75 @@ impl<'a> Device<'a> {
         Ok(DeviceExternalAccess(self))
     }
 
+    /// Begins external access mode, as with `Device::external_access()`, for only as long as
+    /// the device is borrowed.
+    pub(crate) fn external_access_scoped<'b>(&'b mut self) -> Result<DeviceExternalAccess<'b, 'a>> {
+        cvt(unsafe { ped_device_begin_external_access(self.device) })?;
+
+        Ok(DeviceExternalAccess(self))
+    }
+
+    /// Runs `func` with the device in external access mode, passing it the path of the
+    /// device, so that an external program such as `e2fsck` may safely do I/O on it.
+    ///
+    /// External access ends when `func` returns, even if it panics. Reads and writes made
+    /// through this crate to the device while `func` runs fail with an error, rather than
+    /// tripping libparted's assertions.
+    ///
+    /// ```rust,no_run
+    /// # use libparted::Device;
+    /// # use std::process::Command;
+    /// let mut device = Device::new("/dev/sda").unwrap();
+    /// let status = device
+    ///     .with_external_access(|path| Command::new("e2fsck").arg("-n").arg(path).status())
+    ///     .unwrap();
+    /// ```
+    pub fn with_external_access<T, F: FnOnce(&Path) -> T>(&mut self, func: F) -> Result<T> {
+        let access = self.external_access_scoped()?;
+        let path = access.0.path().to_owned();
+        Ok(func(&path))
+    }
+
+    /// Returns the path of each partition of the device, or the device itself, which is in
+    /// use as swap, so that the caller may disable them before repartitioning.
+    #[cfg(feature = "proc")]
+    pub fn active_swaps(&self) -> Result<Vec<PathBuf>> {
+        mounts::active_swaps(self.path())
+    }
+
+    /// Returns whether the device, or any of its partitions, is in use as swap.
+    #[cfg(feature = "proc")]
+    pub fn has_active_swap(&self) -> Result<bool> {
+        self.active_swaps().map(|swaps| !swaps.is_empty())
+    }
+
     /// Flushes all write-behind caches that might be holding up writes.
     ///
     /// It is slow because it guarantees cache coherency among all relevant caches.
     pub fn sync(&mut self) -> Result<()> {
-        cvt(unsafe { ped_device_sync(self.device) })?;
+        let device = self.device;
+        instrument::record("device_sync", || cvt(unsafe { ped_device_sync(device) }))?;
         Ok(())
     }
 
+    /// Flushes every cache between libparted and the storage medium: libparted's own caches,
+    /// as with `Device::sync()`, then the kernel's buffer cache for the device (as with
+    /// `blockdev --flushbufs`), and the device's write cache.
+    ///
+    /// Flushing the kernel's buffer cache requires `CAP_SYS_ADMIN`. The device is opened for
+    /// the duration if it is not already open.
+    pub fn flush(&mut self) -> Result<()> {
+        {
+            // libparted can only sync a device which is open.
+            let handle = self.handle()?;
+            unsafe { Device::borrowed(handle.ped_device()) }.sync()?;
+        }
+        let file = OpenOptions::new().read(true).open(self.path())?;
+        // An fsync of a block device has the kernel flush the device's write cache.
+        file.sync_all()?;
+        kernel::flush_buffers(&file)
+    }
+
     /// Flushes all write-behind caches that might be holding writes.
     ///
     /// It does not ensure cache coherency with other caches.
@@ -172,6 +622,29 @@ impl<'a> Device<'a> {
         unsafe { ped_device_is_busy(self.device) != 0 }
     }
 
+    /// Attempts to read **sectors** sectors from the device, starting at the **start_sector**,
+    /// into the beginning of the buffer.
+    ///
+    /// The buffer must be large enough to hold all of the sectors being read.
+    pub fn read_from_sectors(
+        &self,
+        buffer: &mut [u8],
+        start_sector: i64,
+        sectors: i64,
+    ) -> Result<()> {
+        BufferSizeError::check_read(buffer.len(), sectors, self.sector_size())?;
+        let total_size = self.sector_size() as usize * sectors as usize;
+
+        check_not_external(self.device)?;
+        let buffer_ptr = buffer.as_mut_ptr() as *mut c_void;
+        let device = self.device;
+        instrument::record("device_read", || {
+            cvt(unsafe { ped_device_read(device, buffer_ptr, start_sector, sectors) })
+        })?;
+        instrument::bytes_read("device_read", total_size as u64);
+        Ok(())
+    }
+
     /// Attempts to write the data within the buffer to the device, starting
     /// at the **start_sector**, and spanning across **sectors**.
     pub fn write_to_sectors(
@@ -180,10 +653,10 @@ impl<'a> Device<'a> {
         start_sector: i64,
         sectors: i64,
     ) -> Result<()> {
+        BufferSizeError::check_write(buffer.len(), sectors, self.sector_size())?;
         let total_size = self.sector_size() as usize * sectors as usize;
 
-        // Ensure that the data will fit within the region of sectors.
-        debug_assert!(buffer.len() <= total_size);
+        check_not_external(self.device)?;
 
         // Write as much data as needed to fill the entire sector, writing
         // zeros in the unused space, and obtaining a pointer to the buffer.
@@ -193,7 +666,11 @@ impl<'a> Device<'a> {
         let sector_ptr = sector_buffer.as_slice().as_ptr() as *const c_void;
 
         // Then attempt to write the data to the device.
-        cvt(unsafe { ped_device_write(self.device, sector_ptr, start_sector, sectors) })?;
+        let device = self.device;
+        instrument::record("device_write", || {
+            cvt(unsafe { ped_device_write(device, sector_ptr, start_sector, sectors) })
+        })?;
+        instrument::bytes_written("device_write", total_size as u64);
         Ok(())
     }
 
@@ -238,14 +715,14 @@ impl<'a> Device<'a> {
         range_start: &Geometry,
         range_end: &Geometry,
     ) -> Result<Constraint<'b>> {
-        let alignment_any = Alignment::new(0, 1).unwrap();
+        let alignment_any = Alignment::any()?;
         Constraint::new(
             &alignment_any,
             &alignment_any,
             range_start,
             range_end,
-            1,
-            self.length() as i64,
+            Sector(1),
+            Sector(self.length() as i64),
         )
     }
 
@@ -312,12 +789,70 @@ impl<'a> Device<'a> {
         }
     }
 
+    /// Describes the physical topology of the device, which determines the alignment chosen
+    /// for new partitions.
+    ///
+    /// # Note:
+    ///
+    /// Without the `sysfs` feature, the topology is derived from libparted's alignments, which
+    /// substitute a 1 MiB optimal I/O size when the device does not report one.
+    pub fn topology(&self) -> Topology {
+        Topology::from_device(self)
+    }
+
+    /// Reads details of the device which libparted does not provide from sysfs, such as
+    /// whether it is rotational or removable, and the bus it is attached through.
+    #[cfg(feature = "sysfs")]
+    pub fn sysfs_info(&self) -> Result<SysfsInfo> {
+        SysfsInfo::read(self.path())
+    }
+
+    /// Reads the zone model and zone size of a zoned device (ie: SMR hard drives and ZNS
+    /// SSDs) from sysfs, returning `None` if the device is not zoned.
+    #[cfg(feature = "sysfs")]
+    pub fn zoned_info(&self) -> Result<Option<ZonedInfo>> {
+        ZonedInfo::read(self.path())
+    }
+
+    /// The size of each zone in sectors, if the device is host-managed, and so partitions
+    /// must be aligned to its zones.
+    #[cfg(feature = "sysfs")]
+    pub(crate) fn managed_zone_sectors(&self) -> Option<i64> {
+        match self.zoned_info() {
+            Ok(Some(zoned)) if zoned.model == ZoneModel::HostManaged => {
+                Some((zoned.zone_size / self.sector_size()) as i64)
+            }
+            _ => None,
+        }
+    }
+
+    #[cfg(not(feature = "sysfs"))]
+    pub(crate) fn managed_zone_sectors(&self) -> Option<i64> {
+        None
+    }
+
     /// Remove all identifying signatures of a partition table.
     pub fn clobber(&mut self) -> Result<()> {
         cvt(unsafe { ped_disk_clobber(self.device) })?;
         Ok(())
     }
 
+    /// Removes all identifying signatures of a partition table, and then overwrites the first
+    /// and last mebibyte of the device with zeros.
+    ///
+    /// This erases both primary and backup GPT headers, along with most file system and
+    /// volume signatures which may be found at the start or end of a whole-disk device.
+    pub fn wipe_signatures(&mut self) -> Result<()> {
+        self.clobber()?;
+
+        // libparted can only write to a device which is open.
+        let _handle = self.handle()?;
+        let length = self.length() as i64;
+        let sectors = (1024 * 1024 / self.sector_size() as i64).min(length);
+        Geometry::new(self, Sector(0), Sector(sectors))?.zero()?;
+        Geometry::new(self, Sector(length - sectors), Sector(sectors))?.zero()
+    }
+
     pub fn model(&self) -> &str {
         unsafe { str::from_utf8_unchecked(CStr::from_ptr((*self.device).model).to_bytes()) }
     }
@@ -332,14 +867,90 @@ impl<'a> Device<'a> {
         unsafe { (*self.device).type_ as DeviceType }
     }
 
+    /// A short, human-readable name for the type of the device, such as `"nvme"` or `"scsi"`.
+    pub fn type_name(&self) -> &'static str {
+        match self.type_() {
+            DeviceType::PED_DEVICE_UNKNOWN => "unknown",
+            DeviceType::PED_DEVICE_SCSI => "scsi",
+            DeviceType::PED_DEVICE_IDE => "ide",
+            DeviceType::PED_DEVICE_DAC960 => "dac960",
+            DeviceType::PED_DEVICE_CPQARRAY => "cpqarray",
+            DeviceType::PED_DEVICE_FILE => "file",
+            DeviceType::PED_DEVICE_ATARAID => "ataraid",
+            DeviceType::PED_DEVICE_I2O => "i2o",
+            DeviceType::PED_DEVICE_UBD => "ubd",
+            DeviceType::PED_DEVICE_DASD => "dasd",
+            DeviceType::PED_DEVICE_VIODASD => "viodasd",
+            DeviceType::PED_DEVICE_SX8 => "sx8",
+            DeviceType::PED_DEVICE_DM => "dm",
+            DeviceType::PED_DEVICE_XVD => "xvd",
+            DeviceType::PED_DEVICE_SDMMC => "sdmmc",
+            DeviceType::PED_DEVICE_VIRTBLK => "virtblk",
+            DeviceType::PED_DEVICE_AOE => "aoe",
+            DeviceType::PED_DEVICE_MD => "md",
+            DeviceType::PED_DEVICE_LOOP => "loop",
+            DeviceType::PED_DEVICE_NVME => "nvme",
+            DeviceType::PED_DEVICE_RAM => "ram",
+            DeviceType::PED_DEVICE_PMEM => "pmem",
+        }
+    }
+
+    /// Whether the device is an NVMe namespace.
+    pub fn is_nvme(&self) -> bool {
+        self.type_() == DeviceType::PED_DEVICE_NVME
+    }
+
+    /// Whether the device is a device-mapper device, such as an LVM volume or LUKS mapping.
+    pub fn is_dm(&self) -> bool {
+        self.type_() == DeviceType::PED_DEVICE_DM
+    }
+
+    /// Whether the device is a loop device.
+    pub fn is_loop(&self) -> bool {
+        self.type_() == DeviceType::PED_DEVICE_LOOP
+    }
+
+    /// Whether the device is an md (software RAID) array.
+    pub fn is_md(&self) -> bool {
+        self.type_() == DeviceType::PED_DEVICE_MD
+    }
+
+    /// Whether the device is a regular file, rather than a block device.
+    pub fn is_file(&self) -> bool {
+        self.type_() == DeviceType::PED_DEVICE_FILE
+    }
+
     pub fn sector_size(&self) -> u64 {
         unsafe { (*self.device).sector_size as u64 }
     }
 
+    /// Converts a number of bytes to a number of logical sectors on this device.
+    pub fn bytes_to_sectors<B: Into<Bytes>>(&self, bytes: B, mode: RoundMode) -> i64 {
+        units::bytes_to_sectors(bytes.into().0, self.sector_size(), mode)
+    }
+
+    /// Converts a number of logical sectors on this device to a number of bytes.
+    pub fn sectors_to_bytes<S: Into<Sector>>(&self, sectors: S) -> u64 {
+        sectors.into().0 as u64 * self.sector_size()
+    }
+
     pub fn phys_sector_size(&self) -> u64 {
         unsafe { (*self.device).phys_sector_size as u64 }
     }
 
+    /// Whether the device is an "Advanced Format" 512e drive, which emulates 512-byte logical
+    /// sectors on larger physical sectors. Writes which are not aligned to the physical
+    /// sectors are much slower on such drives.
+    pub fn is_512e(&self) -> bool {
+        self.sector_size() == 512 && self.phys_sector_size() > 512
+    }
+
+    /// Whether the device uses 4096-byte logical sectors (4Kn), so that sector numbers are
+    /// counted in 4 KiB units, rather than the 512 bytes which many tools assume.
+    pub fn is_4k_native(&self) -> bool {
+        self.sector_size() == 4096
+    }
+
     pub fn length(&self) -> u64 {
         unsafe { (*self.device).length as u64 }
     }
@@ -363,7 +974,98 @@ impl<'a> Device<'a> {
         unsafe { (*self.device).did as i16 }
     }
 
-    // TODO: arch_specific
+    /// The major and minor numbers of the device node, if it is a block device.
+    pub fn devno(&self) -> Option<(u32, u32)> {
+        devno(self.path())
+    }
+}
+
+/// The major and minor numbers of the block device node at `path`.
+pub(crate) fn devno(path: &Path) -> Option<(u32, u32)> {
+    let metadata = fs::metadata(path).ok()?;
+    if !metadata.file_type().is_block_device() {
+        return None;
+    }
+
+    let rdev = metadata.rdev();
+    Some((major(rdev) as u32, minor(rdev) as u32))
+}
+
+/// The major number of the device `rdev`, as encoded by glibc.
+pub(crate) fn major(rdev: u64) -> u64 {
+    ((rdev >> 8) & 0xfff) | ((rdev >> 32) & !0xfff)
+}
+
+/// The minor number of the device `rdev`, as encoded by glibc.
+pub(crate) fn minor(rdev: u64) -> u64 {
+    (rdev & 0xff) | ((rdev >> 12) & !0xff)
+}
+
+/// Selects which virtual devices are listed by `DeviceFilter::devices()`. Every kind of
+/// virtual device is excluded unless it is opted back in.
+///
+/// ```rust,no_run
+/// # use libparted::DeviceFilter;
+/// for device in DeviceFilter::new().include_md(true).devices(true) {
+///     println!("{}", device.path().display());
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DeviceFilter {
+    loop_: bool,
+    dm: bool,
+    md: bool,
+    zram: bool,
+}
+
+impl DeviceFilter {
+    /// A filter which excludes every kind of virtual device.
+    pub fn new() -> DeviceFilter {
+        DeviceFilter::default()
+    }
+
+    /// Whether loop devices are listed.
+    pub fn include_loop(mut self, include: bool) -> DeviceFilter {
+        self.loop_ = include;
+        self
+    }
+
+    /// Whether device-mapper devices, such as LVM volumes and LUKS mappings, are listed.
+    pub fn include_dm(mut self, include: bool) -> DeviceFilter {
+        self.dm = include;
+        self
+    }
+
+    /// Whether md (software RAID) arrays are listed.
+    pub fn include_md(mut self, include: bool) -> DeviceFilter {
+        self.md = include;
+        self
+    }
+
+    /// Whether zram devices are listed.
+    pub fn include_zram(mut self, include: bool) -> DeviceFilter {
+        self.zram = include;
+        self
+    }
+
+    /// Whether `device` passes the filter.
+    pub fn matches(&self, device: &Device) -> bool {
+        // libparted has no device type for zram, so it is recognized by name.
+        let is_zram = device
+            .path()
+            .file_name()
+            .map_or(false, |name| name.as_bytes().starts_with(b"zram"));
+
+        (self.loop_ || !device.is_loop())
+            && (self.dm || !device.is_dm())
+            && (self.md || !device.is_md())
+            && (self.zram || !is_zram)
+    }
+
+    /// Lists each device which passes the filter, as `Device::devices()` does.
+    pub fn devices<'b>(self, probe: bool) -> impl Iterator<Item = Device<'b>> {
+        Device::devices(probe).filter(move |device| self.matches(device))
+    }
 }
 
 impl<'a> Iterator for DeviceIter<'a> {
@@ -374,13 +1076,25 @@ impl<'a> Iterator for DeviceIter<'a> {
             None
         } else {
             self.0 = device;
-            let mut device = unsafe { Device::from_ped_device(device) };
-            device.is_droppable = false;
-            Some(device)
+            Some(unsafe { Device::borrowed(device) })
         }
     }
 }
 
+impl<'a> DeviceRef<'a> {
+    pub(crate) unsafe fn from_ped_device(device: *mut PedDevice) -> DeviceRef<'a> {
+        DeviceRef(Device::borrowed(device))
+    }
+}
+
+impl<'a> Deref for DeviceRef<'a> {
+    type Target = Device<'a>;
+
+    fn deref(&self) -> &Device<'a> {
+        &self.0
+    }
+}
+
 impl<'a> Drop for Device<'a> {
     fn drop(&mut self) {
         unsafe {
@@ -391,6 +1105,18 @@ impl<'a> Drop for Device<'a> {
     }
 }
 
+/// Fails if `device` is in external access mode, where libparted must not do any I/O on it.
+pub(crate) fn check_not_external(device: *const PedDevice) -> Result<()> {
+    if unsafe { (*device).external_mode } != 0 {
+        Err(Error::new(
+            ErrorKind::Other,
+            "the device is in external access mode",
+        ))
+    } else {
+        Ok(())
+    }
+}
+
 impl<'a, 'b> Drop for DeviceExternalAccess<'a, 'b> {
     fn drop(&mut self) {
         unsafe {
@@ -1,32 +1,107 @@
+use super::events::{self, ProgressSink};
+use super::exception::{self, ExceptionOption, ExceptionType};
+use super::gpt;
+use super::mbr::{Mbr, MbrEntry};
+#[cfg(feature = "proc")]
+use super::BusyPartition;
 use super::{
-    cvt, get_optional, prefer_snap, snap, Alignment, Constraint, ConstraintSource, Device,
-    Geometry, Partition, MOVE_DOWN, MOVE_STILL, MOVE_UP, SECT_END, SECT_START,
+    busy, copy, cvt, get_optional, instrument, kernel, layout, snapping, spec, version_at_least,
+    AlignPolicy, Alignment, CommitHooks, CommitOptions, Constraint, ConstraintSource, Device,
+    DeviceHandle, DeviceRef, DiskCheckIssue, DiskOp, DiskTransaction, FileSystemType, FsResizer,
+    GeomView, Geometry, KernelSyncReport, LayoutEntry, LayoutIssue, Partition, PartitionDump,
+    PartitionExtent, PartitionFlag, PartitionInfo, PartitionRef, PartitionSpec, PartitionTableDump,
+    PartitionType, Position, RetryPolicy, Sector, Timer,
 };
 use libparted_sys::{
     ped_constraint_any, ped_disk_add_partition, ped_disk_check as check, ped_disk_clobber,
     ped_disk_commit as commit, ped_disk_commit_to_dev as commit_to_dev,
     ped_disk_commit_to_os as commit_to_os, ped_disk_delete_all as delete_all,
     ped_disk_delete_partition, ped_disk_destroy, ped_disk_duplicate, ped_disk_extended_partition,
-    ped_disk_get_flag, ped_disk_get_last_partition_num, ped_disk_get_max_partition_geometry,
-    ped_disk_get_max_primary_partition_count, ped_disk_get_max_supported_partition_count,
-    ped_disk_get_partition, ped_disk_get_partition_alignment, ped_disk_get_partition_by_sector,
+    ped_disk_flag_get_name, ped_disk_get_flag, ped_disk_get_last_partition_num,
+    ped_disk_get_max_partition_geometry, ped_disk_get_max_primary_partition_count,
+    ped_disk_get_max_supported_partition_count, ped_disk_get_partition,
+    ped_disk_get_partition_alignment, ped_disk_get_partition_by_sector,
     ped_disk_get_primary_partition_count, ped_disk_is_flag_available,
     ped_disk_max_partition_length, ped_disk_max_partition_start_sector,
     ped_disk_maximize_partition, ped_disk_minimize_extended_partition, ped_disk_new,
-    ped_disk_new_fresh, ped_disk_next_partition, ped_disk_print, ped_disk_set_flag,
+    ped_disk_new_fresh, ped_disk_next_partition, ped_disk_print, ped_disk_probe, ped_disk_set_flag,
     ped_disk_set_partition_geom, ped_disk_type_check_feature, ped_disk_type_get,
     ped_disk_type_get_next, ped_disk_type_register, ped_disk_type_unregister, PedDisk, PedDiskType,
     PedPartition,
 };
+use std::cell::RefCell;
+use std::collections::BTreeMap;
 use std::ffi::{CStr, CString};
-use std::io::{ErrorKind, Result};
+use std::io::{Error, ErrorKind, Result};
 use std::marker::PhantomData;
+use std::mem;
+use std::os::raw::c_char;
+use std::path::PathBuf;
 use std::ptr;
 use std::str;
 use std::string::ToString;
 
 pub use libparted_sys::_PedDiskFlag as DiskFlag;
 pub use libparted_sys::_PedDiskTypeFeature as DiskTypeFeature;
+pub use libparted_sys::PedDiskOps as DiskOps;
+
+// Declared with integer types, as these return `0` when there is no such flag, which is not
+// a valid `DiskFlag`.
+extern "C" {
+    #[link_name = "ped_disk_flag_next"]
+    fn disk_flag_next(flag: u32) -> u32;
+    #[link_name = "ped_disk_flag_get_by_name"]
+    fn disk_flag_get_by_name(name: *const c_char) -> u32;
+}
+
+/// Name lookup and enumeration for **DiskFlag**s.
+pub trait DiskFlagExt: Sized {
+    /// Every flag known to libparted, in order.
+    fn all() -> Vec<Self>;
+
+    /// Returns the flag with the given `name`, such as `"pmbr_boot"`.
+    fn from_name(name: &str) -> Option<Self>;
+
+    /// Returns the name libparted uses for the flag.
+    fn name(&self) -> &'static str;
+}
+
+/// Converts a flag returned by libparted, which may be newer than the flags that
+/// **DiskFlag** knows of, or `0` for no flag.
+fn disk_flag(flag: u32) -> Option<DiskFlag> {
+    if flag >= 1 && flag <= DiskFlag::PED_DISK_GPT_PMBR_BOOT as u32 {
+        Some(unsafe { mem::transmute::<u32, DiskFlag>(flag) })
+    } else {
+        None
+    }
+}
+
+impl DiskFlagExt for DiskFlag {
+    /// Flags which are newer than **DiskFlag** are skipped.
+    fn all() -> Vec<DiskFlag> {
+        let mut flags = Vec::new();
+        let mut flag = unsafe { disk_flag_next(0) };
+        while flag != 0 {
+            flags.extend(disk_flag(flag));
+            flag = unsafe { disk_flag_next(flag) };
+        }
+
+        flags
+    }
+
+    /// Returns `None` for flags which are newer than **DiskFlag**.
+    fn from_name(name: &str) -> Option<DiskFlag> {
+        let name = CString::new(name).ok()?;
+        disk_flag(unsafe { disk_flag_get_by_name(name.as_ptr()) })
+    }
+
+    fn name(&self) -> &'static str {
+        unsafe {
+            let cstr = CStr::from_ptr(ped_disk_flag_get_name(*self));
+            str::from_utf8_unchecked(cstr.to_bytes())
+        }
+    }
+}
 
 /// Which type of partitioning scheme the disk shall receive.
 /// Derived from https://unix.stackexchange.com/a/289401
@@ -97,20 +172,97 @@ impl PartitionTableType {
     }
 }
 
+thread_local! {
+    /// The tables which have been changed in memory since they were last read from or written
+    /// to their device, so that changes made through a partition mark its disk too.
+    static DIRTY: RefCell<Vec<*mut PedDisk>> = RefCell::new(Vec::new());
+}
+
+/// Records whether the table `disk` has changes which have not been committed.
+pub(crate) fn set_dirty(disk: *mut PedDisk, dirty: bool) {
+    DIRTY.with(|tables| {
+        let mut tables = tables.borrow_mut();
+        tables.retain(|&table| table != disk);
+        if dirty && !disk.is_null() {
+            tables.push(disk);
+        }
+    });
+}
+
 macro_rules! disk_fn_mut {
     ($(#[$attr:meta])* fn $method:tt) => {
         $(#[$attr])*
         pub fn $method(&mut self) -> Result<()> {
-            cvt(unsafe { $method(self.disk) })?;
+            let disk = self.disk;
+            instrument::record(stringify!($method), || cvt(unsafe { $method(disk) }))?;
             Ok(())
         }
     }
 }
 
+/// Limits how far `Disk::snap_geometry()` may move each boundary of a geometry.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SnapOptions {
+    /// The previous geometry of the partition. Boundaries which are within range of where
+    /// they were before are kept there.
+    pub old: Option<GeomView>,
+    /// The sectors the start may be snapped to, or the whole device if `None`.
+    pub start_range: Option<GeomView>,
+    /// The sectors the end may be snapped to, or the whole device if `None`.
+    pub end_range: Option<GeomView>,
+}
+
+/// Controls how `Disk::convert_label()` carries partitions over to the new label.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ConvertOptions {
+    /// Drop partition flags which the new label does not support, rather than refusing to
+    /// convert.
+    pub drop_unsupported_flags: bool,
+    /// Number the partitions sequentially in the order they appear on the disk, rather than
+    /// keeping their numbers (ie: logical partitions 5 and 6 would become 2 and 3).
+    pub renumber: bool,
+}
+
+/// The state of a disk, as returned by `Disk::state()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DiskState {
+    /// The partition table in memory has changes which have not been committed.
+    pub dirty: bool,
+    /// Signatures of other labels on the device will be erased when the table is committed.
+    pub needs_clobber: bool,
+    /// libparted is tracking changes for the operating system, as it does between a commit to
+    /// the device and a commit to the operating system.
+    pub update_mode: bool,
+    /// The revision of the label on the device, as `(major, minor)`, for labels which record
+    /// one (ie: `(1, 0)` for GPT).
+    pub label_revision: Option<(u16, u16)>,
+    /// The length of the device has changed since the table was last read from or written to
+    /// it, such as when `Device::refresh()` picked up a resize, and so the table may not
+    /// describe the device as it is now.
+    pub stale_device: bool,
+}
+
+/// A partition which was removed from a disk, as returned by
+/// `Disk::remove_partition_returning()` and friends.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RemovedPartition {
+    /// The partition as it was before it was removed, which may be given to
+    /// `Disk::restore_partition()` to recreate it.
+    pub partition: PartitionDump,
+    /// The region of free space which now contains the partition's sectors, including any
+    /// free space which was adjacent to it.
+    pub free: GeomView,
+}
+
 pub struct Disk<'a> {
     pub(crate) disk: *mut PedDisk,
     pub(crate) phantom: PhantomData<&'a PedDisk>,
     is_droppable: bool,
+    /// Keeps the device open for as long as the disk exists.
+    handle: Option<DeviceHandle<'a>>,
+    /// The length of the device, in sectors, when the table was last read from or written to
+    /// it.
+    device_length: u64,
 }
 
 pub struct DiskType<'a> {
@@ -171,6 +323,80 @@ impl<'a> DiskType<'a> {
         }
     }
 
+    /// The longest partition, in sectors, which a label of this type can represent on
+    /// `device`, without creating one.
+    pub fn max_partition_length(&self, device: &Device) -> Result<i64> {
+        self.with_fresh_disk(device, |disk| disk.max_partition_length())
+    }
+
+    /// The largest start sector of a partition which a label of this type can represent on
+    /// `device`, without creating one.
+    pub fn max_start_sector(&self, device: &Device) -> Result<i64> {
+        self.with_fresh_disk(device, |disk| disk.max_partition_start_sector())
+    }
+
+    /// Creates an in-memory label of this type on `device` for `func` to query, without
+    /// writing anything to the device.
+    pub(crate) fn with_fresh_disk<T, F>(&self, device: &Device, func: F) -> Result<T>
+    where
+        F: FnOnce(&Disk) -> T,
+    {
+        let disk = Disk {
+            disk: cvt(unsafe { ped_disk_new_fresh(device.ped_device(), self.type_) })?,
+            phantom: PhantomData,
+            is_droppable: true,
+            handle: None,
+            device_length: device.length(),
+        };
+        Ok(func(&disk))
+    }
+
+    /// Creates a disk type for a label implemented outside of libparted, which may then be
+    /// made available to libparted with `register()`.
+    ///
+    /// The type is never freed, as libparted keeps a pointer to it once registered.
+    ///
+    /// `features` may combine several features. The combined bitmask is stored for libparted
+    /// to read, and is only ever tested one feature at a time, as with
+    /// `DiskType::check_feature()`.
+    ///
+    /// # Note:
+    ///
+    /// Implementing a label requires allocating libparted's own disk and partition structures
+    /// from within the callbacks (ie: with `_ped_disk_alloc()` and `_ped_partition_alloc()`),
+    /// so no safe Rust interface is provided for writing one.
+    ///
+    /// # Safety
+    ///
+    /// Every callback of `ops` must uphold the contract of the corresponding operation in
+    /// libparted's `<parted/disk.h>`, and `name` must not already be registered.
+    pub unsafe fn from_raw_ops(
+        name: &'static CStr,
+        ops: &'static DiskOps,
+        features: &[DiskTypeFeature],
+    ) -> DiskType<'static> {
+        let type_ = Box::into_raw(Box::new(PedDiskType {
+            next: ptr::null_mut(),
+            name: name.as_ptr(),
+            ops,
+            features: DiskTypeFeature::PED_DISK_TYPE_EXTENDED,
+        }));
+
+        // A combination of features is not a valid `DiskTypeFeature`, so the bitmask is
+        // written through an integer view of the field once the type is behind a raw pointer.
+        // From then on the field is only read by libparted, as an integer, and never as a
+        // `DiskTypeFeature` from Rust.
+        let features = features
+            .iter()
+            .fold(0, |bits, &feature| bits | feature as u32);
+        ptr::write(ptr::addr_of_mut!((*type_).features) as *mut u32, features);
+
+        DiskType {
+            type_,
+            phantom: PhantomData,
+        }
+    }
+
     pub fn register(&self) {
         unsafe { ped_disk_type_register(self.type_) }
     }
@@ -182,18 +408,28 @@ impl<'a> DiskType<'a> {
 
 pub struct DiskPartIter<'a>(&'a Disk<'a>, *mut PedPartition);
 
+/// Iterates a view of each region of a disk, as returned by `Disk::partitions()`.
+pub struct DiskPartRefIter<'a>(DiskPartIter<'a>);
+
 impl<'a> Disk<'a> {
     /// Read the partition table off a device (if one is found).
     ///
+    /// The disk holds a shared handle to the device, which keeps it open until the disk is
+    /// dropped. As only a shared reference to `device` is needed, it may still be queried
+    /// (ie: for its model) while the disk exists.
+    ///
     /// **Warning**: May modify the supplied `device` if the partition table indicates that the
     /// existing values are incorrect.
-    pub fn new(device: &'a mut Device) -> Result<Disk<'a>> {
-        let is_droppable = device.is_droppable;
+    pub fn new(device: &'a Device) -> Result<Disk<'a>> {
+        let handle = device.handle()?;
         let disk = cvt(unsafe { ped_disk_new(device.ped_device()) })?;
+        set_dirty(disk, false);
         Ok(Disk {
             disk,
             phantom: PhantomData,
-            is_droppable,
+            is_droppable: device.is_droppable,
+            handle: Some(handle),
+            device_length: device.length(),
         })
     }
 
@@ -201,11 +437,16 @@ impl<'a> Disk<'a> {
     ///
     /// The new partition table is only created in-memory, and nothing is written to disk until
     /// `disk.commit_to_dev()` is called.
-    pub fn new_fresh(device: &'a mut Device, type_: DiskType) -> Result<Disk<'a>> {
-        cvt(unsafe { ped_disk_new_fresh(device.ped_device(), type_.type_) }).map(|disk| Disk {
+    pub fn new_fresh(device: &'a Device, type_: DiskType) -> Result<Disk<'a>> {
+        let handle = device.handle()?;
+        let disk = cvt(unsafe { ped_disk_new_fresh(device.ped_device(), type_.type_) })?;
+        set_dirty(disk, true);
+        Ok(Disk {
             disk,
             phantom: PhantomData,
             is_droppable: true,
+            handle: Some(handle),
+            device_length: device.length(),
         })
     }
 
@@ -216,10 +457,10 @@ impl<'a> Disk<'a> {
     ///
     /// This method is a shorthand for
     /// ```rust
-    /// Disk::new_fresh(&mut dev, DiskType::from_type(table_type))
+    /// Disk::new_fresh(&dev, DiskType::from_type(table_type))
     /// ```
     pub fn new_with_partition_table(
-        device: &'a mut Device,
+        device: &'a Device,
         table_type: PartitionTableType,
     ) -> Result<Disk<'a>> {
         Disk::new_fresh(
@@ -231,20 +472,50 @@ impl<'a> Disk<'a> {
         )
     }
 
+    /// Begins a transaction, which discards any changes made through it unless it is
+    /// committed.
+    pub fn begin<'b>(&'b mut self) -> DiskTransaction<'b, 'a> {
+        DiskTransaction::new(self)
+    }
+
+    /// Returns a view of the device which holds the disk.
+    pub fn device(&self) -> DeviceRef {
+        unsafe { DeviceRef::from_ped_device((*self.disk).dev) }
+    }
+
+    /// Obtains a shared handle to the device which holds the disk.
+    ///
+    /// The handle borrows the disk, so it cannot outlive the device the disk was read from.
+    pub fn handle(&self) -> Result<DeviceHandle> {
+        match self.handle {
+            Some(ref handle) => Ok(handle.clone()),
+            None => unsafe { DeviceHandle::from_ped_device((*self.disk).dev) },
+        }
+    }
+
     /// Obtains the inner device from the disk.
     #[allow(clippy::missing_safety_doc)]
+    #[cfg_attr(
+        feature = "v2-api",
+        deprecated(
+            since = "0.1.6",
+            note = "use `device()`, or `handle()` to keep it open"
+        )
+    )]
     pub unsafe fn get_device<'b>(&self) -> Device<'b> {
-        let mut device = Device::from_ped_device((*self.disk).dev);
-        device.is_droppable = false;
-        device
+        self.raw_device()
+    }
+
+    /// The device which holds the disk, which will not be closed when dropped. Its lifetime is
+    /// not tied to `self`, so that it may be used while the disk is borrowed mutably.
+    pub(crate) fn raw_device<'b>(&self) -> Device<'b> {
+        unsafe { Device::borrowed((*self.disk).dev) }
     }
 
     /// Obtains the inner device from the disk, with mutable access.
     #[allow(clippy::missing_safety_doc)]
     pub unsafe fn get_device_mut(&mut self) -> Device {
-        let mut device = Device::from_ped_device((*self.disk).dev);
-        device.is_droppable = false;
-        device
+        Device::borrowed((*self.disk).dev)
     }
 
     /// Obtains the constraint of the inner device.
@@ -276,19 +547,110 @@ impl<'a> Disk<'a> {
         }
     }
 
+    /// Whether the label supports partition names, as set by `Partition::set_name()`.
+    pub fn supports_partition_names(&self) -> bool {
+        self.supports(DiskTypeFeature::PED_DISK_TYPE_PARTITION_NAME)
+    }
+
+    /// Whether the label supports partition type IDs (ie: the system byte of msdos labels).
+    ///
+    /// Always `false` when the loaded libparted is older than 3.5, which introduced them.
+    pub fn supports_type_ids(&self) -> bool {
+        self.supports(DiskTypeFeature::PED_DISK_TYPE_PARTITION_TYPE_ID) && version_at_least(3, 5)
+    }
+
+    /// Whether the label supports partition type UUIDs (ie: the type GUID of GPT labels).
+    ///
+    /// Always `false` when the loaded libparted is older than 3.5, which introduced them.
+    pub fn supports_type_uuids(&self) -> bool {
+        self.supports(DiskTypeFeature::PED_DISK_TYPE_PARTITION_TYPE_UUID) && version_at_least(3, 5)
+    }
+
+    /// Whether the label stores a name for the disk as a whole, as set by
+    /// `Disk::set_label_name()`.
+    ///
+    /// # Note:
+    ///
+    /// libparted does not expose a disk-level name for any label, so this is currently always
+    /// `false`. The names of mac and pc98 labels belong to their partitions, and are set with
+    /// `Partition::set_name()`.
+    pub fn supports_label_name(&self) -> bool {
+        false
+    }
+
+    /// Checks if the disk's label type supports a feature.
+    pub(crate) fn supports(&self, feature: DiskTypeFeature) -> bool {
+        let type_ = DiskType {
+            type_: unsafe { (*self.disk).type_ as *mut PedDiskType },
+            phantom: PhantomData,
+        };
+        type_.check_feature(feature)
+    }
+
+    /// Whether signatures of other labels will be erased from the device when the table is
+    /// committed, as they are for a table created with `Disk::new_fresh()`.
     pub fn needs_clobber(&self) -> bool {
         unsafe { (*self.disk).needs_clobber != 0 }
     }
 
+    /// Whether libparted is in update mode, in which it tracks the changes to a table which
+    /// has been committed to the device but not yet to the operating system.
     pub fn update_mode(&self) -> bool {
         unsafe { (*self.disk).update_mode != 0 }
     }
 
+    /// Whether the partition table in memory differs from the one last read from or written
+    /// to the device, including changes made through the partitions of the disk.
+    ///
+    /// A table which was created with `Disk::new_fresh()` is dirty until it is committed.
+    pub fn is_dirty(&self) -> bool {
+        let disk = self.disk;
+        DIRTY.with(|tables| tables.borrow().contains(&disk))
+    }
+
+    /// Records that the table has been read from or written to the device, and so has no
+    /// uncommitted changes.
+    fn mark_clean(&mut self) {
+        set_dirty(self.disk, false);
+        self.device_length = self.raw_device().length();
+    }
+
+    /// Captures the state of the disk and its device, such as to decide which actions to
+    /// offer the user.
+    ///
+    /// The label revision is read from the device, and so describes the table as it was last
+    /// written. The length of the device is libparted's, so a resize is only noticed once
+    /// `Device::refresh()` has picked it up.
+    pub fn state(&self) -> Result<DiskState> {
+        let device = self.raw_device();
+        let label_revision = if self.get_disk_type_name() == Some("gpt") {
+            gpt::header_revision(&device)?
+        } else {
+            None
+        };
+
+        Ok(DiskState {
+            dirty: self.is_dirty(),
+            needs_clobber: self.needs_clobber(),
+            update_mode: self.update_mode(),
+            label_revision,
+            stale_device: device.length() != self.device_length,
+        })
+    }
+
     /// Get the state of a set flag on a disk.
     pub fn get_flag_state(&self, flag: DiskFlag) -> bool {
         unsafe { ped_disk_get_flag(self.disk, flag) != 0 }
     }
 
+    /// Returns each flag which is currently set on the disk.
+    pub fn flags(&self) -> Vec<DiskFlag> {
+        DiskFlag::all()
+            .into_iter()
+            .filter(|&flag| self.is_flag_available(flag) && self.get_flag_state(flag))
+            .collect()
+    }
+
     /// Check whether a given flag is available on a disk
     pub fn is_flag_available(&self, flag: DiskFlag) -> bool {
         unsafe { ped_disk_is_flag_available(self.disk, flag) != 0 }
@@ -301,10 +663,105 @@ impl<'a> Disk<'a> {
         }
     }
 
+    /// Iterates every partition, free space, and metadata region of the disk, in order.
+    ///
+    /// The partitions yielded are owned by the disk, and are not destroyed when dropped.
+    /// Prefer `Disk::partitions_info()` to keep a partition's metadata after the disk changes.
+    #[cfg_attr(
+        feature = "v2-api",
+        deprecated(since = "0.1.6", note = "use `partitions()` instead")
+    )]
     pub fn parts(&self) -> DiskPartIter {
         DiskPartIter(self, ptr::null_mut())
     }
 
+    /// Iterates a view of every partition, free space, and metadata region of the disk, in
+    /// order.
+    pub fn partitions(&self) -> DiskPartRefIter {
+        DiskPartRefIter(DiskPartIter(self, ptr::null_mut()))
+    }
+
+    /// Iterates an owned copy of the metadata of each active partition, in order.
+    ///
+    /// The content of each partition is detected as it is yielded, which reads from the
+    /// device.
+    pub fn partitions_info<'b>(&'b self) -> impl Iterator<Item = PartitionInfo> + 'b {
+        let has_names = self.supports(DiskTypeFeature::PED_DISK_TYPE_PARTITION_NAME);
+        DiskPartIter(self, ptr::null_mut())
+            .filter(|part| part.is_active())
+            .map(move |mut part| PartitionInfo::new(&mut part, has_names))
+    }
+
+    /// Finds the active partition named `name`, returning its number and a copy of its
+    /// metadata.
+    ///
+    /// Returns `None` if no partition has the name, or the label does not support names.
+    pub fn get_partition_by_name(&self, name: &str) -> Option<(u32, PartitionInfo)> {
+        if !self.supports_partition_names() {
+            return None;
+        }
+
+        self.partitions()
+            .find(|part| part.is_active() && part.name().as_deref() == Some(name))
+            .map(|part| {
+                let mut part = unsafe { Partition::borrowed(part.part) };
+                (part.num() as u32, PartitionInfo::new(&mut part, true))
+            })
+    }
+
+    /// Finds the partition whose unique GUID (ie: its `PARTUUID`) is `uuid`, given in RFC 4122
+    /// byte order, returning its number and a copy of its metadata.
+    ///
+    /// # Note:
+    ///
+    /// libparted does not expose unique partition GUIDs, so they are read from the GPT on the
+    /// device. Partitions which have not yet been committed cannot be found.
+    pub fn get_partition_by_uuid(&self, uuid: &[u8; 16]) -> Result<Option<(u32, PartitionInfo)>> {
+        let start = gpt::unique_guids(&self.raw_device())?
+            .into_iter()
+            .find(|&(_, guid)| guid == *uuid)
+            .map(|(start, _)| start);
+
+        let start = match start {
+            Some(start) => start,
+            None => return Ok(None),
+        };
+
+        let has_names = self.supports_partition_names();
+        Ok(self
+            .partitions()
+            .find(|part| part.is_active() && part.geom().start == start)
+            .map(|part| {
+                let mut part = unsafe { Partition::borrowed(part.part) };
+                (part.num() as u32, PartitionInfo::new(&mut part, has_names))
+            }))
+    }
+
+    /// Iterates a light-weight record of the number, file system type, geometry, and flags
+    /// of each active partition, in order.
+    ///
+    /// Unlike `Disk::partitions_info()`, nothing is read from the device. The records are
+    /// plain data, so they may be collected and kept after the disk is dropped:
+    ///
+    /// ```rust,no_run
+    /// # use libparted::{Device, Disk};
+    /// let device = Device::new("/dev/sda").unwrap();
+    /// let layout = Disk::new(&device).unwrap().iter_layout().collect::<Vec<_>>();
+    /// for entry in layout {
+    ///     println!("{}: {} to {}", entry.num, entry.geom.start, entry.geom.end);
+    /// }
+    /// ```
+    pub fn iter_layout<'b>(&'b self) -> impl Iterator<Item = LayoutEntry> + 'b {
+        self.partitions()
+            .filter(|part| part.is_active())
+            .map(|part| LayoutEntry {
+                num: part.num() as u32,
+                fs_type: part.fs_type_name().map(|name| name.to_owned()),
+                geom: part.geom(),
+                flags: part.flags(),
+            })
+    }
+
     /// Adds the supplied `part` **Partition** to the disk.
     ///
     /// **Warning**: The partition's geometry may be changed, subject to `constraint`. You could
@@ -314,165 +771,1880 @@ impl<'a> Disk<'a> {
     /// will be left unmodified) `part` is assigned a number (`part.num`) in this process.
     pub fn add_partition(&mut self, part: &mut Partition, constraint: &Constraint) -> Result<()> {
         part.is_droppable = false;
-        cvt(unsafe { ped_disk_add_partition(self.disk, part.part, constraint.constraint) })?;
+        instrument::record("add_partition", || {
+            cvt(unsafe { ped_disk_add_partition(self.disk, part.part, constraint.constraint) })
+        })?;
+        set_dirty(self.disk, true);
         Ok(())
     }
 
-    /// Get the highest available partition number on the disk.
-    pub fn get_last_partition_num(&self) -> Option<u32> {
-        match unsafe { ped_disk_get_last_partition_num(self.disk) } {
-            -1 => None,
-            num => Some(num.abs() as u32),
+    /// Creates a new partition described by `spec`, returning the number it was assigned.
+    ///
+    /// The requested boundaries are treated as approximate: they will be snapped to the
+    /// optimal alignment of the device where possible, in addition to any requirements of the
+    /// disk label. The partition is only created in-memory, and nothing is written to disk
+    /// until `disk.commit()` is called.
+    pub fn create_partition(&mut self, spec: &PartitionSpec) -> Result<u32> {
+        self.check_part_type(spec.part_type)?;
+        let (start, end) = self.resolve_extent(&spec.extent, spec.part_type)?;
+
+        let fs_type = match spec.fs_type {
+            Some(ref name) => Some(FileSystemType::get(name).ok_or_else(|| {
+                Error::new(
+                    ErrorKind::NotFound,
+                    format!("unknown file system type: {}", name),
+                )
+            })?),
+            None => None,
+        };
+
+        let device = self.raw_device();
+        let constraint = approximate_constraint(&device, start, end)?;
+
+        let mut part = Partition::new(
+            self,
+            spec.part_type,
+            fs_type.as_ref(),
+            Sector(start),
+            Sector(end),
+        )?;
+        if let Err(why) = self.add_partition(&mut part, &constraint) {
+            part.is_droppable = true;
+            return Err(why);
         }
-    }
 
-    /// Get the highest supported partition number on the disk.
-    pub fn get_max_supported_partition_count(&self) -> Option<u32> {
-        let mut supported = 0i32;
-        if unsafe { ped_disk_get_max_supported_partition_count(self.disk, &mut supported) } {
-            if supported < 0 {
-                None
-            } else {
-                Some(supported.abs() as u32)
+        let result = spec
+            .name
+            .as_ref()
+            .map_or(Ok(()), |name| part.set_name(name))
+            .and_then(|_| part.set_flags(&spec.flags));
+
+        #[cfg(feature = "parted-3-5")]
+        let result = result.and_then(|_| match spec.type_guid {
+            Some(guid) if self.supports(DiskTypeFeature::PED_DISK_TYPE_PARTITION_TYPE_UUID) => {
+                part.set_type_guid(guid)
+            }
+            _ => Ok(()),
+        });
+
+        match result {
+            Ok(()) => Ok(part.num() as u32),
+            Err(why) => {
+                unsafe {
+                    let _ = self.remove_partition_returning(part.part);
+                }
+                Err(why)
             }
-        } else {
-            None
         }
     }
 
-    /// Get the maximum number of (primary) partitions that the disk label supports.
-    pub fn get_max_primary_partition_count(&self) -> u32 {
-        unsafe { ped_disk_get_max_primary_partition_count(self.disk) as u32 }
-    }
+    /// Finds the partition which was created from `spec`, returning its number and device
+    /// path. This should be called after `disk.commit()`, as numbers may change when the
+    /// table is written (ie: logical partitions are renumbered in order of position).
+    ///
+    /// Partitions are matched by their type, by their name when `spec` has one and the label
+    /// supports names, and by the sectors they occupy. As boundaries are snapped when a
+    /// partition is created, a partition given by `PartitionExtent::Sectors` matches the
+    /// partition which overlaps the most of those sectors. Other extents may only be
+    /// resolved by a name which no other partition has.
+    pub fn resolve_partition(&self, spec: &PartitionSpec) -> Option<(u32, PathBuf)> {
+        let type_bits = (PartitionType::PED_PARTITION_LOGICAL as u32)
+            | (PartitionType::PED_PARTITION_EXTENDED as u32);
+        let wanted_bits = spec.part_type as u32 & type_bits;
+        let name = spec
+            .name
+            .as_ref()
+            .filter(|_| self.supports(DiskTypeFeature::PED_DISK_TYPE_PARTITION_NAME));
+
+        let candidates = self.partitions().filter(|part| {
+            part.is_active()
+                && part.type_bits() & type_bits == wanted_bits
+                && name.map_or(true, |name| part.name().as_ref() == Some(name))
+        });
+
+        let part = match spec.extent {
+            PartitionExtent::Sectors { start, end } => candidates
+                .map(|part| {
+                    let geom = part.geom();
+                    let overlap = geom.end.min(end) - geom.start.max(start) + 1;
+                    (overlap, part)
+                })
+                .filter(|&(overlap, _)| overlap > 0)
+                .max_by_key(|&(overlap, _)| overlap)
+                .map(|(_, part)| part),
+            // Without sectors to compare, the name must be unambiguous.
+            _ if name.is_some() => {
+                let mut candidates = candidates;
+                match (candidates.next(), candidates.next()) {
+                    (Some(part), None) => Some(part),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }?;
 
-    /// Get the maximum geometry `part` can be grown to, subject to `constraint`.
-    pub fn get_max_partition_geometry(
-        &'a self,
-        part: &Partition,
-        constraint: &Constraint,
-    ) -> Result<Geometry<'a>> {
-        cvt(unsafe {
-            ped_disk_get_max_partition_geometry(self.disk, part.part, constraint.constraint)
-        })
-        .map(Geometry::from_raw)
+        let path = part.get_path()?.to_owned();
+        Some((part.num() as u32, path))
     }
 
-    disk_fn_mut!(
-        /// Perform a sanity check on a partition table
-        ///
-        /// **NOTE**: The check performed is generic (ie: it does not depend on the label type
-        /// of the disk).
-        fn check
-    );
+    /// Checks a layout of partitions which are to be created with `Disk::create_partition()`,
+    /// returning every problem found without modifying the disk, so that frontends may
+    /// validate user input before applying it.
+    ///
+    /// Specs are placed in order, as they would be created, so a spec given by
+    /// `PartitionExtent::StartOfFree` is placed after the free space taken by earlier specs,
+    /// and a `Position::Fill` boundary extends only as far as the space they leave.
+    /// An empty list means that no problems were found.
+    ///
+    /// A layout with more primary partitions than the label allows is first chained with
+    /// `Disk::chain_logical()`, and checked as it would then be created. The issues still refer
+    /// to the specs as given, and those of the extended partition which is added are reported
+    /// against the first partition it holds. If the layout cannot be chained, it is checked as
+    /// given, and so has too many primary partitions.
+    ///
+    /// # Note:
+    ///
+    /// `LayoutIssue::MissingEsp` is only reported when the running system was booted with
+    /// UEFI, as the disk is then assumed to be the one which the system will boot from.
+    pub fn validate_layout(&self, layout: &[PartitionSpec]) -> Vec<LayoutIssue> {
+        let chain = match self.chain_logical_indexed(layout) {
+            Ok(chain) => chain,
+            Err(_) => return self.check_layout(layout),
+        };
 
-    /// Remove all identifying signatures of a partition table.
-    pub fn clobber(&mut self) -> Result<()> {
-        cvt(unsafe { ped_disk_clobber((*self.disk).dev) })?;
-        Ok(())
+        let (indices, specs): (Vec<usize>, Vec<PartitionSpec>) = chain.into_iter().unzip();
+        self.check_layout(&specs)
+            .into_iter()
+            .map(|issue| issue.map_index(|index| indices[index]))
+            .collect()
     }
 
-    disk_fn_mut!(
-        /// Writes the in-memory changes to a partition table to disk and informs
-        /// the operating system of the changes.
-        ///
-        /// NOTE: Equivalent to calling `disk.commit_to_dev()`, followed by `disk.commit_to_os()`.
-        fn commit
-    );
+    /// Checks each spec of `layout` in turn, as `Disk::validate_layout()` describes.
+    fn check_layout(&self, layout: &[PartitionSpec]) -> Vec<LayoutIssue> {
+        let free_bit = PartitionType::PED_PARTITION_FREE as u32;
+        let logical_bit = PartitionType::PED_PARTITION_LOGICAL as u32;
+        let extended_bit = PartitionType::PED_PARTITION_EXTENDED as u32;
+        let metadata_bit = PartitionType::PED_PARTITION_METADATA as u32;
+
+        let device = self.raw_device();
+        let last = device.length() as i64 - 1;
+        let sector_size = device.sector_size();
+        let max_start = self.max_partition_start_sector();
+        let max_length = self.max_partition_length();
+        let has_names = self.supports(DiskTypeFeature::PED_DISK_TYPE_PARTITION_NAME);
+        let supports_extended = self.supports(DiskTypeFeature::PED_DISK_TYPE_EXTENDED);
+        let whole = Geometry::new(&device, Sector(0), Sector(last + 1)).ok();
+        let usable = self
+            .usable_region()
+            .ok()
+            .map(|usable| (usable.start(), usable.end()));
+        let zone = device.managed_zone_sectors();
+        let alignment = self.partition_alignment().ok().map(|label| {
+            device
+                .get_optimum_alignment()
+                .and_then(|optimum| label.intersect(&optimum))
+                .unwrap_or(label)
+        });
+
+        let existing = self
+            .partitions()
+            .filter(|part| part.type_bits() & free_bit == 0)
+            .map(|part| {
+                let geom = part.geom();
+                (part.num(), part.type_bits(), geom.start, geom.end)
+            })
+            .collect::<Vec<_>>();
+
+        let mut extended = existing
+            .iter()
+            .filter(|&&(_, bits, _, _)| bits & extended_bit != 0)
+            .map(|&(_, _, start, end)| (start, end))
+            .collect::<Vec<_>>();
+
+        let mut free_primary = self.free_sectors(PartitionType::PED_PARTITION_NORMAL);
+        let mut free_logical = self.free_sectors(PartitionType::PED_PARTITION_LOGICAL);
+        let mut count = existing.iter().filter(|part| part.0 > 0).count() as u32;
+        let mut primaries = self.get_primary_partition_count();
+        let mut placed: Vec<(usize, bool, i64, i64)> = Vec::new();
+        let mut issues = Vec::new();
+
+        for (index, spec) in layout.iter().enumerate() {
+            let is_logical = spec.part_type == PartitionType::PED_PARTITION_LOGICAL;
+            let is_extended = spec.part_type == PartitionType::PED_PARTITION_EXTENDED;
+
+            if is_extended && !supports_extended {
+                issues.push(LayoutIssue::ExtendedUnsupported { index });
+            } else if is_extended && !extended.is_empty() {
+                issues.push(LayoutIssue::MultipleExtended { index });
+            }
 
-    disk_fn_mut!(
-        /// Write the changes made to the in-memory description of a partition table to the device.
-        fn commit_to_dev
-    );
+            if spec.name.is_some() && !has_names {
+                issues.push(LayoutIssue::NamesUnsupported { index });
+            }
 
-    disk_fn_mut!(
-        /// Tell the operating system kernel about the partition table layout of `disk`.
-        fn commit_to_os
-    );
+            if let Some(ref fs_type) = spec.fs_type {
+                if FileSystemType::get(fs_type).is_none() {
+                    issues.push(LayoutIssue::UnknownFileSystem {
+                        index,
+                        fs_type: fs_type.clone(),
+                    });
+                }
+            }
 
-    disk_fn_mut!(
-        /// Removes and destroys all partitions on `disk`.
-        fn delete_all
-    );
+            let free = if is_logical {
+                &mut free_logical
+            } else {
+                &mut free_primary
+            };
 
-    // Clones the disk object, returning a deep copy if it suceeds.
-    pub fn duplicate<'b>(&mut self) -> Result<Disk<'b>> {
-        cvt(unsafe { ped_disk_duplicate(self.disk) }).map(|disk| Disk {
-            disk,
-            phantom: PhantomData,
-            is_droppable: true,
-        })
-    }
+            let range = match spec.extent {
+                PartitionExtent::Sectors { start, end } => {
+                    if start < 0 || end < start || end > last {
+                        issues.push(LayoutIssue::OutsideDevice { index });
+                        continue;
+                    }
 
-    // Obtains the extended partition from the disk, if it exists.
-    pub fn extended_partition(&self) -> Option<Partition> {
-        get_optional(unsafe { ped_disk_extended_partition(self.disk) }).map(|part| {
-            let mut partition = Partition::from(part);
-            partition.is_droppable = false;
-            partition
-        })
-    }
+                    Some((start, end))
+                }
+                PartitionExtent::StartOfFree(length) => free
+                    .iter()
+                    .find(|&&(start, end)| end - start + 1 >= length)
+                    .map(|&(start, _)| (start, start + length - 1)),
+                PartitionExtent::EndOfFree(length) => free
+                    .iter()
+                    .rev()
+                    .find(|&&(start, end)| end - start + 1 >= length)
+                    .map(|&(_, end)| (end - length + 1, end)),
+                PartitionExtent::Between { start, end } => {
+                    match usable.and_then(|usable| Position::resolve(start, end, usable, free)) {
+                        Some((start, end)) if start < 0 || end > last => {
+                            issues.push(LayoutIssue::OutsideDevice { index });
+                            continue;
+                        }
+                        // Without a `Fill` boundary, the only range which does not resolve is
+                        // one which ends before it starts.
+                        None if usable.is_some()
+                            && start != Position::Fill
+                            && end != Position::Fill =>
+                        {
+                            issues.push(LayoutIssue::OutsideDevice { index });
+                            continue;
+                        }
+                        range => range,
+                    }
+                }
+                PartitionExtent::Percent { start, end } => {
+                    let sectors = usable
+                        .and_then(|usable| spec::percent_sectors(start, end, last + 1, usable));
+                    match sectors {
+                        Some((start, end)) if start <= end => Some((start, end)),
+                        _ => {
+                            issues.push(LayoutIssue::OutsideDevice { index });
+                            continue;
+                        }
+                    }
+                }
+            };
 
-    /// Get the alignment needed for partition boundaries on this disk.
-    ///
-    /// The returned alignment describes the alignment for the start sector of the
-    /// partition, for all disklabel types which require alignment, except Sun disklables, the
-    /// end sector must be aligned too. To get the end sector alignment, decrease the Alignment
-    /// offset by 1.
-    pub fn get_partition_alignment(&'a self) -> Result<Alignment<'a>> {
-        cvt(unsafe { ped_disk_get_partition_alignment(self.disk) }).map(|alignment| Alignment {
-            alignment,
-            phantom: PhantomData,
-        })
-    }
+            let (start, end) = match range {
+                Some(range) => range,
+                None => {
+                    issues.push(LayoutIssue::NoFreeSpace { index });
+                    continue;
+                }
+            };
 
-    /// Returns the partition that contains `sector`. If `sector` lies within a logical
-    /// partition, then the logical partition is returned (not the extended partition).
-    pub fn get_partition_by_sector(&'a self, sector: i64) -> Option<Partition<'a>> {
-        let part = unsafe { ped_disk_get_partition_by_sector(self.disk, sector) };
-        if part.is_null() {
-            None
-        } else {
-            let mut partition = Partition::from(part);
-            partition.is_droppable = false;
-            Some(partition)
-        }
-    }
+            layout::subtract(free, start, end);
+            if is_extended {
+                free_logical.push((start, end));
+                free_logical.sort();
+            }
 
-    /// Similar to `get_partition_by_sector`, but returns a raw pointer instead.
-    #[allow(clippy::missing_safety_doc)]
-    pub unsafe fn get_partition_by_sector_raw(&self, sector: i64) -> *mut PedPartition {
-        ped_disk_get_partition_by_sector(self.disk, sector)
-    }
+            if is_logical
+                && !extended
+                    .iter()
+                    .any(|&(first, last)| first <= start && end <= last)
+            {
+                issues.push(LayoutIssue::LogicalOutsideExtended { index });
+            }
 
-    /// Returns the partition numbered `num`.
-    pub fn get_partition(&'a self, num: u32) -> Option<Partition<'a>> {
-        get_optional(unsafe { ped_disk_get_partition(self.disk, num as i32) }).map(|part| {
-            let mut partition = Partition::from(part);
-            partition.is_droppable = false;
-            partition
-        })
-    }
+            // Logical partitions are only compared with other logical partitions, as every
+            // other partition is compared with the extended partition which holds them.
+            let overlaps = |first: i64, last: i64| first <= end && start <= last;
+            let mut overlaps_metadata = false;
+            for &(num, bits, first, last) in &existing {
+                if (bits & logical_bit != 0) != is_logical || !overlaps(first, last) {
+                    continue;
+                }
 
-    /// Similar to `get_partition`, but returns a raw pointer instead.
-    #[allow(clippy::missing_safety_doc)]
-    pub unsafe fn get_partition_raw(&self, num: u32) -> *mut PedPartition {
-        ped_disk_get_partition(self.disk, num as i32)
-    }
+                if bits & metadata_bit != 0 {
+                    overlaps_metadata = true;
+                } else if bits & extended_bit == 0 || !is_logical {
+                    issues.push(LayoutIssue::OverlapsPartition {
+                        index,
+                        num: num as u32,
+                    });
+                }
+            }
 
-    /// Get the number of primary partitions.
-    pub fn get_primary_partition_count(&self) -> u32 {
-        unsafe { ped_disk_get_primary_partition_count(self.disk) as u32 }
-    }
+            if overlaps_metadata {
+                issues.push(LayoutIssue::OverlapsMetadata { index });
+            }
 
-    /// Return the maximum representable length (in sectors) of a partition on the disk.
-    pub fn max_partition_length(&self) -> i64 {
-        unsafe { ped_disk_max_partition_length(self.disk) }
-    }
+            for &(other, other_logical, first, last) in &placed {
+                if other_logical == is_logical && overlaps(first, last) {
+                    issues.push(LayoutIssue::OverlapsSpec { index, other });
+                }
+            }
 
-    /// Return the maximum representable start sector of a partition on the disk.
-    pub fn max_partition_start_sector(&self) -> i64 {
-        unsafe { ped_disk_max_partition_start_sector(self.disk) }
-    }
+            if let PartitionExtent::Sectors { .. } = spec.extent {
+                if let (Some(alignment), Some(whole)) = (alignment.as_ref(), whole.as_ref()) {
+                    if !alignment.is_aligned(whole, start) {
+                        issues.push(LayoutIssue::Misaligned { index });
+                    }
+                }
+
+                if let Some(zone) = zone {
+                    if start % zone != 0 || (end + 1) % zone != 0 {
+                        issues.push(LayoutIssue::NotZoneAligned { index, zone });
+                    }
+                }
+            }
+
+            let length = end - start + 1;
+            if start > max_start {
+                issues.push(LayoutIssue::StartTooLarge {
+                    index,
+                    max: max_start,
+                });
+            }
+
+            if length > max_length {
+                issues.push(LayoutIssue::TooLong {
+                    index,
+                    max: max_length,
+                });
+            }
+
+            if let Some(ref fs_type) = spec.fs_type {
+                if let Some(min) = layout::minimum_size(fs_type) {
+                    if (length as u64) * sector_size < min {
+                        issues.push(LayoutIssue::TooSmall {
+                            index,
+                            fs_type: fs_type.clone(),
+                            min,
+                        });
+                    }
+                }
+            }
+
+            if is_extended {
+                extended.push((start, end));
+            }
+
+            if !is_logical {
+                primaries += 1;
+            }
+
+            count += 1;
+            placed.push((index, is_logical, start, end));
+        }
+
+        if let Ok(max) = self.max_supported_partition_count() {
+            if count > max {
+                issues.push(LayoutIssue::TooManyPartitions {
+                    requested: count,
+                    max,
+                });
+            }
+        }
+
+        let max_primaries = self.get_max_primary_partition_count();
+        if primaries > max_primaries {
+            issues.push(LayoutIssue::TooManyPrimary {
+                requested: primaries,
+                max: max_primaries,
+            });
+        }
+
+        let esp = PartitionFlag::PED_PARTITION_ESP;
+        let has_esp = layout.iter().any(layout::is_esp)
+            || self
+                .partitions()
+                .any(|part| part.is_active() && part.is_flag_available(esp) && part.get_flag(esp));
+        if !has_esp && layout::booted_with_uefi() {
+            issues.push(LayoutIssue::MissingEsp);
+        }
+
+        issues
+    }
+
+    /// Rewrites `layout` so that it fits within the primary partitions the label allows, by
+    /// placing the partitions which do not fit as logical partitions within a new extended
+    /// partition, as needed on msdos disks with more than four partitions.
+    ///
+    /// The layout is returned unchanged if it already fits, or if the label does not support
+    /// extended partitions or already has one. Otherwise, the normal partitions are ordered by
+    /// their start, and all but the first which still fit as primary partitions become
+    /// logical. The extended partition spans from the start of the first logical partition to
+    /// the end of the last, and each logical partition is moved one alignment grain further
+    /// from the one before it, to leave room for its extended boot record (EBR).
+    ///
+    /// The specs which stay primary come first, in their original order, followed by the
+    /// extended partition and the logical partitions in order, so that they may be passed
+    /// straight to `Disk::validate_layout()` and `Disk::create_partition()`.
+    ///
+    /// # Note:
+    ///
+    /// Partitions which become logical must be given by `PartitionExtent::Sectors`.
+    pub fn chain_logical(&self, layout: &[PartitionSpec]) -> Result<Vec<PartitionSpec>> {
+        self.chain_logical_indexed(layout)
+            .map(|chain| chain.into_iter().map(|(_, spec)| spec).collect())
+    }
+
+    /// Chains `layout` as `Disk::chain_logical()` does, pairing each spec of the chain with
+    /// the position of the spec it came from. The extended partition is paired with the
+    /// first partition it holds.
+    fn chain_logical_indexed(
+        &self,
+        layout: &[PartitionSpec],
+    ) -> Result<Vec<(usize, PartitionSpec)>> {
+        let normal = PartitionType::PED_PARTITION_NORMAL;
+        let slots = self
+            .get_max_primary_partition_count()
+            .saturating_sub(self.get_primary_partition_count()) as usize;
+        let mut primaries = layout
+            .iter()
+            .enumerate()
+            .filter(|&(_, spec)| spec.part_type == normal)
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+
+        if primaries.len() <= slots
+            || slots == 0
+            || !self.supports(DiskTypeFeature::PED_DISK_TYPE_EXTENDED)
+            || self.extended_partition().is_some()
+        {
+            return Ok(layout.iter().cloned().enumerate().collect());
+        }
+
+        let bounds = |spec: &PartitionSpec| match spec.extent {
+            PartitionExtent::Sectors { start, end } => Some((start, end)),
+            _ => None,
+        };
+
+        // Partitions without a fixed position sort first, so that they stay primary.
+        primaries.sort_by_key(|&index| bounds(&layout[index]).map_or(i64::MIN, |(start, _)| start));
+        let logical = primaries.split_off(slots - 1);
+
+        let device = self.raw_device();
+        let grain = device
+            .get_optimum_alignment()
+            .map_or(1, |alignment| alignment.grain_size())
+            .max(1);
+
+        let mut chained = Vec::with_capacity(logical.len());
+        let mut extended: Option<(i64, i64)> = None;
+        for &index in &logical {
+            let (start, end) = bounds(&layout[index]).ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "partition {} must be given in sectors to be made logical",
+                        index
+                    ),
+                )
+            })?;
+
+            let (first, previous) = extended.unwrap_or((start, start - 1));
+            let start = start.max(previous + 1 + grain);
+            if start > end {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "partition {} is too small to hold an extended boot record",
+                        index
+                    ),
+                ));
+            }
+
+            extended = Some((first, end));
+            let mut spec = layout[index].clone();
+            spec.extent = PartitionExtent::Sectors { start, end };
+            spec.part_type = PartitionType::PED_PARTITION_LOGICAL;
+            chained.push((index, spec));
+        }
+
+        let mut chain = layout
+            .iter()
+            .cloned()
+            .enumerate()
+            .filter(|&(index, _)| !logical.contains(&index))
+            .collect::<Vec<_>>();
+        if let Some((start, end)) = extended {
+            chain.push((
+                logical[0],
+                PartitionSpec::with_sectors(start, end)
+                    .part_type(PartitionType::PED_PARTITION_EXTENDED),
+            ));
+        }
+        chain.extend(chained);
+
+        Ok(chain)
+    }
+
+    /// Creates a logical partition described by `spec` within the extended partition,
+    /// returning the number it was assigned. The `part_type` of `spec` is ignored.
+    pub fn add_logical(&mut self, spec: &PartitionSpec) -> Result<u32> {
+        let mut spec = spec.clone();
+        spec.part_type = PartitionType::PED_PARTITION_LOGICAL;
+        self.create_partition(&spec)
+    }
+
+    /// Creates an extended partition which covers the largest region of free space outside
+    /// of any existing partition, returning the number it was assigned.
+    ///
+    /// Logical partitions may then be added within it with `Disk::add_logical()`.
+    pub fn create_extended_covering_free_space(&mut self) -> Result<u32> {
+        let (start, end) = self
+            .free_sectors(PartitionType::PED_PARTITION_NORMAL)
+            .into_iter()
+            .max_by_key(|&(start, end)| end - start)
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::NotFound,
+                    "no free space is available for an extended partition",
+                )
+            })?;
+
+        let spec = PartitionSpec::with_sectors(start, end)
+            .part_type(PartitionType::PED_PARTITION_EXTENDED);
+        self.create_partition(&spec)
+    }
+
+    /// Rejects a partition of `part_type` which the label could not accept, with an error
+    /// describing why, rather than the generic failure that libparted would give.
+    fn check_part_type(&self, part_type: PartitionType) -> Result<()> {
+        let has_extended = self.extended_partition().is_some();
+        let supports_extended = self.supports(DiskTypeFeature::PED_DISK_TYPE_EXTENDED);
+        let primaries = self.get_primary_partition_count();
+        let max_primaries = self.get_max_primary_partition_count();
+
+        let reason = match part_type {
+            PartitionType::PED_PARTITION_LOGICAL if !has_extended => {
+                "logical partitions must be placed within an extended partition".to_owned()
+            }
+            PartitionType::PED_PARTITION_EXTENDED if !supports_extended => {
+                "the disk label does not support extended partitions".to_owned()
+            }
+            PartitionType::PED_PARTITION_EXTENDED if has_extended => {
+                "the disk already has an extended partition".to_owned()
+            }
+            PartitionType::PED_PARTITION_NORMAL | PartitionType::PED_PARTITION_EXTENDED
+                if primaries >= max_primaries =>
+            {
+                let mut reason = format!(
+                    "the disk already has the maximum of {} primary partitions",
+                    max_primaries
+                );
+                if supports_extended && has_extended {
+                    reason.push_str("; use a logical partition instead");
+                }
+                reason
+            }
+            _ => return Ok(()),
+        };
+
+        Err(Error::new(ErrorKind::InvalidInput, reason))
+    }
+
+    /// Finds the first and last sectors described by `extent`.
+    fn resolve_extent(
+        &self,
+        extent: &PartitionExtent,
+        part_type: PartitionType,
+    ) -> Result<(i64, i64)> {
+        let region = match *extent {
+            PartitionExtent::Sectors { start, end } => {
+                let last = unsafe { (*(*self.disk).dev).length - 1 };
+                if start < 0 || end < start || end > last {
+                    return Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        format!("sectors {}..{} are not within the device", start, end),
+                    ));
+                }
+
+                return Ok((start, end));
+            }
+            PartitionExtent::StartOfFree(length) => self
+                .free_sectors(part_type)
+                .into_iter()
+                .find(|&(start, end)| end - start + 1 >= length)
+                .map(|(start, _)| (start, start + length - 1)),
+            PartitionExtent::EndOfFree(length) => self
+                .free_sectors(part_type)
+                .into_iter()
+                .rev()
+                .find(|&(start, end)| end - start + 1 >= length)
+                .map(|(_, end)| (end - length + 1, end)),
+            PartitionExtent::Between { start, end } => {
+                let usable = self.usable_region()?;
+                let free = self.free_sectors(part_type);
+                match Position::resolve(start, end, (usable.start(), usable.end()), &free) {
+                    Some(sectors) => {
+                        let extent = PartitionExtent::Sectors {
+                            start: sectors.0,
+                            end: sectors.1,
+                        };
+                        return self.resolve_extent(&extent, part_type);
+                    }
+                    None => None,
+                }
+            }
+            PartitionExtent::Percent { start, end } => {
+                let usable = self.usable_region()?;
+                let length = unsafe { (*(*self.disk).dev).length };
+                let (start, end) =
+                    spec::percent_sectors(start, end, length, (usable.start(), usable.end()))
+                        .ok_or_else(|| {
+                            Error::new(
+                                ErrorKind::InvalidInput,
+                                format!("{}%..{}% is not a valid span of the device", start, end),
+                            )
+                        })?;
+                let extent = PartitionExtent::Sectors { start, end };
+                return self.resolve_extent(&extent, part_type);
+            }
+        };
+
+        region.ok_or_else(|| {
+            Error::new(
+                ErrorKind::NotFound,
+                "no free region is large enough for the partition",
+            )
+        })
+    }
+
+    /// The first and last sectors of each free region where a partition of `part_type` could
+    /// be placed. Logical partitions may only be placed within the extended partition.
+    pub(crate) fn free_sectors(&self, part_type: PartitionType) -> Vec<(i64, i64)> {
+        let free = PartitionType::PED_PARTITION_FREE as u32;
+        let logical = PartitionType::PED_PARTITION_LOGICAL as u32;
+        let want_logical = part_type == PartitionType::PED_PARTITION_LOGICAL;
+
+        self.partitions()
+            .filter(|part| {
+                let bits = part.type_bits();
+                bits & free != 0 && (bits & logical != 0) == want_logical
+            })
+            .map(|part| {
+                let geom = part.geom();
+                (geom.start, geom.end)
+            })
+            .collect()
+    }
+
+    /// Returns each region of free space on the disk, in order, including free space within
+    /// the extended partition.
+    ///
+    /// Regions reserved for label metadata are never included. Regions are also trimmed to
+    /// what the label can represent, so a region beyond the largest partition start sector
+    /// supported by the label (ie: past 2 TiB on msdos disks) is omitted.
+    pub fn free_regions<'b>(&self) -> Result<Vec<Geometry<'b>>> {
+        let device = self.raw_device();
+        let max_start = self.max_partition_start_sector();
+        let max_length = self.max_partition_length();
+
+        let mut regions = self.free_sectors(PartitionType::PED_PARTITION_NORMAL);
+        regions.extend(self.free_sectors(PartitionType::PED_PARTITION_LOGICAL));
+        regions.sort();
+
+        regions
+            .into_iter()
+            .filter(|&(start, _)| start <= max_start)
+            .map(|(start, end)| {
+                let length = (end - start + 1).min(max_length);
+                Geometry::new(&device, Sector(start), Sector(length))
+            })
+            .collect()
+    }
+
+    /// Discards every region of free space on the disk, including free space within the
+    /// extended partition, with `Geometry::discard()`. Returns the number of sectors
+    /// discarded.
+    ///
+    /// The first MiB of the device is never discarded, as boot loaders (ie: GRUB on msdos
+    /// disks) may be embedded in the free space before the first partition.
+    ///
+    /// Returns an error if the partition table has changes which have not been committed, as
+    /// the free space in memory may then hold partitions which are still on the device.
+    #[cfg(feature = "discard")]
+    pub fn discard_free_space(&self) -> Result<i64> {
+        if self.is_dirty() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "the partition table has uncommitted changes, which must be committed before \
+                 its free space is discarded",
+            ));
+        }
+
+        let device = self.raw_device();
+        let reserved = (1024 * 1024 / device.sector_size() as i64).max(1);
+
+        let mut regions = self.free_sectors(PartitionType::PED_PARTITION_NORMAL);
+        regions.extend(self.free_sectors(PartitionType::PED_PARTITION_LOGICAL));
+
+        let mut discarded = 0;
+        for (start, end) in regions {
+            let start = start.max(reserved);
+            if start > end {
+                continue;
+            }
+
+            let length = end - start + 1;
+            Geometry::new(&device, Sector(start), Sector(length))?.discard()?;
+            discarded += length;
+        }
+
+        Ok(discarded)
+    }
+
+    /// Returns the largest region of free space on the disk, if there is any.
+    pub fn largest_free_region<'b>(&self) -> Result<Option<Geometry<'b>>> {
+        self.free_regions()
+            .map(|regions| regions.into_iter().max_by_key(|region| region.length()))
+    }
+
+    /// Returns the region of the device in which partitions may be placed, which excludes the
+    /// areas reserved by the label, such as the GPT headers and entries, or the first track
+    /// of an msdos disk.
+    pub fn usable_region<'b>(&self) -> Result<Geometry<'b>> {
+        let metadata = PartitionType::PED_PARTITION_METADATA as u32;
+        let (start, end) = self
+            .partitions()
+            .filter(|part| part.type_bits() & metadata == 0)
+            .map(|part| part.geom())
+            .fold(None, |range: Option<(i64, i64)>, geom| match range {
+                Some((start, end)) => Some((start.min(geom.start), end.max(geom.end))),
+                None => Some((geom.start, geom.end)),
+            })
+            .ok_or_else(|| {
+                Error::new(ErrorKind::Other, "the label leaves no room for partitions")
+            })?;
+
+        Geometry::new(&self.raw_device(), Sector(start), Sector(end - start + 1))
+    }
+
+    /// Returns the number of free sectors immediately following the partition numbered `num`,
+    /// into which it could be grown without moving any other partition.
+    pub fn gap_after(&self, num: u32) -> Result<i64> {
+        let geom = self.partition_geom(num)?;
+        Ok(self
+            .free_region_at(geom.end + 1)
+            .map_or(0, |free| free.end - geom.end))
+    }
+
+    /// Returns the number of free sectors immediately preceding the partition numbered `num`.
+    pub fn gap_before(&self, num: u32) -> Result<i64> {
+        let geom = self.partition_geom(num)?;
+        Ok(self
+            .free_region_at(geom.start - 1)
+            .map_or(0, |free| geom.start - free.start))
+    }
+
+    fn partition_geom(&self, num: u32) -> Result<GeomView> {
+        self.partition(num).map(|part| part.geom()).ok_or_else(|| {
+            Error::new(
+                ErrorKind::NotFound,
+                format!("partition {} does not exist", num),
+            )
+        })
+    }
+
+    /// The region of free space which contains `sector`, if there is one.
+    fn free_region_at(&self, sector: i64) -> Option<GeomView> {
+        let free = PartitionType::PED_PARTITION_FREE as u32;
+        if sector < 0 {
+            return None;
+        }
+
+        self.partition_by_sector(sector)
+            .filter(|part| part.type_bits() & free != 0)
+            .map(|part| part.geom())
+    }
+
+    /// Repairs a GPT whose backup header is not at the end of the device, such as after the
+    /// disk was imaged onto a larger drive, and extends the usable space to the whole device.
+    ///
+    /// The partition table is re-read from the device, accepting each fix which libparted
+    /// offers, and written back if anything was fixed. Returns whether a fix was applied.
+    ///
+    /// # Note:
+    ///
+    /// Any uncommitted changes to the disk are discarded.
+    pub fn fix_gpt_backup(&mut self) -> Result<bool> {
+        self.require_gpt()?;
+
+        let mut fixed = false;
+        let dev = unsafe { (*self.disk).dev };
+        let disk = exception::with_handler(
+            |ex| {
+                if ex.allows(ExceptionOption::PED_EXCEPTION_FIX) {
+                    fixed = true;
+                    ExceptionOption::PED_EXCEPTION_FIX
+                } else {
+                    ExceptionOption::PED_EXCEPTION_UNHANDLED
+                }
+            },
+            || cvt(unsafe { ped_disk_new(dev) }),
+        )?;
+
+        // The table which was read replaces the one held, so it is freed along with the disk,
+        // even if the fixes cannot be written. Until they are, the disk is dirty.
+        unsafe { self.replace_disk(disk) };
+        self.mark_clean();
+        set_dirty(self.disk, fixed);
+
+        if fixed {
+            self.commit_to_dev()?;
+        }
+
+        Ok(fixed)
+    }
+
+    /// Reads the protective or hybrid MBR of a GPT disk from the device.
+    pub fn hybrid_mbr(&self) -> Result<Mbr> {
+        self.require_gpt()?;
+        Mbr::read(&self.raw_device())
+    }
+
+    /// Writes a hybrid MBR to the device, which mirrors up to three of the GPT partitions.
+    ///
+    /// # Note:
+    ///
+    /// libparted rewrites the protective MBR whenever the disk is committed, so this must be
+    /// called after any commit.
+    pub fn set_hybrid_mbr(&mut self, partitions: &[MbrEntry]) -> Result<()> {
+        self.require_gpt()?;
+        let mbr = Mbr::hybrid(partitions)?;
+        let max = unsafe { (*(*self.disk).dev).length } as u64;
+        if partitions
+            .iter()
+            .any(|part| u64::from(part.start) + u64::from(part.length) > max)
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "mirrored partition extends beyond the end of the device",
+            ));
+        }
+
+        mbr.write(&mut self.raw_device())
+    }
+
+    /// Whether the device holds a protective MBR, with a single `0xEE` partition covering the
+    /// disk, as a GPT disk should. A hybrid MBR, or a disk which does not have a GPT label,
+    /// does not.
+    pub fn has_protective_mbr(&self) -> Result<bool> {
+        if self.require_gpt().is_err() {
+            return Ok(false);
+        }
+
+        Mbr::read(&self.raw_device()).map(|mbr| mbr.is_protective())
+    }
+
+    /// Sets or clears the boot flag on the protective MBR of a GPT disk (the `pmbr_boot` disk
+    /// flag), which some BIOS firmware requires before it will boot from a GPT disk.
+    ///
+    /// Returns an error if the label does not support the flag.
+    pub fn set_pmbr_boot(&mut self, state: bool) -> Result<()> {
+        let flag = DiskFlag::PED_DISK_GPT_PMBR_BOOT;
+        if !self.is_flag_available(flag) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "the pmbr_boot flag is only available on GPT disks",
+            ));
+        }
+
+        if self.set_flag(flag, state) {
+            Ok(())
+        } else {
+            Err(Error::new(
+                ErrorKind::Other,
+                "failed to set the pmbr_boot flag",
+            ))
+        }
+    }
+
+    /// Sets the name of the disk as a whole, on labels which store one.
+    ///
+    /// Returns an `InvalidInput` error if the label does not support a disk-level name, as
+    /// reported by `Disk::supports_label_name()`, rather than silently discarding it. As
+    /// libparted exposes no such name for any label, this is currently always the case.
+    pub fn set_label_name(&mut self, name: &str) -> Result<()> {
+        if !self.supports_label_name() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "unsupported: the {} label does not have a disk name to set to {:?}; \
+                     set the names of its partitions instead, where the label supports them",
+                    self.get_disk_type_name().unwrap_or("unknown"),
+                    name
+                ),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn require_gpt(&self) -> Result<()> {
+        if self.get_disk_type_name() == Some("gpt") {
+            Ok(())
+        } else {
+            Err(Error::new(
+                ErrorKind::InvalidInput,
+                "disk does not have a GPT label",
+            ))
+        }
+    }
+
+    /// Reads the first `sectors` sectors of the device, which hold the MBR or the primary GPT
+    /// header and entries, so that they may be put back with `Disk::restore_first_sectors()`
+    /// if a later clobber or commit fails.
+    ///
+    /// # Note:
+    ///
+    /// The backup GPT header at the end of the device is not included.
+    pub fn backup_first_sectors(&self, sectors: i64) -> Result<Vec<u8>> {
+        let _handle = self.handle()?;
+        let device = self.raw_device();
+        if sectors < 1 || sectors as u64 > device.length() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "cannot back up {} sectors of a device with {} sectors",
+                    sectors,
+                    device.length()
+                ),
+            ));
+        }
+
+        let mut buffer = vec![0; device.sectors_to_bytes(Sector(sectors)) as usize];
+        device.read_from_sectors(&mut buffer, 0, sectors)?;
+        Ok(buffer)
+    }
+
+    /// Writes sectors captured by `Disk::backup_first_sectors()` back to the start of the
+    /// device, and re-reads the partition table from them.
+    ///
+    /// # Note:
+    ///
+    /// Any uncommitted changes to the disk are discarded.
+    pub fn restore_first_sectors(&mut self, backup: &[u8]) -> Result<()> {
+        let mut device = self.raw_device();
+        let sector_size = device.sector_size() as usize;
+        if backup.is_empty() || backup.len() % sector_size != 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "backup of {} bytes is not a whole number of {} byte sectors",
+                    backup.len(),
+                    sector_size
+                ),
+            ));
+        }
+
+        let sectors = (backup.len() / sector_size) as i64;
+        {
+            let _handle = self.handle()?;
+            device.write_to_sectors(backup, 0, sectors)?;
+            device.sync()?;
+        }
+        self.reread()
+    }
+
+    /// Discards any uncommitted changes by reading the partition table from the device again.
+    pub(crate) fn reread(&mut self) -> Result<()> {
+        let disk = cvt(unsafe { ped_disk_new((*self.disk).dev) })?;
+        unsafe { self.replace_disk(disk) };
+        self.mark_clean();
+        Ok(())
+    }
+
+    /// Replaces the table held by the disk with `disk`, freeing the table it replaces.
+    ///
+    /// The replaced table was always allocated for this disk, so it is freed even when the
+    /// disk is not droppable, as it would otherwise be leaked.
+    ///
+    /// # Safety
+    ///
+    /// `disk` must be a table of the same device, which is not owned by anything else.
+    unsafe fn replace_disk(&mut self, disk: *mut PedDisk) {
+        set_dirty(self.disk, false);
+        ped_disk_destroy(self.disk);
+        self.disk = disk;
+    }
+
+    /// Captures the label type and every active partition on the disk, so that the partition
+    /// table may later be recreated with `Disk::restore()`.
+    ///
+    /// # Note:
+    ///
+    /// Partition type UUIDs are only captured with the `parted-3-5` feature. The unique
+    /// partition GUIDs of GPT disks are never captured, as libparted only exposes them from
+    /// 3.6 onwards, with `ped_partition_get_uuid()`.
+    pub fn dump(&self) -> PartitionTableDump {
+        let sector_size = unsafe { (*(*self.disk).dev).sector_size as u64 };
+        let has_names = self.supports(DiskTypeFeature::PED_DISK_TYPE_PARTITION_NAME);
+
+        let partitions = self
+            .partitions()
+            .filter(|part| part.is_active())
+            .map(|part| dump_partition(&part, has_names))
+            .collect();
+
+        PartitionTableDump {
+            label: self.get_disk_type_name().unwrap_or_default().to_owned(),
+            sector_size,
+            flags: self.flags(),
+            partitions,
+        }
+    }
+
+    /// Recreates a partition which was removed with
+    /// `Disk::remove_partition_by_number_returning()` or friends, with its exact geometry,
+    /// number, file system type, name, and flags.
+    ///
+    /// The sectors it occupied must still be free.
+    pub fn restore_partition(&mut self, removed: &RemovedPartition) -> Result<()> {
+        self.add_dumped(&removed.partition, false)
+    }
+
+    /// Creates a new partition table on `device` from `dump`, with the same disk flags, and
+    /// recreates each partition with its exact geometry, number, file system type, name, and
+    /// flags.
+    ///
+    /// As with `Disk::new_fresh()`, nothing is written to disk until `disk.commit()` is called.
+    ///
+    /// # Note:
+    ///
+    /// Logical partitions are renumbered by libparted in the order of their position on the
+    /// disk. Type UUIDs are only restored with the `parted-3-5` feature.
+    pub fn restore(device: &'a Device, dump: &PartitionTableDump) -> Result<Disk<'a>> {
+        if device.sector_size() != dump.sector_size {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "dump was taken from a device with {}-byte sectors, but this device has {}-byte sectors",
+                    dump.sector_size,
+                    device.sector_size()
+                ),
+            ));
+        }
+
+        let type_ = DiskType::from_table_type(PartitionTableType::Other(dump.label.clone()))
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::NotFound,
+                    format!("unknown disk label type: {}", dump.label),
+                )
+            })?;
+
+        let mut disk = Disk::new_fresh(device, type_)?;
+
+        for flag in DiskFlag::all() {
+            if disk.is_flag_available(flag) {
+                disk.set_flag(flag, dump.flags.contains(&flag));
+            }
+        }
+
+        // Primary and extended partitions must exist before any logical partitions.
+        let mut parts = dump.partitions.iter().collect::<Vec<_>>();
+        parts.sort_by_key(|part| {
+            (
+                part.part_type == PartitionType::PED_PARTITION_LOGICAL,
+                part.num,
+            )
+        });
+
+        for spec in parts {
+            disk.add_dumped(spec, false)?;
+        }
+
+        Ok(disk)
+    }
+
+    /// Recreates the partition described by `spec`, with its exact geometry and number.
+    ///
+    /// Flags which the label does not support on the partition are skipped if `skip_flags`
+    /// is set, and are otherwise an error.
+    fn add_dumped(&mut self, spec: &PartitionDump, skip_flags: bool) -> Result<()> {
+        let fs_type = match spec.fs_type {
+            Some(ref name) => Some(FileSystemType::get(name).ok_or_else(|| {
+                Error::new(
+                    ErrorKind::NotFound,
+                    format!("unknown file system type: {}", name),
+                )
+            })?),
+            None => None,
+        };
+
+        let device = self.raw_device();
+        let geom = Geometry::new(&device, Sector(spec.start), Sector(spec.length))?;
+        let constraint = geom
+            .exact()
+            .ok_or_else(|| Error::new(ErrorKind::Other, "failed to create an exact constraint"))?;
+
+        let mut part = Partition::new(
+            self,
+            spec.part_type,
+            fs_type.as_ref(),
+            Sector(spec.start),
+            Sector(spec.end()),
+        )?;
+        // Labels keep the number of a partition which already has one, rather than
+        // assigning the lowest free number.
+        unsafe { (*part.part).num = spec.num as i32 };
+        if let Err(why) = self.add_partition(&mut part, &constraint) {
+            part.is_droppable = true;
+            return Err(why);
+        }
+
+        if let Some(ref name) = spec.name_raw {
+            part.set_name_raw(name)?;
+        } else if let Some(ref name) = spec.name {
+            part.set_name(name)?;
+        }
+
+        for &flag in &spec.flags {
+            if !skip_flags || part.is_flag_available(flag) {
+                part.set_flag(flag, true)?;
+            }
+        }
+
+        #[cfg(feature = "parted-3-5")]
+        {
+            if let Some(ref uuid) = spec.type_uuid {
+                part.set_type_uuid(uuid)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the label with a new, in-memory label of type `type_`, and recreates each
+    /// partition within it with the same geometry, file system type, name, and flags, as
+    /// `sgdisk --mbrtogpt` does.
+    ///
+    /// Logical partitions become primary partitions on labels without extended partitions.
+    /// Names and type UUIDs are dropped where the new label cannot store them. If any
+    /// partition cannot be represented by the new label, such as when it overlaps the area
+    /// which GPT reserves for its backup header, the disk is left unchanged and an error is
+    /// returned.
+    ///
+    /// As with `Disk::new_fresh()`, nothing is written to disk until `disk.commit()` is called.
+    pub fn convert_label(&mut self, type_: DiskType, options: ConvertOptions) -> Result<()> {
+        let dump = self.dump();
+        let mut fresh = Disk {
+            disk: cvt(unsafe { ped_disk_new_fresh((*self.disk).dev, type_.type_) })?,
+            phantom: PhantomData,
+            is_droppable: true,
+            handle: None,
+            device_length: self.device_length,
+        };
+
+        for flag in DiskFlag::all() {
+            if fresh.is_flag_available(flag) && dump.flags.contains(&flag) {
+                fresh.set_flag(flag, true);
+            }
+        }
+
+        let extended = fresh.supports(DiskTypeFeature::PED_DISK_TYPE_EXTENDED);
+        let has_names = fresh.supports(DiskTypeFeature::PED_DISK_TYPE_PARTITION_NAME);
+        let has_uuids = fresh.supports(DiskTypeFeature::PED_DISK_TYPE_PARTITION_TYPE_UUID);
+
+        let mut parts = dump
+            .partitions
+            .into_iter()
+            .filter(|part| extended || part.part_type != PartitionType::PED_PARTITION_EXTENDED)
+            .collect::<Vec<_>>();
+        parts.sort_by_key(|part| part.start);
+
+        for (index, part) in parts.iter_mut().enumerate() {
+            if !extended {
+                part.part_type = PartitionType::PED_PARTITION_NORMAL;
+            }
+            if !has_names {
+                part.name = None;
+                part.name_raw = None;
+            }
+            if !has_uuids {
+                part.type_uuid = None;
+            }
+            if options.renumber {
+                part.num = index as u32 + 1;
+            }
+        }
+
+        // Primary and extended partitions must exist before any logical partitions.
+        parts.sort_by_key(|part| {
+            (
+                part.part_type == PartitionType::PED_PARTITION_LOGICAL,
+                part.num,
+            )
+        });
+
+        // Report why libparted refuses a partition, rather than asking the user.
+        let mut refusal = None;
+        let result = exception::with_handler(
+            |ex| {
+                if refusal.is_none() {
+                    refusal = Some(ex.message.clone());
+                }
+
+                if ex.allows(ExceptionOption::PED_EXCEPTION_CANCEL) {
+                    ExceptionOption::PED_EXCEPTION_CANCEL
+                } else {
+                    ExceptionOption::PED_EXCEPTION_UNHANDLED
+                }
+            },
+            || {
+                parts.iter().try_for_each(|part| {
+                    fresh
+                        .add_dumped(part, options.drop_unsupported_flags)
+                        .map_err(|why| {
+                            Error::new(
+                                why.kind(),
+                                format!("partition {} cannot be converted: {}", part.num, why),
+                            )
+                        })
+                })
+            },
+        );
+
+        if let Err(why) = result {
+            return Err(match refusal {
+                Some(message) => Error::new(why.kind(), format!("{}: {}", why, message)),
+                None => why,
+            });
+        }
+
+        fresh.is_droppable = false;
+        unsafe { self.replace_disk(fresh.disk) };
+        set_dirty(self.disk, true);
+        Ok(())
+    }
+
+    /// Get the highest available partition number on the disk.
+    #[cfg_attr(
+        feature = "v2-api",
+        deprecated(since = "0.1.6", note = "use `last_partition_num()` instead")
+    )]
+    pub fn get_last_partition_num(&self) -> Option<u32> {
+        self.last_partition_num().ok().and_then(|num| num)
+    }
+
+    /// The highest number of any partition on the disk, or `None` if the disk has no
+    /// partitions.
+    pub fn last_partition_num(&self) -> Result<Option<u32>> {
+        let disk = self.disk;
+        exception::capture("failed to get the last partition number", || {
+            match unsafe { ped_disk_get_last_partition_num(disk) } {
+                -1 => Some(None),
+                num if num >= 0 => Some(Some(num as u32)),
+                _ => None,
+            }
+        })
+    }
+
+    /// Get the highest supported partition number on the disk.
+    #[cfg_attr(
+        feature = "v2-api",
+        deprecated(
+            since = "0.1.6",
+            note = "use `max_supported_partition_count()` instead"
+        )
+    )]
+    pub fn get_max_supported_partition_count(&self) -> Option<u32> {
+        self.max_supported_partition_count().ok()
+    }
+
+    /// The highest partition number which the label supports.
+    ///
+    /// Returns an error if the label does not report a limit, or the limit could not be
+    /// determined, with the reason libparted gave if there was one.
+    pub fn max_supported_partition_count(&self) -> Result<u32> {
+        let disk = self.disk;
+        let label = self.get_disk_type_name().unwrap_or("unknown").to_owned();
+        exception::capture(
+            &format!(
+                "the {} label does not report how many partitions it supports",
+                label
+            ),
+            || {
+                let mut supported = 0i32;
+                let found =
+                    unsafe { ped_disk_get_max_supported_partition_count(disk, &mut supported) };
+                Some(supported as u32).filter(|_| found && supported >= 0)
+            },
+        )
+    }
+
+    /// Get the maximum number of (primary) partitions that the disk label supports.
+    pub fn get_max_primary_partition_count(&self) -> u32 {
+        unsafe { ped_disk_get_max_primary_partition_count(self.disk) as u32 }
+    }
+
+    /// Get the maximum geometry `part` can be grown to, subject to `constraint`.
+    pub fn get_max_partition_geometry(
+        &'a self,
+        part: &Partition,
+        constraint: &Constraint,
+    ) -> Result<Geometry<'a>> {
+        cvt(unsafe {
+            ped_disk_get_max_partition_geometry(self.disk, part.part, constraint.constraint)
+        })
+        .map(Geometry::from_raw)
+    }
+
+    disk_fn_mut!(
+        /// Perform a sanity check on a partition table
+        ///
+        /// **NOTE**: The check performed is generic (ie: it does not depend on the label type
+        /// of the disk).
+        fn check
+    );
+
+    /// Performs the same sanity check as `Disk::check()`, returning each inconsistency that
+    /// libparted reported rather than stopping at the first.
+    ///
+    /// An empty list means that no problems were found. An error is only returned if the
+    /// check failed without reporting why.
+    pub fn check_issues(&self) -> Result<Vec<DiskCheckIssue>> {
+        let mut issues = Vec::new();
+        let result = exception::with_handler(
+            |ex| {
+                issues.push(DiskCheckIssue::from_exception(ex));
+                if ex.allows(ExceptionOption::PED_EXCEPTION_IGNORE) {
+                    ExceptionOption::PED_EXCEPTION_IGNORE
+                } else if ex.allows(ExceptionOption::PED_EXCEPTION_CANCEL) {
+                    ExceptionOption::PED_EXCEPTION_CANCEL
+                } else {
+                    ExceptionOption::PED_EXCEPTION_UNHANDLED
+                }
+            },
+            || cvt(unsafe { check(self.disk) }),
+        );
+
+        match result {
+            Err(why) if issues.is_empty() => Err(why),
+            _ => Ok(issues),
+        }
+    }
+
+    /// Remove all identifying signatures of a partition table.
+    pub fn clobber(&mut self) -> Result<()> {
+        cvt(unsafe { ped_disk_clobber((*self.disk).dev) })?;
+        Ok(())
+    }
+
+    /// Writes the in-memory changes to a partition table to disk and informs
+    /// the operating system of the changes.
+    ///
+    /// NOTE: Equivalent to calling `disk.commit_to_dev()`, followed by `disk.commit_to_os()`.
+    pub fn commit(&mut self) -> Result<()> {
+        let disk = self.disk;
+        instrument::record("commit", || cvt(unsafe { commit(disk) }))?;
+        self.mark_clean();
+        Ok(())
+    }
+
+    /// Write the changes made to the in-memory description of a partition table to the device.
+    pub fn commit_to_dev(&mut self) -> Result<()> {
+        let disk = self.disk;
+        instrument::record("commit_to_dev", || cvt(unsafe { commit_to_dev(disk) }))?;
+        self.mark_clean();
+        Ok(())
+    }
+
+    disk_fn_mut!(
+        /// Tell the operating system kernel about the partition table layout of `disk`.
+        fn commit_to_os
+    );
+
+    /// Commits the changes to the device and the operating system, reporting the start and
+    /// end of the commit, and any exceptions raised, to `sink`.
+    pub fn commit_with_progress(&mut self, sink: Option<&mut dyn ProgressSink>) -> Result<()> {
+        events::observe(sink, |_| self.commit())
+    }
+
+    /// Commits the changes to the device and then to the operating system, running `hooks` at
+    /// each stage.
+    pub fn commit_with(&mut self, mut hooks: CommitHooks) -> Result<()> {
+        self.commit_to_dev()?;
+        for hook in &mut hooks.after_dev {
+            hook(self)?;
+        }
+
+        self.commit_to_os()?;
+        for hook in &mut hooks.after_os {
+            hook(self)?;
+        }
+
+        Ok(())
+    }
+
+    /// Commits the changes to the device and, if `options.inform_kernel` is set, to the
+    /// operating system.
+    ///
+    /// Unless `options.force` is set, the table is not written if it would change or remove
+    /// any partition which is in use on the device, and an error listing those partitions is
+    /// returned instead.
+    pub fn commit_with_options(&mut self, options: CommitOptions) -> Result<()> {
+        if !options.force {
+            let busy = self.busy_on_device()?;
+            if !busy.is_empty() {
+                let nums = busy.iter().map(|num| num.to_string()).collect::<Vec<_>>();
+                return Err(Error::new(
+                    ErrorKind::Other,
+                    format!(
+                        "partitions {} are in use, and would be changed by the commit",
+                        nums.join(", ")
+                    ),
+                ));
+            }
+        }
+
+        self.commit_to_dev()?;
+        if !options.inform_kernel {
+            Ok(())
+        } else if options.force {
+            self.commit_to_os_detailed().map(|_| ())
+        } else {
+            self.commit_to_os()
+        }
+    }
+
+    /// The numbers of the partitions in the table on the device which are in use, and which
+    /// the table in memory changes or removes.
+    ///
+    /// A device without a label libparted recognizes has no partitions to be busy, but any
+    /// other failure to read the table on the device is an error.
+    fn busy_on_device(&self) -> Result<Vec<u32>> {
+        let dev = unsafe { (*self.disk).dev };
+        let fallback = "failed to read the partition table on the device";
+        let probed = exception::capture(fallback, || unsafe { Some(ped_disk_probe(dev)) })?;
+        if probed.is_null() {
+            return Ok(Vec::new());
+        }
+
+        let disk = exception::capture(fallback, || {
+            Some(unsafe { ped_disk_new(dev) }).filter(|disk| !disk.is_null())
+        })?;
+        let on_device = Disk {
+            disk,
+            phantom: PhantomData,
+            is_droppable: true,
+            handle: None,
+            device_length: self.device_length,
+        };
+
+        let kept = |num: u32, geom: GeomView| {
+            self.partition(num)
+                .map_or(false, |part| part.is_active() && part.geom() == geom)
+        };
+
+        Ok(on_device
+            .partitions()
+            .filter(|part| part.is_active() && part.is_busy())
+            .filter(|part| !kept(part.num() as u32, part.geom()))
+            .map(|part| part.num() as u32)
+            .collect())
+    }
+
+    /// Applies each of `ops` to the partition table in memory, then writes the result to the
+    /// device and informs the operating system of it once, and re-reads the table that was
+    /// written.
+    ///
+    /// Committing once, rather than after each operation, avoids repeatedly asking the kernel
+    /// to re-read the table, and the flood of udev events that follows each re-read.
+    ///
+    /// If any operation fails, or the table cannot be written to the device, none of the
+    /// changes are kept. If the table is written but the operating system cannot be informed
+    /// of it, such as when a partition is in use, the changes are kept, as they are already on
+    /// the device, and the error says so. The operating system may then be informed later with
+    /// `Disk::commit_to_os()`.
+    ///
+    /// # Note:
+    ///
+    /// Partition numbers refer to the table as it is when that operation is applied, so
+    /// removing a logical partition renumbers the logical partitions after it.
+    pub fn apply_batch(&mut self, ops: Vec<DiskOp>) -> Result<()> {
+        {
+            let mut transaction = self.begin();
+            for (index, op) in ops.iter().enumerate() {
+                transaction.apply_op(op).map_err(|why| {
+                    Error::new(
+                        why.kind(),
+                        format!("operation {} ({}) failed: {}", index, op, why),
+                    )
+                })?;
+            }
+
+            transaction.commit_to_dev_only()?;
+        }
+
+        let informed = self.commit_to_os();
+        let reread = self.reread();
+        informed.map_err(|why| {
+            Error::new(
+                why.kind(),
+                format!(
+                    "the partition table was written to the device, but the operating system \
+                     could not be informed of it: {}",
+                    why
+                ),
+            )
+        })?;
+        reread
+    }
+
+    fn apply_op(&mut self, op: &DiskOp) -> Result<()> {
+        match *op {
+            DiskOp::Create(ref spec) => self.create_partition(spec).map(|_| ()),
+            DiskOp::Remove(num) => self.remove_partition_by_number_returning(num).map(|_| ()),
+            DiskOp::Resize { num, start, end } => {
+                let mut part = unsafe {
+                    Partition::borrowed(cvt(ped_disk_get_partition(self.disk, num as i32))?)
+                };
+                let device = self.raw_device();
+                let geom = Geometry::new(&device, Sector(start), Sector(end - start + 1))?;
+                let constraint = geom.exact().ok_or_else(|| {
+                    Error::new(ErrorKind::Other, "failed to create an exact constraint")
+                })?;
+                self.set_partition_geometry(&mut part, &constraint, start, end)
+            }
+        }
+    }
+
+    /// Commits the changes to the device and the operating system, retrying according to
+    /// `policy` while the device is busy.
+    pub fn commit_with_retry(&mut self, policy: &RetryPolicy) -> Result<()> {
+        policy.run(|| self.commit())
+    }
+
+    /// Informs the operating system of the partition table, retrying according to `policy`
+    /// while the device is busy.
+    pub fn commit_to_os_with_retry(&mut self, policy: &RetryPolicy) -> Result<()> {
+        policy.run(|| self.commit_to_os())
+    }
+
+    /// Tells the operating system kernel about the partition table layout of `disk`, returning
+    /// the number of each partition which the kernel refused to update because it is in use.
+    ///
+    /// Unlike `Disk::commit_to_os()`, a partition being in use is not treated as an error, so
+    /// callers may ask the user to unmount the listed partitions and try again.
+    ///
+    /// # Note:
+    ///
+    /// The refusal is recognised as the only error of a commit to the operating system which
+    /// may be ignored. The partitions are read from libparted's message when it is in English,
+    /// and are otherwise those which libparted reports as busy, as the message may have been
+    /// translated for the current locale.
+    pub fn commit_to_os_detailed(&mut self) -> Result<Vec<u32>> {
+        let mut refusal = None;
+        let disk = self.disk;
+        let result = exception::with_handler(
+            |ex| match ex.type_ {
+                ExceptionType::PED_EXCEPTION_ERROR
+                    if ex.allows(ExceptionOption::PED_EXCEPTION_IGNORE)
+                        && ex.allows(ExceptionOption::PED_EXCEPTION_CANCEL) =>
+                {
+                    refusal = Some(ex.message.clone());
+                    ExceptionOption::PED_EXCEPTION_IGNORE
+                }
+                _ => ExceptionOption::PED_EXCEPTION_UNHANDLED,
+            },
+            || instrument::record("commit_to_os", || cvt(unsafe { commit_to_os(disk) })),
+        );
+
+        match refusal {
+            Some(message) => Ok(busy::refused_partitions(&message).unwrap_or_else(|| {
+                self.partitions()
+                    .filter(|part| part.is_active() && part.is_busy())
+                    .map(|part| part.num() as u32)
+                    .collect()
+            })),
+            None => result.map(|_| Vec::new()),
+        }
+    }
+
+    /// Tells the kernel about the partition table layout of `disk`, as with
+    /// `Disk::commit_to_os()`, and if that fails, updates each partition which differs from
+    /// the kernel's view individually, as `partx --update` would.
+    ///
+    /// Partitions which are in use cannot be removed or moved, but may still be grown or
+    /// shrunk in place. The returned report describes what the kernel sees afterwards, and
+    /// which partitions still differ from the partition table.
+    pub fn commit_to_os_with_fallback(&mut self) -> Result<KernelSyncReport> {
+        let device = self.raw_device();
+        let sector_size = device.sector_size();
+        let bytes = |sectors: i64| sectors as u64 * sector_size;
+
+        let wanted = self.kernel_layout(sector_size);
+
+        let committed = match self.commit_to_os_detailed() {
+            Ok(ref refused) => refused.is_empty(),
+            Err(_) => false,
+        };
+
+        if !committed {
+            let file = std::fs::File::open(device.path())?;
+            let current = kernel::partitions(device.path(), sector_size)?;
+
+            for old in &current {
+                if wanted.iter().all(|&(num, _, _)| num != old.num) {
+                    let _ = kernel::remove(&file, old.num);
+                }
+            }
+
+            for &(num, start, length) in &wanted {
+                match current.iter().find(|old| old.num == num) {
+                    Some(old) if bytes(old.start) == start && bytes(old.length) == length => (),
+                    Some(old) if bytes(old.start) == start => {
+                        if kernel::resize(&file, num, start, length).is_err()
+                            && kernel::remove(&file, num).is_ok()
+                        {
+                            let _ = kernel::add(&file, num, start, length);
+                        }
+                    }
+                    Some(_) => {
+                        if kernel::remove(&file, num).is_ok() {
+                            let _ = kernel::add(&file, num, start, length);
+                        }
+                    }
+                    None => {
+                        let _ = kernel::add(&file, num, start, length);
+                    }
+                }
+            }
+        }
+
+        let kernel = kernel::partitions(device.path(), sector_size)?;
+        let mismatched = kernel::mismatched(&wanted, &kernel, sector_size);
+
+        Ok(KernelSyncReport { kernel, mismatched })
+    }
+
+    /// Whether the partitions the kernel currently exposes for the device, as read from
+    /// sysfs, match the partition table held in memory.
+    ///
+    /// If this returns `false`, the table must be committed with `Disk::commit_to_os()` (or
+    /// the system rebooted) before the kernel will see the new layout.
+    ///
+    /// Like `Disk::commit_to_os_with_fallback()`, this does not require the `sysfs` feature,
+    /// which only concerns the I/O topology of devices.
+    pub fn kernel_in_sync(&self) -> Result<bool> {
+        let device = self.raw_device();
+        let sector_size = device.sector_size();
+        let kernel = kernel::partitions(device.path(), sector_size)?;
+        let wanted = self.kernel_layout(sector_size);
+        Ok(kernel::mismatched(&wanted, &kernel, sector_size).is_empty())
+    }
+
+    /// The number, start, and length in bytes of each partition, as the kernel should see it.
+    fn kernel_layout(&self, sector_size: u64) -> Vec<(u32, u64, u64)> {
+        let bytes = |sectors: i64| sectors as u64 * sector_size;
+        let extended = PartitionType::PED_PARTITION_EXTENDED as u32;
+        self.partitions()
+            .filter(|part| part.is_active())
+            .map(|part| {
+                let geom = part.geom();
+                // The kernel represents an extended partition as a stub of 1024 bytes, or one
+                // sector on devices with larger sectors, but no longer than the partition
+                // itself, as in block/partitions/msdos.c.
+                let length = if part.type_bits() & extended == 0 {
+                    bytes(geom.length)
+                } else {
+                    bytes(geom.length).min(sector_size.max(1024))
+                };
+                (part.num() as u32, bytes(geom.start), length)
+            })
+            .collect()
+    }
+
+    /// Returns each partition which is mounted, in use as swap, or otherwise held open, and
+    /// which the kernel would therefore refuse to update.
+    #[cfg(feature = "proc")]
+    pub fn busy_partitions(&self) -> Vec<BusyPartition> {
+        self.partitions()
+            .filter(|part| part.is_active() && part.is_busy())
+            .map(|part| {
+                let path = part.get_path().map(|path| path.to_owned());
+                BusyPartition::new(part.num() as u32, path)
+            })
+            .collect()
+    }
+
+    /// Removes and destroys all partitions on `disk`.
+    pub fn delete_all(&mut self) -> Result<()> {
+        let disk = self.disk;
+        instrument::record("delete_all", || cvt(unsafe { delete_all(disk) }))?;
+        set_dirty(disk, true);
+        Ok(())
+    }
+
+    /// Clones the disk object, returning a deep copy if it suceeds.
+    ///
+    /// The copy refers to the same device as the original, so it may outlive the original,
+    /// but not the device which both were read from.
+    ///
+    /// ```rust,no_run
+    /// # use libparted::{Device, Disk};
+    /// let device = Device::new("/dev/sda").unwrap();
+    /// let copy = {
+    ///     let disk = Disk::new(&device).unwrap();
+    ///     disk.duplicate().unwrap()
+    /// };
+    /// copy.print();
+    /// ```
+    pub fn duplicate(&self) -> Result<Disk<'a>> {
+        let disk = cvt(unsafe { ped_disk_duplicate(self.disk) })?;
+        set_dirty(disk, self.is_dirty());
+        Ok(Disk {
+            disk,
+            phantom: PhantomData,
+            is_droppable: true,
+            handle: self.handle.clone(),
+            device_length: self.device_length,
+        })
+    }
+
+    // Obtains the extended partition from the disk, if it exists.
+    pub fn extended_partition(&self) -> Option<Partition> {
+        get_optional(unsafe { ped_disk_extended_partition(self.disk) })
+            .map(|part| unsafe { Partition::borrowed(part) })
+    }
+
+    /// Get the alignment needed for partition boundaries on this disk.
+    ///
+    /// The returned alignment describes the alignment for the start sector of the
+    /// partition, for all disklabel types which require alignment, except Sun disklables, the
+    /// end sector must be aligned too. To get the end sector alignment, decrease the Alignment
+    /// offset by 1.
+    pub fn get_partition_alignment(&'a self) -> Result<Alignment<'a>> {
+        self.partition_alignment()
+    }
+
+    fn partition_alignment<'b>(&self) -> Result<Alignment<'b>> {
+        cvt(unsafe { ped_disk_get_partition_alignment(self.disk) }).map(|alignment| Alignment {
+            alignment,
+            phantom: PhantomData,
+        })
+    }
+
+    /// Get the alignment needed for the end sector of partitions on this disk, which is the
+    /// partition alignment with its offset decreased by one.
+    pub fn get_partition_end_alignment<'b>(&self) -> Result<Alignment<'b>> {
+        let start = self.partition_alignment()?;
+        Alignment::new(start.offset() - 1, start.grain_size())
+    }
+
+    /// Creates a constraint for a partition which should occupy roughly `start` to `end`.
+    ///
+    /// Each boundary may be moved by up to one grain of alignment, so that the start and end
+    /// of the partition satisfy both the alignment required by the label and the optimum
+    /// alignment of the device.
+    pub fn aligned_constraint_for_range<'b>(&self, start: i64, end: i64) -> Result<Constraint<'b>> {
+        let device = self.raw_device();
+        let label = self.partition_alignment()?;
+        let start_align = device
+            .get_optimum_alignment()
+            .and_then(|optimum| label.intersect(&optimum))
+            .unwrap_or(label);
+        let end_align = Alignment::new(start_align.offset() - 1, start_align.grain_size())?;
+
+        let grain = start_align.grain_size().max(1);
+        let last = device.length() as i64 - 1;
+        let range = |sector: i64| {
+            let low = (sector - grain).max(0);
+            let high = (sector + grain).min(last);
+            Geometry::new(&device, Sector(low), Sector(high - low + 1))
+        };
+
+        Constraint::new(
+            &start_align,
+            &end_align,
+            &range(start)?,
+            &range(end)?,
+            Sector(1),
+            Sector(device.length() as i64),
+        )
+    }
+
+    /// Returns the partition that contains `sector`. If `sector` lies within a logical
+    /// partition, then the logical partition is returned (not the extended partition).
+    #[cfg_attr(
+        feature = "v2-api",
+        deprecated(since = "0.1.6", note = "use `partition_by_sector()` instead")
+    )]
+    pub fn get_partition_by_sector(&'a self, sector: i64) -> Option<Partition<'a>> {
+        let part = unsafe { ped_disk_get_partition_by_sector(self.disk, sector) };
+        if part.is_null() {
+            None
+        } else {
+            Some(unsafe { Partition::borrowed(part) })
+        }
+    }
+
+    /// Similar to `get_partition_by_sector`, but returns a raw pointer instead.
+    #[allow(clippy::missing_safety_doc)]
+    pub unsafe fn get_partition_by_sector_raw(&self, sector: i64) -> *mut PedPartition {
+        ped_disk_get_partition_by_sector(self.disk, sector)
+    }
+
+    /// Returns the partition numbered `num`.
+    #[cfg_attr(
+        feature = "v2-api",
+        deprecated(since = "0.1.6", note = "use `partition()` instead")
+    )]
+    pub fn get_partition(&'a self, num: u32) -> Option<Partition<'a>> {
+        get_optional(unsafe { ped_disk_get_partition(self.disk, num as i32) })
+            .map(|part| unsafe { Partition::borrowed(part) })
+    }
+
+    /// Returns a view of the partition numbered `num`.
+    pub fn partition(&self, num: u32) -> Option<PartitionRef> {
+        get_optional(unsafe { ped_disk_get_partition(self.disk, num as i32) })
+            .map(|part| unsafe { PartitionRef::from_ped_partition(part) })
+    }
+
+    /// Returns a view of the partition that contains `sector`. If `sector` lies within a
+    /// logical partition, then the logical partition is returned (not the extended partition).
+    pub fn partition_by_sector(&self, sector: i64) -> Option<PartitionRef> {
+        get_optional(unsafe { ped_disk_get_partition_by_sector(self.disk, sector) })
+            .map(|part| unsafe { PartitionRef::from_ped_partition(part) })
+    }
+
+    /// Similar to `get_partition`, but returns a raw pointer instead.
+    #[allow(clippy::missing_safety_doc)]
+    pub unsafe fn get_partition_raw(&self, num: u32) -> *mut PedPartition {
+        ped_disk_get_partition(self.disk, num as i32)
+    }
+
+    /// Get the number of primary partitions.
+    pub fn get_primary_partition_count(&self) -> u32 {
+        unsafe { ped_disk_get_primary_partition_count(self.disk) as u32 }
+    }
+
+    /// Return the maximum representable length (in sectors) of a partition on the disk.
+    pub fn max_partition_length(&self) -> i64 {
+        unsafe { ped_disk_max_partition_length(self.disk) }
+    }
+
+    /// Return the maximum representable start sector of a partition on the disk.
+    pub fn max_partition_start_sector(&self) -> i64 {
+        unsafe { ped_disk_max_partition_start_sector(self.disk) }
+    }
 
     /// Grow the supplied `part` to the maximimum size possible, subject to `constraint`.
     /// The new geometry will be a superset of the old geometry.
@@ -481,44 +2653,259 @@ impl<'a> Disk<'a> {
         part: &mut Partition,
         constraint: &Constraint,
     ) -> Result<()> {
-        cvt(unsafe { ped_disk_maximize_partition(self.disk, part.part, constraint.constraint) })
-            .map(|_| ())
+        cvt(unsafe { ped_disk_maximize_partition(self.disk, part.part, constraint.constraint) })?;
+        set_dirty(self.disk, true);
+        Ok(())
+    }
+
+    /// Grows the partition numbered `num` to the maximum size possible, with boundaries which
+    /// respect the device alignment chosen by `align`, and returns its new geometry.
+    ///
+    /// # Note:
+    ///
+    /// The new geometry is a superset of the old geometry, so this will fail if the partition
+    /// does not already start on a boundary that `align` permits and there is no aligned
+    /// sector in the free space before it.
+    pub fn maximize_partition_by_num(&mut self, num: u32, align: AlignPolicy) -> Result<GeomView> {
+        let constraint = {
+            let device = self.raw_device();
+            match align {
+                AlignPolicy::None => device.get_constraint()?,
+                AlignPolicy::Minimal => device.get_minimal_aligned_constraint()?,
+                AlignPolicy::Optimal => device.get_optimal_aligned_constraint()?,
+            }
+        };
+
+        let mut part =
+            unsafe { Partition::borrowed(cvt(ped_disk_get_partition(self.disk, num as i32))?) };
+
+        self.maximize_partition(&mut part, &constraint)?;
+        Ok(part.geom())
+    }
+
+    /// Moves the partition numbered `num` so that it begins at `new_start`, subject to
+    /// `constraint`, and copies its contents to the new location.
+    ///
+    /// The contents are copied sector by sector, so any file system on the partition is
+    /// preserved without needing to be understood. The source and destination may overlap.
+    /// If `timer` is not `None`, it will be used as the progress meter.
+    ///
+    /// Once the contents have been copied, the partition table is written to the device, so
+    /// that it never points to the old location after the data has left it, and then the
+    /// operating system is informed of it.
+    ///
+    /// If the copy fails, the partition keeps its original geometry, though the data may have
+    /// been partially copied. If the table cannot be written, the data is copied back, and the
+    /// partition keeps its original geometry.
+    ///
+    /// # Note:
+    ///
+    /// Any other uncommitted changes to the disk are written to the device along with it.
+    ///
+    /// The partition must not be in use, and the constraint must allow the partition to keep
+    /// at least its current length.
+    pub fn move_partition(
+        &mut self,
+        num: u32,
+        new_start: i64,
+        constraint: &Constraint,
+        timer: Option<&mut Timer>,
+    ) -> Result<()> {
+        let mut part =
+            unsafe { Partition::borrowed(cvt(ped_disk_get_partition(self.disk, num as i32))?) };
+
+        if part.is_busy() {
+            return Err(Error::from_raw_os_error(libc::EBUSY));
+        }
+
+        let old = part.geom();
+        self.set_partition_geometry(&mut part, constraint, new_start, new_start + old.length - 1)?;
+
+        let new = part.geom();
+        if new.length < old.length {
+            self.reset_geometry(&mut part, old);
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "constraint does not allow the partition to keep its length",
+            ));
+        }
+
+        let handle = self.handle()?;
+        let mut device = unsafe { Device::borrowed(handle.ped_device()) };
+        if let Err(why) = copy::move_sectors(&mut device, old.start, new.start, old.length, timer) {
+            self.reset_geometry(&mut part, old);
+            return Err(why);
+        }
+
+        if let Err(why) = self.commit_to_dev() {
+            let moved_back =
+                copy::move_sectors(&mut device, new.start, old.start, old.length, None);
+            self.reset_geometry(&mut part, old);
+            return Err(match moved_back {
+                Ok(()) => why,
+                Err(back) => Error::new(
+                    why.kind(),
+                    format!(
+                        "{}, and the data could not be copied back to sector {}: {}",
+                        why, old.start, back
+                    ),
+                ),
+            });
+        }
+
+        self.commit_to_os()
+    }
+
+    /// Returns `part` to the sectors of `geom`, which it occupied before a failed change.
+    fn reset_geometry(&mut self, part: &mut Partition, geom: GeomView) {
+        if let Some(any) = self.constraint_any() {
+            let _ = self.set_partition_geometry(part, &any, geom.start, geom.end);
+        }
     }
 
     /// Reduce the size of the extended partition to a minimum while still wrapping its
     /// logical partitions. If there are no logical partitions, remove the extended partition.
     pub fn minimize_extended_partition(&mut self) -> Result<()> {
-        cvt(unsafe { ped_disk_minimize_extended_partition(self.disk) }).map(|_| ())
+        cvt(unsafe { ped_disk_minimize_extended_partition(self.disk) })?;
+        set_dirty(self.disk, true);
+        Ok(())
     }
 
     /// Removes the `part` **Partition** from the disk.
     ///
     /// If `part` is an extended partition, it must not contain any logical partitions.
     #[allow(clippy::missing_safety_doc)]
+    #[cfg_attr(
+        feature = "v2-api",
+        deprecated(since = "0.1.6", note = "use `remove_partition_returning()` instead")
+    )]
     pub unsafe fn remove_partition(&mut self, part: *mut PedPartition) -> Result<()> {
-        cvt(ped_disk_delete_partition(self.disk, part)).map(|_| ())
+        self.remove_partition_returning(part).map(|_| ())
     }
 
     /// Removes a partition from the disk by the partition number.
     ///
     /// If that partition is an extended partition, it must not contain any logical partitions.
+    #[cfg_attr(
+        feature = "v2-api",
+        deprecated(
+            since = "0.1.6",
+            note = "use `remove_partition_by_number_returning()` instead"
+        )
+    )]
     pub fn remove_partition_by_number(&mut self, num: u32) -> Result<()> {
+        self.remove_partition_by_number_returning(num).map(|_| ())
+    }
+
+    /// Removes a partition from the disk by the sector where that partition lies.
+    ///
+    /// If that partition is an extended partition, it must not contain any logical partitions.
+    #[cfg_attr(
+        feature = "v2-api",
+        deprecated(
+            since = "0.1.6",
+            note = "use `remove_partition_by_sector_returning()` instead"
+        )
+    )]
+    pub fn remove_partition_by_sector(&mut self, sector: i64) -> Result<()> {
+        self.remove_partition_by_sector_returning(sector)
+            .map(|_| ())
+    }
+
+    /// Removes the `part` **Partition** from the disk, returning a description of it and the
+    /// region of free space it now lies within.
+    ///
+    /// If `part` is an extended partition, it must not contain any logical partitions.
+    #[allow(clippy::missing_safety_doc)]
+    pub unsafe fn remove_partition_returning(
+        &mut self,
+        part: *mut PedPartition,
+    ) -> Result<RemovedPartition> {
+        let has_names = self.supports(DiskTypeFeature::PED_DISK_TYPE_PARTITION_NAME);
+        let partition = dump_partition(&Partition::borrowed(part), has_names);
+        cvt(ped_disk_delete_partition(self.disk, part))?;
+        set_dirty(self.disk, true);
+
+        // Removing a partition merges its sectors with any free space on either side.
+        let free = self
+            .free_region_at(partition.start)
+            .unwrap_or_else(|| GeomView {
+                start: partition.start,
+                end: partition.end(),
+                length: partition.length,
+            });
+
+        Ok(RemovedPartition { partition, free })
+    }
+
+    /// Removes a partition from the disk by the partition number, returning a description of
+    /// it and the region of free space it now lies within.
+    ///
+    /// If that partition is an extended partition, it must not contain any logical partitions.
+    pub fn remove_partition_by_number_returning(&mut self, num: u32) -> Result<RemovedPartition> {
         unsafe {
-            cvt(ped_disk_get_partition(self.disk, num as i32))
-                .and_then(|part| cvt(ped_disk_delete_partition(self.disk, part)))
-                .map(|_| ())
+            let part = cvt(ped_disk_get_partition(self.disk, num as i32))?;
+            self.remove_partition_returning(part)
         }
     }
 
-    /// Removes a partition from the disk by the sector where that partition lies.alignment
+    /// Removes a partition from the disk by the sector where that partition lies, returning a
+    /// description of it and the region of free space it now lies within.
     ///
     /// If that partition is an extended partition, it must not contain any logical partitions.
-    pub fn remove_partition_by_sector(&mut self, sector: i64) -> Result<()> {
+    pub fn remove_partition_by_sector_returning(
+        &mut self,
+        sector: i64,
+    ) -> Result<RemovedPartition> {
         unsafe {
-            cvt(ped_disk_get_partition_by_sector(self.disk, sector))
-                .and_then(|part| cvt(ped_disk_delete_partition(self.disk, part)))
-                .map(|_| ())
+            let part = cvt(ped_disk_get_partition_by_sector(self.disk, sector))?;
+            self.remove_partition_returning(part)
+        }
+    }
+
+    /// The partitions which would be renumbered if the partitions numbered `removed` were
+    /// removed, mapping each current number to the number it would have afterwards.
+    ///
+    /// On msdos labels, removing a logical partition renumbers the logical partitions after
+    /// it (ie: removing 5 makes 6 into 5), which changes their device paths. The removals are
+    /// simulated on a copy of the disk, so the disk itself is left untouched.
+    pub fn renumber_map(&self, removed: &[u32]) -> Result<BTreeMap<u32, u32>> {
+        let mut copy = self.duplicate()?;
+        let extended = PartitionType::PED_PARTITION_EXTENDED as u32;
+
+        // Every partition is looked up before any are removed, so that the numbers refer to
+        // the current table. Removing an extended partition destroys its logical partitions,
+        // so those are removed first.
+        let mut parts = Vec::with_capacity(removed.len());
+        for &num in removed {
+            let part = unsafe { cvt(ped_disk_get_partition(copy.disk, num as i32))? };
+            if !parts.contains(&part) {
+                parts.push(part);
+            }
+        }
+        parts.sort_by_key(|&part| unsafe { Partition::borrowed(part).type_bits() } & extended);
+
+        for part in parts {
+            unsafe { copy.remove_partition_returning(part)? };
+        }
+
+        let mut map = BTreeMap::new();
+        for part in self.partitions().filter(|part| part.is_active()) {
+            let geom = part.geom();
+            let renumbered = copy
+                .partitions()
+                .filter(|other| other.is_active() && other.geom() == geom)
+                .map(|other| other.num() as u32)
+                .next();
+
+            if let Some(num) = renumbered {
+                if num != part.num() as u32 {
+                    map.insert(part.num() as u32, num);
+                }
+            }
         }
+
+        Ok(map)
     }
 
     /// Set the state of a flag on a disk.
@@ -533,7 +2920,90 @@ impl<'a> Disk<'a> {
     /// Throws `PED_EXCEPTION_ERROR` if the requested flag is not available for this label.
     pub fn set_flag(&mut self, flag: DiskFlag, state: bool) -> bool {
         let state = if state { 1 } else { 0 };
-        unsafe { ped_disk_set_flag(self.disk, flag, state) != 0 }
+        let set = unsafe { ped_disk_set_flag(self.disk, flag, state) != 0 };
+        if set {
+            set_dirty(self.disk, true);
+        }
+        set
+    }
+
+    /// Resizes the partition numbered `num` to `new_length` sectors, keeping its start, and
+    /// resizes its file system to match with the tool `resizer` has registered for its type.
+    ///
+    /// When shrinking, the file system is shrunk before the partition. When growing, the
+    /// partition is grown and committed before the file system is grown into it. The device is
+    /// in external access mode while the tool runs. A partition without a file system is
+    /// resized on its own.
+    ///
+    /// # Note:
+    ///
+    /// The partition table is committed to the device and the kernel as part of the resize,
+    /// so that the tool sees the partition at its new size when growing.
+    pub fn resize_partition_and_fs(
+        &mut self,
+        num: u32,
+        new_length: i64,
+        resizer: &FsResizer,
+    ) -> Result<()> {
+        if new_length < 1 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "a partition must be at least one sector long",
+            ));
+        }
+
+        let mut part =
+            unsafe { Partition::borrowed(cvt(ped_disk_get_partition(self.disk, num as i32))?) };
+        let old = part.geom();
+        if new_length == old.length {
+            return Ok(());
+        }
+
+        let fs_type = part.fs_type_name().map(|name| name.to_owned());
+        if let Some(ref fs_type) = fs_type {
+            if !resizer.supports(fs_type) {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("no resizer is registered for {} file systems", fs_type),
+                ));
+            }
+        }
+
+        let path = part.get_path().map(|path| path.to_owned());
+        let resize_fs = |disk: &Disk| -> Result<()> {
+            match (fs_type.as_ref(), path.as_ref()) {
+                (Some(fs_type), Some(path)) => {
+                    let mut device = disk.raw_device();
+                    let size = new_length as u64 * device.sector_size();
+                    let _access = device.external_access_scoped()?;
+                    resizer.resize(fs_type, path, size)
+                }
+                _ => Ok(()),
+            }
+        };
+
+        let shrinking = new_length < old.length;
+        if shrinking {
+            resize_fs(self)?;
+        }
+
+        let device = self.raw_device();
+        let geom = Geometry::new(&device, Sector(old.start), Sector(new_length))?;
+        let constraint = geom.exact().ok_or_else(|| {
+            Error::new(
+                ErrorKind::Other,
+                "failed to create a constraint for the new size",
+            )
+        })?;
+        let end = old.start + new_length - 1;
+        self.set_partition_geometry(&mut part, &constraint, old.start, end)?;
+        self.commit()?;
+
+        if !shrinking {
+            resize_fs(self)?;
+        }
+
+        Ok(())
     }
 
     /// Sets the geometry of `part` (IE: change a partition's location).
@@ -549,10 +3019,15 @@ impl<'a> Disk<'a> {
     ) -> Result<()> {
         cvt(unsafe {
             ped_disk_set_partition_geom(self.disk, part.part, constraint.constraint, start, end)
-        })
-        .map(|_| ())
+        })?;
+        set_dirty(self.disk, true);
+        Ok(())
     }
 
+    /// Snaps the start and end of `new_geom` to the boundaries of neighbouring partitions and
+    /// free space, where they are within `start_range` and `end_range` respectively.
+    ///
+    /// Boundaries which are within range of `old_geom` are kept where they were.
     pub fn snap_to_boundaries(
         &self,
         new_geom: &mut Geometry,
@@ -560,101 +3035,109 @@ impl<'a> Disk<'a> {
         start_range: &Geometry,
         end_range: &Geometry,
     ) {
-        let (mut start_dist, mut end_dist) = (-1, -1);
-        let mut start = new_geom.start();
-        let mut end = new_geom.end();
-
-        let start_part = match self.get_partition_by_sector(start) {
-            Some(part) => part,
-            None => Partition::from(ptr::null_mut()),
-        };
-
-        let end_part = match self.get_partition_by_sector(end) {
-            Some(part) => part,
-            None => Partition::from(ptr::null_mut()),
-        };
-
-        let adjacent = start_part.geom_end() + 1 == end_part.geom_start();
-        let mut start_allow = MOVE_STILL | MOVE_UP | MOVE_DOWN;
-        let mut end_allow = start_allow;
-
-        if let Some(old_geom) = old_geom {
-            if snap(&mut start, old_geom.start(), start_range) {
-                start_allow = MOVE_STILL;
-            }
+        let old = old_geom.map(|geom| geom.view());
+        let (start, end) = self.snap_sectors(new_geom.view(), old, start_range, end_range);
+        let _ = new_geom.set(Sector(start), Sector(end - start + 1));
+    }
 
-            if snap(&mut end, old_geom.end(), end_range) {
-                end_allow = MOVE_STILL;
+    /// Returns a copy of `geom` with its start and end snapped to the boundaries of
+    /// neighbouring partitions and free space, as permitted by `options`.
+    pub fn snap_geometry<'b>(&self, geom: &Geometry, options: SnapOptions) -> Result<Geometry<'b>> {
+        let device = self.raw_device();
+        let last = device.length() as i64 - 1;
+        let range = |view: Option<GeomView>, sector: i64| -> Result<Geometry<'b>> {
+            let (start, end) =
+                view.map_or((0, last), |view| (view.start.max(0), view.end.min(last)));
+            if sector < start || sector > end {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("sector {} is outside of its snap range", sector),
+                ));
             }
-        }
+            Geometry::new(&device, Sector(start), Sector(end - start + 1))
+        };
 
-        if start_part == end_part {
-            start_allow &= !MOVE_UP;
-            end_allow &= !MOVE_DOWN;
-        }
+        let view = geom.view();
+        let start_range = range(options.start_range, view.start)?;
+        let end_range = range(options.end_range, view.end)?;
+        let (start, end) = self.snap_sectors(view, options.old, &start_range, &end_range);
 
-        let mut start_want = prefer_snap(
-            start,
-            SECT_START,
-            start_range,
-            &mut start_allow,
-            &start_part,
-            &mut start_dist,
-        );
+        let mut snapped = geom.duplicate()?;
+        snapped.set(Sector(start), Sector(end - start + 1))?;
+        Ok(snapped)
+    }
 
-        let mut end_want = prefer_snap(
-            end,
-            SECT_END,
-            end_range,
-            &mut end_allow,
-            &end_part,
-            &mut end_dist,
-        );
+    fn snap_sectors(
+        &self,
+        new: GeomView,
+        old: Option<GeomView>,
+        start_range: &Geometry,
+        end_range: &Geometry,
+    ) -> (i64, i64) {
+        // A sector outside of any partition or free space has no boundaries to snap to.
+        let start_geom = self.partition_by_sector(new.start).map(|part| part.geom());
+        let end_geom = self.partition_by_sector(new.end).map(|part| part.geom());
+        snapping::snap_bounds(
+            new,
+            old,
+            start_range.view(),
+            end_range.view(),
+            start_geom,
+            end_geom,
+        )
+    }
+}
 
-        debug_assert!(start_dist >= 0 && end_dist >= 0);
-
-        if adjacent && start_want == MOVE_UP && end_want == MOVE_DOWN {
-            if end_dist < start_dist {
-                start_allow &= !MOVE_UP;
-                start_want = prefer_snap(
-                    start,
-                    SECT_START,
-                    start_range,
-                    &mut start_allow,
-                    &start_part,
-                    &mut start_dist,
-                );
-                debug_assert!(start_dist >= 0);
-            } else {
-                end_allow &= !MOVE_DOWN;
-                end_want = prefer_snap(
-                    start,
-                    SECT_END,
-                    end_range,
-                    &mut end_allow,
-                    &end_part,
-                    &mut end_dist,
-                );
-                debug_assert!(end_dist >= 0);
-            }
-        }
+/// Builds a constraint which allows the start and end of a partition to move by up to one
+/// alignment grain, preferring the optimal alignment of the device where it can be satisfied.
+fn approximate_constraint<'b>(device: &Device, start: i64, end: i64) -> Result<Constraint<'b>> {
+    let last = device.length() as i64 - 1;
+    let zone = device.managed_zone_sectors();
+    let radius = device
+        .get_optimum_alignment()
+        .map_or(2048, |alignment| alignment.grain_size())
+        .max(zone.unwrap_or(1));
+
+    let range = |sector: i64| {
+        let low = (sector - radius).max(0);
+        let high = (sector + radius).min(last);
+        Geometry::new(device, Sector(low), Sector(high - low + 1))
+    };
+
+    let user = device.constraint_from_start_end(&range(start)?, &range(end)?)?;
+
+    // Host-managed zoned devices refuse writes which do not follow the write pointer of a
+    // zone, so partitions must begin and end on zone boundaries.
+    if let Some(zone) = zone {
+        let whole = Geometry::new(device, Sector(0), Sector(last + 1))?;
+        let zoned = Constraint::new(
+            &Alignment::new(0, zone)?,
+            &Alignment::new(-1, zone)?,
+            &whole,
+            &whole,
+            Sector(zone),
+            Sector(last + 1),
+        )?;
+        return user.intersect(&zoned).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                "the partition cannot be aligned to the zones of the device",
+            )
+        });
+    }
 
-        start = match start_want {
-            MOVE_DOWN => start_part.geom_start(),
-            MOVE_UP => start_part.geom_end() + 1,
-            _ => start,
-        };
+    let optimal = device
+        .get_optimal_aligned_constraint()
+        .ok()
+        .and_then(|optimal| user.intersect(&optimal));
 
-        end = match end_want {
-            MOVE_DOWN => end_part.geom_start() - 1,
-            MOVE_UP => end_part.geom_end(),
-            _ => end,
-        };
+    Ok(optimal.unwrap_or(user))
+}
 
-        debug_assert!(start_range.test_sector_inside(start));
-        debug_assert!(end_range.test_sector_inside(end));
-        debug_assert!(start <= end);
-        let _ = new_geom.set(start, end - start + 1);
+impl<'a> Iterator for DiskPartRefIter<'a> {
+    type Item = PartitionRef<'a>;
+    fn next(&mut self) -> Option<PartitionRef<'a>> {
+        self.0.next().map(PartitionRef)
     }
 }
 
@@ -666,9 +3149,7 @@ impl<'a> Iterator for DiskPartIter<'a> {
             None
         } else {
             self.1 = partition;
-            let mut partition = Partition::from(partition);
-            partition.is_droppable = false;
-            Some(partition)
+            Some(unsafe { Partition::borrowed(partition) })
         }
     }
 }
@@ -676,9 +3157,215 @@ impl<'a> Iterator for DiskPartIter<'a> {
 impl<'a> Drop for Disk<'a> {
     fn drop(&mut self) {
         if self.is_droppable {
+            set_dirty(self.disk, false);
             unsafe {
                 ped_disk_destroy(self.disk);
             }
         }
     }
 }
+
+/// Captures `part` for a dump, including its name only if `has_names` is set.
+fn dump_partition(part: &Partition, has_names: bool) -> PartitionDump {
+    let geom = part.geom();
+    let name = part.name();
+    let name_raw = match part.name_raw() {
+        Some(raw) if has_names && name.is_none() => Some(raw.to_owned()),
+        _ => None,
+    };
+
+    PartitionDump {
+        num: part.num() as u32,
+        part_type: part.kind(),
+        start: geom.start,
+        length: geom.length,
+        fs_type: part.fs_type_name().map(|name| name.to_owned()),
+        name: if has_names { name } else { None },
+        name_raw,
+        flags: part.flags(),
+        #[cfg(feature = "parted-3-5")]
+        type_uuid: part.type_uuid(),
+        #[cfg(not(feature = "parted-3-5"))]
+        type_uuid: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{self, OpenOptions};
+    use std::process;
+    use {DiskBuilder, MIB};
+
+    /// A zeroed image file, which libparted opens as a device without requiring root.
+    struct Image(PathBuf);
+
+    impl Image {
+        fn new(name: &str) -> Image {
+            let path =
+                std::env::temp_dir().join(format!("libparted-disk-{}-{}.img", name, process::id()));
+            OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(&path)
+                .and_then(|file| file.set_len(32 * 1024 * 1024))
+                .unwrap();
+            Image(path)
+        }
+    }
+
+    impl Drop for Image {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    fn partition_nums(disk: &Disk) -> Vec<u32> {
+        (1..=disk.last_partition_num().unwrap().unwrap_or(0))
+            .filter(|&num| disk.partition(num).is_some())
+            .collect()
+    }
+
+    #[test]
+    fn duplicate_of_fresh_disk_outlives_the_original() {
+        let image = Image::new("duplicate-fresh");
+        let device = Device::new(&image.0).unwrap();
+
+        let mut copy = {
+            let mut disk = Disk::new_fresh(
+                &device,
+                DiskType::from_table_type(PartitionTableType::GPT).unwrap(),
+            )
+            .unwrap();
+            disk.create_partition(&PartitionSpec::with_sectors(2048, 4095))
+                .unwrap();
+            disk.duplicate().unwrap()
+        };
+
+        copy.create_partition(&PartitionSpec::with_sectors(4096, 8191))
+            .unwrap();
+        assert_eq!(partition_nums(&copy), vec![1, 2]);
+        copy.commit_to_dev().unwrap();
+        drop(copy);
+
+        let disk = Disk::new(&device).unwrap();
+        assert_eq!(disk.get_disk_type_name(), Some("gpt"));
+        assert_eq!(partition_nums(&disk), vec![1, 2]);
+    }
+
+    #[test]
+    fn duplicate_of_read_disk_outlives_the_original() {
+        let image = Image::new("duplicate-read");
+        let device = Device::new(&image.0).unwrap();
+        {
+            let mut disk = Disk::new_fresh(
+                &device,
+                DiskType::from_table_type(PartitionTableType::MSDOS).unwrap(),
+            )
+            .unwrap();
+            disk.create_partition(&PartitionSpec::with_sectors(2048, 4095))
+                .unwrap();
+            disk.create_partition(&PartitionSpec::with_sectors(4096, 8191))
+                .unwrap();
+            disk.commit_to_dev().unwrap();
+        }
+
+        let original = Disk::new(&device).unwrap();
+        let mut copy = original.duplicate().unwrap();
+        drop(original);
+
+        copy.remove_partition_by_number_returning(1).unwrap();
+        assert!(copy.partition(2).is_some());
+        copy.commit_to_dev().unwrap();
+        drop(copy);
+
+        let disk = Disk::new(&device).unwrap();
+        assert_eq!(disk.get_disk_type_name(), Some("msdos"));
+        assert_eq!(partition_nums(&disk), vec![2]);
+    }
+
+    #[test]
+    fn builder_chains_logical_partitions_on_msdos() {
+        let image = Image::new("builder-chain");
+        let mut device = Device::new(&image.0).unwrap();
+
+        let mut builder = DiskBuilder::new(&mut device).label("msdos");
+        for _ in 0..5 {
+            builder = builder.partition(4 * MIB, "ext4", "data");
+        }
+        let disk = builder.partition_fill("ext4", "rest").build().unwrap();
+
+        // Three primary partitions, the extended partition, and three logical partitions.
+        assert_eq!(partition_nums(&disk), vec![1, 2, 3, 4, 5, 6, 7]);
+        let extended = disk.partition(4).unwrap();
+        assert_eq!(
+            extended.type_bits(),
+            PartitionType::PED_PARTITION_EXTENDED as u32
+        );
+    }
+
+    #[test]
+    fn validate_layout_chains_partitions_beyond_the_primary_limit() {
+        let image = Image::new("validate-chain");
+        let device = Device::new(&image.0).unwrap();
+        let disk = Disk::new_fresh(
+            &device,
+            DiskType::from_table_type(PartitionTableType::MSDOS).unwrap(),
+        )
+        .unwrap();
+
+        let layout = (1..7)
+            .map(|n| PartitionSpec::with_sectors(n * 8192, n * 8192 + 8191))
+            .collect::<Vec<_>>();
+        let issues = disk
+            .validate_layout(&layout)
+            .into_iter()
+            .filter(|issue| *issue != LayoutIssue::MissingEsp)
+            .collect::<Vec<_>>();
+        assert_eq!(issues, Vec::new());
+    }
+
+    #[test]
+    fn partition_changes_mark_the_disk_dirty() {
+        let image = Image::new("dirty");
+        let device = Device::new(&image.0).unwrap();
+        {
+            let mut disk = Disk::new_fresh(
+                &device,
+                DiskType::from_table_type(PartitionTableType::MSDOS).unwrap(),
+            )
+            .unwrap();
+            assert!(disk.is_dirty());
+            disk.create_partition(&PartitionSpec::with_sectors(2048, 4095))
+                .unwrap();
+            disk.commit_to_dev().unwrap();
+            assert!(!disk.is_dirty());
+        }
+
+        let disk = Disk::new(&device).unwrap();
+        assert!(!disk.is_dirty());
+        assert!(!disk.state().unwrap().stale_device);
+
+        let mut part = disk.get_partition(1).unwrap();
+        part.set_flag(PartitionFlag::PED_PARTITION_BOOT, true)
+            .unwrap();
+        assert!(disk.is_dirty());
+        assert!(disk.state().unwrap().dirty);
+    }
+
+    #[test]
+    fn set_label_name_errors_without_a_disk_name() {
+        let image = Image::new("label-name");
+        let device = Device::new(&image.0).unwrap();
+
+        for table in vec![PartitionTableType::GPT, PartitionTableType::MSDOS] {
+            let type_ = DiskType::from_table_type(table).unwrap();
+            let mut disk = Disk::new_fresh(&device, type_).unwrap();
+            assert!(!disk.supports_label_name());
+
+            let err = disk.set_label_name("installer").unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        }
+    }
+}
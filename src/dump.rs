@@ -0,0 +1,405 @@
+//! An owned snapshot of a partition table, which may be saved as text and restored later.
+//!
+//! The text format is modeled after the output of `sfdisk --dump`:
+//!
+//! ```text
+//! label: gpt
+//! sector-size: 512
+//! disk-flags: pmbr_boot
+//!
+//! 1 : start=2048, size=1048576, type=primary, fs=fat32, name="EFI", flags="boot,esp"
+//! 2 : start=1050624, size=40960000, type=primary, fs=ext4, name="root"
+//! ```
+//!
+//! Partition type UUIDs are written as `type-uuid=` when they are known, and names which are
+//! not valid UTF-8 are written in hexadecimal as `name-hex=`. Fields which are absent are left
+//! unset when the dump is restored.
+
+use super::gpt::{format_uuid, parse_uuid};
+use super::{DiskFlag, DiskFlagExt, PartitionFlag, PartitionFlagExt, PartitionType};
+use std::fmt::{self, Display, Formatter};
+use std::io::{Error, ErrorKind, Result};
+use std::str::FromStr;
+
+/// A partition table, as captured by `Disk::dump()`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PartitionTableDump {
+    /// The name of the disk label type, such as `gpt` or `msdos`.
+    pub label: String,
+    /// The logical sector size of the device the table was captured from.
+    pub sector_size: u64,
+    /// The flags which are set on the disk.
+    pub flags: Vec<DiskFlag>,
+    /// Each active partition, in the order they appear on the disk.
+    pub partitions: Vec<PartitionDump>,
+}
+
+/// A single partition within a **PartitionTableDump**.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PartitionDump {
+    pub num: u32,
+    pub part_type: PartitionType,
+    pub start: i64,
+    pub length: i64,
+    pub fs_type: Option<String>,
+    pub name: Option<String>,
+    /// The bytes of the name, when they are not valid UTF-8 and so cannot be held by `name`
+    /// (ie: a MacRoman or Shift JIS name on a mac or pc98 label).
+    pub name_raw: Option<Vec<u8>>,
+    pub flags: Vec<PartitionFlag>,
+    /// The partition type UUID, in RFC 4122 byte order (ie: the type GUID on GPT disks).
+    pub type_uuid: Option<[u8; 16]>,
+}
+
+impl PartitionDump {
+    /// The last sector of the partition.
+    pub fn end(&self) -> i64 {
+        self.start + self.length - 1
+    }
+}
+
+impl Display for PartitionTableDump {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(f, "label: {}", self.label)?;
+        writeln!(f, "sector-size: {}", self.sector_size)?;
+        if !self.flags.is_empty() {
+            let flags = self
+                .flags
+                .iter()
+                .map(|flag| flag.name())
+                .collect::<Vec<_>>();
+            writeln!(f, "disk-flags: {}", flags.join(","))?;
+        }
+        writeln!(f)?;
+
+        for part in &self.partitions {
+            write!(
+                f,
+                "{} : start={}, size={}, type={}",
+                part.num,
+                part.start,
+                part.length,
+                type_name(part.part_type)
+            )?;
+
+            if let Some(ref fs_type) = part.fs_type {
+                write!(f, ", fs={}", fs_type)?;
+            }
+
+            if let Some(ref name) = part.name {
+                write!(f, ", name={}", quote(name))?;
+            }
+
+            if let Some(ref name) = part.name_raw {
+                let hex = name
+                    .iter()
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect::<String>();
+                write!(f, ", name-hex={}", hex)?;
+            }
+
+            if !part.flags.is_empty() {
+                let flags = part
+                    .flags
+                    .iter()
+                    .map(|flag| flag.name())
+                    .collect::<Vec<_>>();
+                write!(f, ", flags={}", quote(&flags.join(",")))?;
+            }
+
+            if let Some(ref uuid) = part.type_uuid {
+                write!(f, ", type-uuid={}", format_uuid(uuid))?;
+            }
+
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for PartitionTableDump {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<PartitionTableDump> {
+        let mut label = None;
+        let mut sector_size = None;
+        let mut flags = Vec::new();
+        let mut partitions = Vec::new();
+
+        for (no, line) in input.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let result = match line.find(" : ") {
+                Some(pos) => parse_partition(&line[..pos], &line[pos + 3..])
+                    .map(|part| partitions.push(part)),
+                None => match line.find(':') {
+                    Some(pos) => {
+                        let value = line[pos + 1..].trim();
+                        match line[..pos].trim() {
+                            "label" => {
+                                label = Some(value.to_owned());
+                                Ok(())
+                            }
+                            "sector-size" => parse_number(value).map(|size| {
+                                sector_size = Some(size);
+                            }),
+                            "disk-flags" => parse_flags(value, DiskFlag::from_name).map(|parsed| {
+                                flags = parsed;
+                            }),
+                            // Unknown header fields are ignored, as sfdisk does.
+                            _ => Ok(()),
+                        }
+                    }
+                    None => Err(invalid("expected a header or partition line")),
+                },
+            };
+
+            result.map_err(|why| invalid(&format!("line {}: {}", no + 1, why)))?;
+        }
+
+        Ok(PartitionTableDump {
+            label: label.ok_or_else(|| invalid("missing `label` header"))?,
+            sector_size: sector_size.ok_or_else(|| invalid("missing `sector-size` header"))?,
+            flags,
+            partitions,
+        })
+    }
+}
+
+fn parse_partition(num: &str, fields: &str) -> Result<PartitionDump> {
+    let mut part = PartitionDump {
+        num: parse_number(num.trim())?,
+        part_type: PartitionType::PED_PARTITION_NORMAL,
+        start: -1,
+        length: -1,
+        fs_type: None,
+        name: None,
+        name_raw: None,
+        flags: Vec::new(),
+        type_uuid: None,
+    };
+
+    for (key, value) in split_fields(fields)? {
+        match key.as_str() {
+            "start" => part.start = parse_number(&value)?,
+            "size" => part.length = parse_number(&value)?,
+            "type" => part.part_type = parse_type(&value)?,
+            "fs" => part.fs_type = Some(value),
+            "name" => part.name = Some(value),
+            "name-hex" => part.name_raw = Some(parse_hex(&value)?),
+            "flags" => part.flags = parse_flags(&value, PartitionFlag::from_name)?,
+            "type-uuid" => part.type_uuid = Some(parse_uuid(&value)?),
+            _ => return Err(invalid(&format!("unknown field `{}`", key))),
+        }
+    }
+
+    if part.start < 0 || part.length < 1 {
+        return Err(invalid(
+            "partition requires a `start` and a non-zero `size`",
+        ));
+    }
+
+    Ok(part)
+}
+
+/// Parses bytes written as pairs of hexadecimal digits.
+fn parse_hex(value: &str) -> Result<Vec<u8>> {
+    if value.len() % 2 != 0 || !value.is_ascii() {
+        return Err(invalid(&format!("invalid hexadecimal bytes `{}`", value)));
+    }
+
+    (0..value.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&value[i..i + 2], 16)
+                .map_err(|_| invalid(&format!("invalid hexadecimal bytes `{}`", value)))
+        })
+        .collect()
+}
+
+/// Parses a comma-separated list of flag names.
+fn parse_flags<T, F: Fn(&str) -> Option<T>>(value: &str, from_name: F) -> Result<Vec<T>> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|flag| !flag.is_empty())
+        .map(|flag| from_name(flag).ok_or_else(|| invalid(&format!("unknown flag `{}`", flag))))
+        .collect()
+}
+
+/// Splits `key=value` fields separated by commas, where values may be quoted.
+fn split_fields(input: &str) -> Result<Vec<(String, String)>> {
+    let mut fields = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    loop {
+        while chars
+            .peek()
+            .map_or(false, |&c| c == ',' || c.is_whitespace())
+        {
+            chars.next();
+        }
+
+        if chars.peek().is_none() {
+            return Ok(fields);
+        }
+
+        let mut key = String::new();
+        loop {
+            match chars.next() {
+                Some('=') => break,
+                Some(c) => key.push(c),
+                None => return Err(invalid(&format!("field `{}` has no value", key.trim()))),
+            }
+        }
+
+        let mut value = String::new();
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some(c) => value.push(c),
+                        None => return Err(invalid("unterminated escape")),
+                    },
+                    Some(c) => value.push(c),
+                    None => return Err(invalid("unterminated quote")),
+                }
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                if c == ',' {
+                    break;
+                }
+                value.push(c);
+                chars.next();
+            }
+            value = value.trim_end().to_owned();
+        }
+
+        fields.push((key.trim().to_owned(), value));
+    }
+}
+
+fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        if c == '"' || c == '\\' {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn type_name(part_type: PartitionType) -> &'static str {
+    match part_type {
+        PartitionType::PED_PARTITION_LOGICAL => "logical",
+        PartitionType::PED_PARTITION_EXTENDED => "extended",
+        _ => "primary",
+    }
+}
+
+fn parse_type(value: &str) -> Result<PartitionType> {
+    match value {
+        "primary" => Ok(PartitionType::PED_PARTITION_NORMAL),
+        "logical" => Ok(PartitionType::PED_PARTITION_LOGICAL),
+        "extended" => Ok(PartitionType::PED_PARTITION_EXTENDED),
+        _ => Err(invalid(&format!("unknown partition type `{}`", value))),
+    }
+}
+
+fn parse_number<T: FromStr>(value: &str) -> Result<T> {
+    value
+        .parse()
+        .map_err(|_| invalid(&format!("`{}` is not a valid number", value)))
+}
+
+fn invalid(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn partition(num: u32, start: i64, length: i64) -> PartitionDump {
+        PartitionDump {
+            num,
+            part_type: PartitionType::PED_PARTITION_NORMAL,
+            start,
+            length,
+            fs_type: None,
+            name: None,
+            name_raw: None,
+            flags: Vec::new(),
+            type_uuid: None,
+        }
+    }
+
+    #[test]
+    fn round_trips_through_text() {
+        let mut esp = partition(1, 2048, 1_048_576);
+        esp.fs_type = Some("fat32".into());
+        esp.name = Some("EFI \"System\" \\ Partition".into());
+        esp.type_uuid = Some([
+            0x28, 0x73, 0x2A, 0xC1, 0x1F, 0xF8, 0xD2, 0x11, 0xBA, 0x4B, 0x00, 0xA0, 0xC9, 0x3E,
+            0xC9, 0x3B,
+        ]);
+
+        let mut logical = partition(5, 1_050_624, 4096);
+        logical.part_type = PartitionType::PED_PARTITION_LOGICAL;
+        logical.name_raw = Some(vec![0x82, 0xa0, 0xff]);
+
+        let dump = PartitionTableDump {
+            label: "gpt".into(),
+            sector_size: 4096,
+            flags: Vec::new(),
+            partitions: vec![esp, logical],
+        };
+
+        let text = dump.to_string();
+        assert_eq!(text.parse::<PartitionTableDump>().unwrap(), dump);
+    }
+
+    #[test]
+    fn parses_sfdisk_style_input() {
+        let dump: PartitionTableDump = "# saved\nlabel: msdos\nsector-size: 512\nunit: \
+                                        sectors\n\n1 : start=2048, size=4096, type=extended\n"
+            .parse()
+            .unwrap();
+
+        assert_eq!(dump.label, "msdos");
+        assert_eq!(dump.sector_size, 512);
+        assert_eq!(dump.partitions.len(), 1);
+        assert_eq!(
+            dump.partitions[0].part_type,
+            PartitionType::PED_PARTITION_EXTENDED
+        );
+        assert_eq!(dump.partitions[0].end(), 6143);
+    }
+
+    #[test]
+    fn rejects_invalid_input() {
+        let fails = |text: &str| text.parse::<PartitionTableDump>().is_err();
+        assert!(fails("sector-size: 512\n"));
+        assert!(fails("label: gpt\n"));
+        assert!(fails("label: gpt\nsector-size: 512\n1 : start=2048\n"));
+        assert!(fails(
+            "label: gpt\nsector-size: 512\n1 : start=0, size=1, colour=red\n"
+        ));
+        assert!(fails(
+            "label: gpt\nsector-size: 512\n1 : start=0, size=1, name=\"open\n"
+        ));
+        assert!(fails(
+            "label: gpt\nsector-size: 512\n1 : start=0, size=1, name-hex=abc\n"
+        ));
+    }
+}
@@ -0,0 +1,113 @@
+//! Observes the progress of long-running operations through a single interface.
+//!
+//! Operations which accept a **ProgressSink** report when they start and finish, how far
+//! they have progressed (for those which libparted times), and every exception libparted
+//! raises along the way, so that a UI needs neither a **Timer** nor an exception handler of
+//! its own.
+//!
+//! ```rust,no_run
+//! # use libparted::{Device, Disk};
+//! # use libparted::events::ProgressEvent;
+//! let device = Device::new("/dev/sda").unwrap();
+//! let mut disk = Disk::new(&device).unwrap();
+//! let mut sink = |event: ProgressEvent| match event {
+//!     ProgressEvent::Progress { fraction } => println!("{:.0}%", fraction * 100.0),
+//!     ProgressEvent::ExceptionRaised(ex) => eprintln!("{}", ex.message),
+//!     _ => (),
+//! };
+//! disk.commit_with_progress(Some(&mut sink)).unwrap();
+//! ```
+
+use super::exception::{self, Exception, ExceptionOption};
+use super::Timer;
+use libparted_sys::{ped_timer_destroy, ped_timer_new, PedTimer};
+use std::cell::RefCell;
+use std::marker::PhantomData;
+use std::os::raw::c_void;
+
+/// An event emitted by an operation to its **ProgressSink**.
+#[derive(Clone, Debug)]
+pub enum ProgressEvent {
+    /// The operation has begun.
+    Started,
+    /// The fraction of the operation, from `0.0` to `1.0`, which has been completed.
+    Progress { fraction: f32 },
+    /// libparted raised an exception, which will be given its default response.
+    ExceptionRaised(Exception),
+    /// The operation has ended, whether or not it succeeded.
+    Finished,
+}
+
+/// Receives the events of an operation.
+///
+/// Closures which accept a **ProgressEvent** are sinks.
+pub trait ProgressSink {
+    fn event(&mut self, event: ProgressEvent);
+}
+
+impl<F: FnMut(ProgressEvent)> ProgressSink for F {
+    fn event(&mut self, event: ProgressEvent) {
+        self(event)
+    }
+}
+
+type Shared<'s> = RefCell<&'s mut dyn ProgressSink>;
+
+unsafe extern "C" fn timer_handler(timer: *mut PedTimer, context: *mut c_void) {
+    let sink = &*(context as *const Shared);
+    // The timer may be updated from within another event, which is then skipped.
+    if let Ok(mut sink) = sink.try_borrow_mut() {
+        sink.event(ProgressEvent::Progress {
+            fraction: (*timer).frac,
+        });
+    }
+}
+
+/// Runs `func` with a timer which reports its progress to `sink`, forwarding each exception
+/// raised meanwhile, and bracketing it with `Started` and `Finished` events.
+///
+/// Without a sink, `func` is run without a timer.
+pub(crate) fn observe<T, F>(sink: Option<&mut dyn ProgressSink>, func: F) -> T
+where
+    F: FnOnce(Option<&mut Timer>) -> T,
+{
+    let sink = match sink {
+        Some(sink) => sink,
+        None => return func(None),
+    };
+
+    sink.event(ProgressEvent::Started);
+    let shared: Shared = RefCell::new(sink);
+    let result = {
+        let context = &shared as *const Shared as *mut c_void;
+        let raw = unsafe { ped_timer_new(Some(timer_handler), context) };
+        let mut timer = Timer {
+            timer: raw,
+            phantom: PhantomData,
+        };
+
+        let result = exception::with_handler(
+            |ex: &Exception| {
+                if let Ok(mut sink) = shared.try_borrow_mut() {
+                    sink.event(ProgressEvent::ExceptionRaised(ex.clone()));
+                }
+                ExceptionOption::PED_EXCEPTION_UNHANDLED
+            },
+            || {
+                func(if raw.is_null() {
+                    None
+                } else {
+                    Some(&mut timer)
+                })
+            },
+        );
+
+        if !raw.is_null() {
+            unsafe { ped_timer_destroy(raw) };
+        }
+        result
+    };
+
+    shared.into_inner().event(ProgressEvent::Finished);
+    result
+}
@@ -0,0 +1,181 @@
+//! Captures exceptions raised by libparted while a closure runs.
+//!
+//! libparted reports errors, warnings, and questions (such as whether a damaged GPT should be
+//! repaired) through a single process-wide exception handler. `with_handler()` temporarily
+//! installs a handler which forwards each exception to a Rust closure on the calling thread,
+//! restoring the previous handler afterwards.
+//!
+//! As the handler is shared by every thread, it is installed when the first `with_handler()`
+//! call begins and restored when the last one ends. Exceptions raised on a thread which has no
+//! closure of its own are passed to the handler that was installed beforehand.
+
+use libparted_sys::{
+    ped_exception_default_handler, ped_exception_get_handler, ped_exception_set_handler,
+    PedException, PedExceptionHandler,
+};
+use std::cell::RefCell;
+use std::ffi::CStr;
+use std::io;
+use std::mem;
+use std::ptr;
+use std::sync::{Mutex, MutexGuard, Once};
+
+pub use libparted_sys::PedExceptionOption as ExceptionOption;
+pub use libparted_sys::PedExceptionType as ExceptionType;
+
+/// An exception which was raised by libparted.
+#[derive(Clone, Debug)]
+pub struct Exception {
+    pub type_: ExceptionType,
+    /// Bitmask of the `ExceptionOption`s which may be given in response.
+    pub options: u32,
+    pub message: String,
+}
+
+impl Exception {
+    unsafe fn from_raw(ex: *const PedException) -> Exception {
+        let message = if (*ex).message.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr((*ex).message).to_string_lossy().into_owned()
+        };
+
+        Exception {
+            type_: (*ex).type_,
+            // A combination of options is not a valid `ExceptionOption`.
+            options: ptr::read(&(*ex).options as *const ExceptionOption as *const u32),
+            message,
+        }
+    }
+
+    /// Whether `option` is one of the permitted responses to this exception.
+    pub fn allows(&self, option: ExceptionOption) -> bool {
+        self.options & option as u32 != 0
+    }
+}
+
+type Handler<'a> = dyn FnMut(&Exception) -> ExceptionOption + 'a;
+
+thread_local! {
+    static HANDLERS: RefCell<Vec<*mut Handler<'static>>> = RefCell::new(Vec::new());
+}
+
+/// The handler which was installed before the trampoline, and the number of `with_handler()`
+/// calls which are running across all threads.
+struct Installed {
+    previous: PedExceptionHandler,
+    active: usize,
+}
+
+fn installed() -> MutexGuard<'static, Installed> {
+    static INIT: Once = Once::new();
+    static mut INSTALLED: Option<Mutex<Installed>> = None;
+
+    unsafe {
+        INIT.call_once(|| {
+            INSTALLED = Some(Mutex::new(Installed {
+                previous: None,
+                active: 0,
+            }))
+        });
+
+        let installed = (*ptr::addr_of!(INSTALLED))
+            .as_ref()
+            .expect("initialized above");
+        // The state is only changed while the lock is held, so it is consistent even if a
+        // thread panicked while holding it.
+        installed
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Removes the closure of this thread, and restores the previous handler once no other
+/// thread needs the trampoline, even if the closure panics.
+struct Guard;
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        HANDLERS.with(|handlers| handlers.borrow_mut().pop());
+
+        let mut installed = installed();
+        installed.active -= 1;
+        if installed.active == 0 {
+            unsafe { ped_exception_set_handler(installed.previous) };
+        }
+    }
+}
+
+unsafe extern "C" fn trampoline(ex: *mut PedException) -> ExceptionOption {
+    match HANDLERS.with(|handlers| handlers.borrow().last().cloned()) {
+        Some(handler) => (*handler)(&Exception::from_raw(ex)),
+        // Raised on another thread, which has not installed a handler of its own.
+        None => match installed().previous {
+            Some(previous) => previous(ex),
+            None => ped_exception_default_handler(ex),
+        },
+    }
+}
+
+/// Runs `func`, passing each exception libparted raises on this thread to `handler`.
+///
+/// The option returned by `handler` is given to libparted as the response to the exception;
+/// returning `PED_EXCEPTION_UNHANDLED` lets libparted take its default action.
+pub(crate) fn with_handler<T, H, F>(mut handler: H, func: F) -> T
+where
+    H: FnMut(&Exception) -> ExceptionOption,
+    F: FnOnce() -> T,
+{
+    let handler: *mut Handler = &mut handler;
+    // The handler is removed from the stack before it goes out of scope.
+    let handler = unsafe { mem::transmute::<*mut Handler, *mut Handler<'static>>(handler) };
+    HANDLERS.with(|handlers| handlers.borrow_mut().push(handler));
+
+    let _guard = {
+        let mut installed = installed();
+        if installed.active == 0 {
+            unsafe {
+                installed.previous = ped_exception_get_handler();
+                ped_exception_set_handler(Some(trampoline));
+            }
+        }
+        installed.active += 1;
+        Guard
+    };
+
+    func()
+}
+
+/// Runs `func`, which returns `None` on failure, turning the failure into an error carrying
+/// the message of the last error libparted raised, or `fallback` if it raised none.
+pub(crate) fn capture<T, F>(fallback: &str, func: F) -> io::Result<T>
+where
+    F: FnOnce() -> Option<T>,
+{
+    let mut message = None;
+    let result = with_handler(
+        |ex| {
+            let is_error = !matches!(
+                ex.type_,
+                ExceptionType::PED_EXCEPTION_INFORMATION | ExceptionType::PED_EXCEPTION_WARNING
+            );
+            if is_error {
+                message = Some(ex.message.clone());
+            }
+
+            if ex.allows(ExceptionOption::PED_EXCEPTION_CANCEL) {
+                ExceptionOption::PED_EXCEPTION_CANCEL
+            } else {
+                ExceptionOption::PED_EXCEPTION_UNHANDLED
+            }
+        },
+        func,
+    );
+
+    result.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::Other,
+            message.unwrap_or_else(|| fallback.to_owned()),
+        )
+    })
+}
@@ -1,9 +1,10 @@
-use super::{cvt, get_optional, Geometry, Timer};
+use super::events::{self, ProgressSink};
+use super::{cvt, get_optional, instrument, probe, Geometry, Timer};
 use libparted_sys::{
     ped_file_system_alias_get_next, ped_file_system_alias_register,
     ped_file_system_alias_unregister, ped_file_system_resize, ped_file_system_type_get,
     ped_file_system_type_get_next, ped_file_system_type_register, ped_file_system_type_unregister,
-    PedFileSystem, PedFileSystemAlias, PedFileSystemType,
+    PedFileSystem, PedFileSystemAlias, PedFileSystemOps, PedFileSystemType,
 };
 use std::ffi::{CStr, CString};
 use std::io;
@@ -98,7 +99,39 @@ impl<'a> FileSystem<'a> {
     /// Throws `PED_EXCEPTION_NO_FEATURE` if resizing of the file system is not implemented yet.
     pub fn resize(&mut self, geom: &Geometry, timer: Option<&mut Timer>) -> io::Result<()> {
         let timer = timer.map_or(ptr::null_mut(), |t| t.timer);
-        cvt(unsafe { ped_file_system_resize(self.fs, geom.geometry, timer) }).map(|_| ())
+        instrument::record("fs_resize", || {
+            cvt(unsafe { ped_file_system_resize(self.fs, geom.geometry, timer) })
+        })
+        .map(|_| ())
+    }
+
+    /// Checks that every sector of the file system can be read. If `timer` is not `None`, it
+    /// will be used as the progress meter.
+    ///
+    /// # Note:
+    ///
+    /// libparted 3 no longer checks the consistency of file systems, so this only finds
+    /// physical defects. Use the file system's own `fsck` tool to check its structures.
+    pub fn check(&self, timer: Option<&mut Timer>) -> io::Result<()> {
+        let mut geom = Geometry::from_raw(unsafe { (*self.fs).geom });
+        geom.is_droppable = false;
+        let timer = timer.map_or(ptr::null_mut(), |t| t.timer);
+        geom.check_raw(0, 1, geom.length(), timer)
+            .map_err(|sector| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("sector {} of the file system could not be read", sector),
+                )
+            })
+    }
+
+    /// Resizes the file system to a new geometry, reporting its progress to `sink`.
+    pub fn resize_with_progress(
+        &mut self,
+        geom: &Geometry,
+        sink: Option<&mut dyn ProgressSink>,
+    ) -> io::Result<()> {
+        events::observe(sink, |timer| self.resize(geom, timer))
     }
 }
 
@@ -159,6 +192,24 @@ impl<'a> FileSystemType<'a> {
 
     // TODO: fn ops()
 
+    /// Whether libparted can resize file systems of this type itself, in either direction.
+    ///
+    /// Other file systems must be resized with their own tools, such as `resize2fs` or
+    /// `ntfsresize`, before or after their partition is resized.
+    pub fn supports_resize(&self) -> bool {
+        self.supports_grow() || self.supports_shrink()
+    }
+
+    /// Whether libparted can grow file systems of this type.
+    pub fn supports_grow(&self) -> bool {
+        resize_support(self.name()).0
+    }
+
+    /// Whether libparted can shrink file systems of this type.
+    pub fn supports_shrink(&self) -> bool {
+        resize_support(self.name()).1
+    }
+
     /// Get a **FileSystemType** by its `name`.
     pub fn get(name: &str) -> Option<FileSystemType<'a>> {
         CString::new(name.as_bytes()).ok().and_then(|name_cstr| {
@@ -168,6 +219,40 @@ impl<'a> FileSystemType<'a> {
         })
     }
 
+    /// Registers a file system type named `name`, which libparted recognizes wherever `probe`
+    /// returns `true` for a region, so that it is reported by `Partition::fs_type_name()`.
+    ///
+    /// # Note:
+    ///
+    /// Registrations are permanent, as libparted keeps pointers to them, and at most 8 may be
+    /// made by a process. A probe which panics is treated as not having found the file system.
+    pub fn register_probe<F>(name: &str, probe: F) -> io::Result<FileSystemType<'static>>
+    where
+        F: Fn(&Geometry) -> bool + Send + Sync + 'static,
+    {
+        let name = CString::new(name)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "name contains a nul byte"))?;
+
+        let trampoline = probe::install(Box::new(probe)).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Other,
+                "no more file system probes may be registered",
+            )
+        })?;
+
+        let ops = Box::into_raw(Box::new(PedFileSystemOps {
+            probe: Some(trampoline),
+        }));
+
+        let mut fs_type = FileSystemType::from_raw(Box::into_raw(Box::new(PedFileSystemType {
+            next: ptr::null_mut(),
+            name: name.into_raw(),
+            ops,
+        })));
+        fs_type.register();
+        Ok(fs_type)
+    }
+
     pub fn register(&mut self) {
         unsafe { ped_file_system_type_register(self.fs) }
     }
@@ -192,6 +277,19 @@ impl<'a> FileSystemType<'a> {
     }
 }
 
+/// Whether libparted-fs-resize can grow and shrink the named file system type, respectively.
+///
+/// libparted does not expose this through the file system type's operations, so it mirrors
+/// the file systems which `ped_file_system_resize()` can open.
+fn resize_support(name: &str) -> (bool, bool) {
+    match name {
+        "fat16" | "fat32" => (true, true),
+        // HFS volumes may only be shrunk.
+        "hfs" | "hfs+" | "hfsx" => (false, true),
+        _ => (false, false),
+    }
+}
+
 pub struct FileSystemAliasIter<'a>(&'a FileSystemAlias<'a>, *mut PedFileSystemAlias);
 
 impl<'a> Iterator for FileSystemAliasIter<'a> {
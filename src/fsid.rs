@@ -0,0 +1,256 @@
+//! Reads the UUID and label of a file system from its superblock, as `blkid` reports them,
+//! for the file systems commonly listed in `/etc/fstab`.
+//!
+//! This is experimental, and only the primary superblock of each file system is read. The
+//! label of a FAT file system is read from its boot sector, and not its root directory.
+
+use super::gpt;
+use super::signatures::{le_u16, le_u32, le_u64, read_bytes};
+use super::SectorIo;
+use std::io;
+
+/// The identifiers of a file system, as returned by `identify()`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct FsIdentity {
+    pub uuid: Option<String>,
+    pub label: Option<String>,
+}
+
+/// Reads the UUID and label of the ext2/3/4, XFS, swap, NTFS, or FAT file system in `geom`,
+/// or `None` if none of them is found.
+pub(crate) fn identify<T: SectorIo>(geom: &T) -> io::Result<Option<FsIdentity>> {
+    if let Some(found) = ext(geom)? {
+        return Ok(Some(found));
+    }
+    if let Some(found) = xfs(geom)? {
+        return Ok(Some(found));
+    }
+    if let Some(found) = swap(geom)? {
+        return Ok(Some(found));
+    }
+    if let Some(found) = ntfs(geom)? {
+        return Ok(Some(found));
+    }
+    fat(geom)
+}
+
+fn ext<T: SectorIo>(geom: &T) -> io::Result<Option<FsIdentity>> {
+    let sb = match read_bytes(geom, 1024, 136)? {
+        Some(ref sb) if le_u16(&sb[56..58]) == 0xEF53 => sb.clone(),
+        _ => return Ok(None),
+    };
+
+    Ok(Some(FsIdentity {
+        uuid: uuid(&sb[104..120]),
+        label: label(&sb[120..136]),
+    }))
+}
+
+fn xfs<T: SectorIo>(geom: &T) -> io::Result<Option<FsIdentity>> {
+    let sb = match read_bytes(geom, 0, 120)? {
+        Some(ref sb) if &sb[..4] == b"XFSB" => sb.clone(),
+        _ => return Ok(None),
+    };
+
+    Ok(Some(FsIdentity {
+        uuid: uuid(&sb[32..48]),
+        label: label(&sb[108..120]),
+    }))
+}
+
+fn swap<T: SectorIo>(geom: &T) -> io::Result<Option<FsIdentity>> {
+    // The signature is at the end of the first page, whose size depends on the architecture
+    // the swap area was created on.
+    for &page in &[4096, 8192, 16384, 65536] {
+        match read_bytes(geom, page - 10, 10)? {
+            Some(ref magic) if magic == b"SWAPSPACE2" => (),
+            _ => continue,
+        }
+
+        return Ok(read_bytes(geom, 1036, 32)?.map(|header| FsIdentity {
+            uuid: uuid(&header[..16]),
+            label: label(&header[16..32]),
+        }));
+    }
+
+    Ok(None)
+}
+
+fn ntfs<T: SectorIo>(geom: &T) -> io::Result<Option<FsIdentity>> {
+    let boot = match read_bytes(geom, 0, 512)? {
+        Some(ref boot) if &boot[3..11] == b"NTFS    " => boot.clone(),
+        _ => return Ok(None),
+    };
+
+    let serial = le_u64(&boot[0x48..0x50]);
+    Ok(Some(FsIdentity {
+        uuid: Some(format!("{:016X}", serial)).filter(|_| serial != 0),
+        label: ntfs_label(geom, &boot)?,
+    }))
+}
+
+/// Reads the `$VOLUME_NAME` attribute of the `$Volume` file, which is the fourth record of
+/// the master file table.
+fn ntfs_label<T: SectorIo>(geom: &T, boot: &[u8]) -> io::Result<Option<String>> {
+    let sector_size = u64::from(le_u16(&boot[0x0B..0x0D]));
+    let cluster_size = sector_size * u64::from(boot[0x0D]);
+    let record_size = match boot[0x40] as i8 {
+        size if size < 0 => 1u64.checked_shl(-i32::from(size) as u32).unwrap_or(0),
+        size => (size as u64).checked_mul(cluster_size).unwrap_or(0),
+    };
+    if sector_size == 0 || cluster_size == 0 || !(48..=65536).contains(&record_size) {
+        return Ok(None);
+    }
+
+    // The location of the table is read from the disk, so may be anywhere at all.
+    let offset = match le_u64(&boot[0x30..0x38])
+        .checked_mul(cluster_size)
+        .and_then(|mft| mft.checked_add(3 * record_size))
+    {
+        Some(offset) => offset,
+        None => return Ok(None),
+    };
+    let mut record = match read_bytes(geom, offset, record_size)? {
+        Some(ref record) if &record[..4] == b"FILE" => record.clone(),
+        _ => return Ok(None),
+    };
+
+    // The last two bytes of each sector of the record were swapped out for a sequence
+    // number when it was written, and are put back from the update sequence array.
+    let fixups = le_u16(&record[4..6]) as usize;
+    let count = le_u16(&record[6..8]) as usize;
+    for i in 1..count {
+        let end = i * 512;
+        if end > record.len() || fixups + i * 2 + 2 > record.len() {
+            break;
+        }
+        let (original, sector) = (fixups + i * 2, end - 2);
+        let value = [record[original], record[original + 1]];
+        record[sector..end].copy_from_slice(&value);
+    }
+
+    let mut attr = le_u16(&record[0x14..0x16]) as usize;
+    while attr + 24 <= record.len() {
+        let kind = le_u32(&record[attr..attr + 4]);
+        let length = le_u32(&record[attr + 4..attr + 8]) as usize;
+        if kind == 0xFFFF_FFFF || length == 0 {
+            break;
+        }
+
+        // A resident `$VOLUME_NAME` attribute, holding the label in UTF-16.
+        if kind == 0x60 && record[attr + 8] == 0 {
+            let size = le_u32(&record[attr + 0x10..attr + 0x14]) as usize;
+            let start = attr + le_u16(&record[attr + 0x14..attr + 0x16]) as usize;
+            let name = match start
+                .checked_add(size)
+                .and_then(|end| record.get(start..end))
+            {
+                Some(name) => name,
+                None => return Ok(None),
+            };
+            let units = name
+                .chunks(2)
+                .filter(|unit| unit.len() == 2)
+                .map(le_u16)
+                .collect::<Vec<_>>();
+            return Ok(Some(String::from_utf16_lossy(&units)).filter(|name| !name.is_empty()));
+        }
+
+        attr = match attr.checked_add(length) {
+            Some(next) => next,
+            None => break,
+        };
+    }
+
+    Ok(None)
+}
+
+fn fat<T: SectorIo>(geom: &T) -> io::Result<Option<FsIdentity>> {
+    let boot = match read_bytes(geom, 0, 512)? {
+        Some(ref boot) if boot[510..512] == [0x55, 0xAA] => boot.clone(),
+        _ => return Ok(None),
+    };
+
+    // FAT32 extends the BIOS parameter block, which moves the serial number and label.
+    let (serial, name) = if &boot[0x52..0x57] == b"FAT32" {
+        (&boot[0x43..0x47], &boot[0x47..0x52])
+    } else if &boot[0x36..0x39] == b"FAT" {
+        (&boot[0x27..0x2B], &boot[0x2B..0x36])
+    } else {
+        return Ok(None);
+    };
+
+    let serial = le_u32(serial);
+    Ok(Some(FsIdentity {
+        uuid: Some(format!("{:04X}-{:04X}", serial >> 16, serial & 0xFFFF)),
+        label: label(name).filter(|name| name != "NO NAME"),
+    }))
+}
+
+/// Formats a UUID stored in RFC 4122 byte order as `blkid` does, or `None` if it is unset.
+fn uuid(bytes: &[u8]) -> Option<String> {
+    if bytes.iter().all(|&byte| byte == 0) {
+        return None;
+    }
+
+    let mut uuid = [0; 16];
+    uuid.copy_from_slice(bytes);
+    Some(gpt::format_uuid(&uuid).to_lowercase())
+}
+
+/// Reads a label padded with nuls or spaces, or `None` if it is empty.
+fn label(bytes: &[u8]) -> Option<String> {
+    let end = bytes
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap_or(bytes.len());
+    let label = String::from_utf8_lossy(&bytes[..end]).trim_end().to_owned();
+    Some(label).filter(|label| !label.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryDevice;
+
+    fn ntfs_boot(mft_cluster: u64) -> MemoryDevice {
+        let mut boot = vec![0; 512];
+        boot[3..11].copy_from_slice(b"NTFS    ");
+        boot[0x0B..0x0D].copy_from_slice(&512u16.to_le_bytes());
+        boot[0x0D] = 8;
+        boot[0x30..0x38].copy_from_slice(&mft_cluster.to_le_bytes());
+        boot[0x40] = 0xF6;
+        boot[0x48..0x50].copy_from_slice(&0x1234_5678_9ABC_DEF0u64.to_le_bytes());
+
+        let mut device = MemoryDevice::new(64, 512);
+        device.write_sectors(&boot, 0, 1).unwrap();
+        device
+    }
+
+    #[test]
+    fn ntfs_table_beyond_any_device_has_no_label() {
+        for &cluster in &[u64::MAX, u64::MAX / 4096, 1 << 40] {
+            let found = identify(&ntfs_boot(cluster)).unwrap().unwrap();
+            assert_eq!(found.uuid.as_deref(), Some("123456789ABCDEF0"));
+            assert_eq!(found.label, None);
+        }
+    }
+
+    #[test]
+    fn reads_ext_identity() {
+        let mut sb = vec![0; 1024];
+        sb[56..58].copy_from_slice(&0xEF53u16.to_le_bytes());
+        sb[104..120].copy_from_slice(&[0x11; 16]);
+        sb[120..124].copy_from_slice(b"root");
+
+        let mut device = MemoryDevice::new(64, 512);
+        device.write_sectors(&sb, 2, 2).unwrap();
+        assert_eq!(
+            identify(&device).unwrap(),
+            Some(FsIdentity {
+                uuid: Some("11111111-1111-1111-1111-111111111111".into()),
+                label: Some("root".into()),
+            })
+        );
+    }
+}
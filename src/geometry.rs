@@ -1,5 +1,10 @@
+use super::events::{self, ProgressSink};
+#[cfg(feature = "discard")]
+use super::kernel;
 use super::{
-    cvt, get_optional, Constraint, ConstraintSource, Device, FileSystem, FileSystemType, Timer,
+    copy, cvt, device, get_optional, imaging, instrument, BufferSizeError, Constraint,
+    ConstraintSource, Device, DeviceHandle, DeviceRef, FileSystem, FileSystemType, Sector,
+    SectorIo, Timer,
 };
 use libparted_sys::{
     ped_constraint_exact, ped_file_system_open, ped_file_system_probe,
@@ -7,11 +12,50 @@ use libparted_sys::{
     ped_geometry_duplicate, ped_geometry_init, ped_geometry_intersect, ped_geometry_map,
     ped_geometry_new, ped_geometry_read, ped_geometry_set, ped_geometry_set_end,
     ped_geometry_set_start, ped_geometry_sync, ped_geometry_sync_fast, ped_geometry_test_equal,
-    ped_geometry_test_inside, ped_geometry_write, PedGeometry,
+    ped_geometry_test_inside, ped_geometry_test_overlap, ped_geometry_write, PedGeometry, PedTimer,
 };
-use std::io;
+use std::fs::File;
+#[cfg(feature = "discard")]
+use std::fs::OpenOptions;
+use std::io::{self, Read, Seek, SeekFrom, Write};
 use std::marker::PhantomData;
 use std::os::raw::c_void;
+use std::path::Path;
+use std::ptr;
+
+/// A plain copy of the boundaries of a region, read once from libparted.
+///
+/// Unlike **Geometry**, this does not refer to any libparted memory, so it may be freely
+/// copied and kept after the object it was read from has been destroyed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct GeomView {
+    pub start: i64,
+    pub end: i64,
+    pub length: i64,
+}
+
+impl GeomView {
+    /// Tests if `sector` is inside the region.
+    pub fn contains_sector(&self, sector: i64) -> bool {
+        sector >= self.start && sector <= self.end
+    }
+}
+
+/// The data written over a region by `Geometry::wipe()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WipePattern {
+    /// Every byte is set to zero.
+    Zeros,
+    /// Every byte is set to `0xFF`.
+    Ones,
+    /// Every byte is set to the given value.
+    Byte(u8),
+    /// Bytes are read from `/dev/urandom`.
+    Random,
+}
+
+/// The number of sectors a **GeometryReader** or **GeometryWriter** buffers at a time.
+const STREAM_CHUNK_SECTORS: i64 = 256;
 
 pub struct Geometry<'a> {
     pub(crate) geometry: *mut PedGeometry,
@@ -57,17 +101,43 @@ impl<'a> Geometry<'a> {
         count: i64,
         timer: &Timer,
     ) -> Result<(), u64> {
-        let mut buffer: Vec<u8> = Vec::with_capacity(8192);
-        let buffer_ptr = buffer.as_mut_slice().as_mut_ptr();
+        self.check_raw(offset, granularity, count, timer.timer)
+    }
+
+    /// Checks for physical disk errors, as with `Geometry::check()`, reporting the progress
+    /// of the check to `sink`.
+    pub fn check_with_progress(
+        &self,
+        offset: i64,
+        granularity: i64,
+        count: i64,
+        sink: Option<&mut dyn ProgressSink>,
+    ) -> Result<(), u64> {
+        events::observe(sink, |timer| {
+            let timer = timer.map_or(ptr::null_mut(), |timer| timer.timer);
+            self.check_raw(offset, granularity, count, timer)
+        })
+    }
+
+    pub(crate) fn check_raw(
+        &self,
+        offset: i64,
+        granularity: i64,
+        count: i64,
+        timer: *mut PedTimer,
+    ) -> Result<(), u64> {
+        // libparted reads up to this many sectors into the buffer at a time.
+        const BUFFER_SECTORS: i64 = 256;
+        let mut buffer = vec![0u8; BUFFER_SECTORS as usize * self.sector_size() as usize];
         let result = unsafe {
             ped_geometry_check(
                 self.geometry,
-                buffer_ptr as *mut c_void,
-                buffer.len() as i64,
+                buffer.as_mut_ptr() as *mut c_void,
+                BUFFER_SECTORS,
                 offset,
                 granularity,
                 count,
-                timer.timer,
+                timer,
             )
         };
 
@@ -77,12 +147,38 @@ impl<'a> Geometry<'a> {
         }
     }
 
+    /// Returns a view of the device on which the region lies.
+    pub fn device(&self) -> DeviceRef {
+        unsafe { DeviceRef::from_ped_device((*self.geometry).dev) }
+    }
+
+    /// Obtains a shared handle to the device on which the region lies.
+    pub fn handle(&self) -> io::Result<DeviceHandle> {
+        unsafe { DeviceHandle::from_ped_device((*self.geometry).dev) }
+    }
+
+    #[cfg_attr(
+        feature = "v2-api",
+        deprecated(
+            since = "0.1.6",
+            note = "use `device()`, or `handle()` to keep it open"
+        )
+    )]
     pub fn dev(&self) -> Device {
-        unsafe { Device::from_ped_device((*self.geometry).dev) }
+        self.raw_device()
     }
 
+    #[cfg_attr(
+        feature = "v2-api",
+        deprecated(since = "0.1.6", note = "use `device()` instead")
+    )]
     pub fn dev_mut(&mut self) -> Device {
-        unsafe { Device::from_ped_device((*self.geometry).dev) }
+        self.raw_device()
+    }
+
+    /// The device on which the region lies, which will not be closed when dropped.
+    fn raw_device(&self) -> Device<'a> {
+        unsafe { Device::borrowed((*self.geometry).dev) }
     }
 
     /// Duplicate a `Geometry` object.
@@ -94,8 +190,18 @@ impl<'a> Geometry<'a> {
         unsafe { (*self.geometry).end }
     }
 
+    /// The last sector of the region, as a **Sector**.
+    pub fn end_sector(&self) -> Sector {
+        Sector(self.end())
+    }
+
     /// Initializes a pre-allocated **Geometry**.
-    pub fn init(&mut self, device: &Device, start: i64, length: i64) -> io::Result<()> {
+    pub fn init<S, L>(&mut self, device: &Device, start: S, length: L) -> io::Result<()>
+    where
+        S: Into<Sector>,
+        L: Into<Sector>,
+    {
+        let (start, length) = (start.into().0, length.into().0);
         cvt(unsafe { ped_geometry_init(self.geometry, device.ped_device(), start, length) })
             .map(|_| ())
     }
@@ -111,16 +217,21 @@ impl<'a> Geometry<'a> {
         unsafe { (*self.geometry).length }
     }
 
+    /// The number of sectors in the region, as a **Sector**.
+    pub fn length_sectors(&self) -> Sector {
+        Sector(self.length())
+    }
+
     /// Takes a `sector` inside the region described by `src` and returns that sector's address
     /// inside of our own **Geometry** marked as `self`. This means that the following
     /// code examples are equivalent:
     ///
     /// ```
-    /// geometry.read(buf, geometry.map(src, sector), 1);
+    /// geometry.read_to_vec(geometry.map(src, sector), 1);
     /// ```
     ///
     /// ```
-    /// geometry.read(buf, sector, 1);
+    /// src.read_to_vec(sector, 1);
     /// ```
     ///
     /// Clearly, this will only work if `self` and `src` overlap.
@@ -135,55 +246,217 @@ impl<'a> Geometry<'a> {
 
     /// Create a new **Geometry** object on `disk`, starting at `start`
     /// with a size of `length` sectors.
-    pub fn new(device: &Device, start: i64, length: i64) -> io::Result<Geometry<'a>> {
+    pub fn new<S, L>(device: &Device, start: S, length: L) -> io::Result<Geometry<'a>>
+    where
+        S: Into<Sector>,
+        L: Into<Sector>,
+    {
+        let (start, length) = (start.into().0, length.into().0);
         cvt(unsafe { ped_geometry_new(device.ped_device(), start, length) }).map(Geometry::from_raw)
     }
 
+    /// Create a new **Geometry** object on `device`, as with `Geometry::new()`, but only
+    /// accepting **Sector**s, so that a byte offset cannot be passed by mistake.
+    pub fn with_sectors(
+        device: &Device,
+        start: Sector,
+        length: Sector,
+    ) -> io::Result<Geometry<'a>> {
+        Geometry::new(device, start, length)
+    }
+
+    /// Assign a new `start` and `length`, as with `Geometry::set()`, but only accepting
+    /// **Sector**s, so that a byte offset cannot be passed by mistake.
+    pub fn set_sectors(&mut self, start: Sector, length: Sector) -> io::Result<()> {
+        self.set(start, length)
+    }
+
     /// Reads data from the region within our `Geometry`. `offset` is the location from within
     /// the region, not from the start of the disk. `count` sectors are read into `buffer`. An
     /// exception is thrown when attempting to read sectors outside of the partition.
     ///
     /// # Note:
     ///
-    /// The supplied vector will be reallocated to the correct size automatically.
+    /// The supplied vector will be resized to hold exactly the data that was read.
     ///
     /// # Throws:
     ///
     /// Throws `PED_EXCEPTION_ERROR` when attempting to read sectors outside of partition.
+    #[cfg_attr(
+        feature = "v2-api",
+        deprecated(
+            since = "0.1.6",
+            note = "use `read_to_vec()`, or `read_into()` to reuse a buffer"
+        )
+    )]
     pub fn read(&self, buffer: &mut Vec<u8>, offset: i64, count: i64) -> io::Result<()> {
-        // Ensure that the buffer is the correct length to hold the data
+        *buffer = self.read_to_vec(offset, count)?;
+        Ok(())
+    }
+
+    /// Reads `count` sectors from the region within our `Geometry`, returning a vector which
+    /// holds exactly the data that was read. `offset` is the location from within the region,
+    /// not from the start of the disk.
+    ///
+    /// # Throws:
+    ///
+    /// Throws `PED_EXCEPTION_ERROR` when attempting to read sectors outside of partition.
+    pub fn read_to_vec(&self, offset: i64, count: i64) -> io::Result<Vec<u8>> {
+        if count < 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "cannot read a negative number of sectors",
+            ));
+        }
+
+        let sector_size = unsafe { (*(*self.geometry).dev).sector_size as usize };
+        let mut buffer = vec![0; count as usize * sector_size];
+        self.read_into(&mut buffer, offset, count)?;
+        Ok(buffer)
+    }
+
+    /// Writes the raw contents of the region to a new image file at `path`, returning the
+    /// number of bytes written. If `timer` is not `None`, it will be used as the progress meter.
+    ///
+    /// The image file is written with `O_DIRECT` where its file system supports it.
+    pub fn dump_to<P: AsRef<Path>>(&self, path: P, timer: Option<&mut Timer>) -> io::Result<u64> {
+        imaging::dump(self, path.as_ref(), timer)
+    }
+
+    /// Writes the image file at `path` to the start of the region, returning the number of
+    /// bytes restored. If `timer` is not `None`, it will be used as the progress meter.
+    ///
+    /// The image must not be larger than the region. If it does not end on a sector boundary,
+    /// the remainder of its last sector is zeroed.
+    pub fn restore_from<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        timer: Option<&mut Timer>,
+    ) -> io::Result<u64> {
+        imaging::restore(self, path.as_ref(), timer)
+    }
+
+    /// Returns a reader over the contents of the region, for use with APIs which consume
+    /// `std::io::Read`, such as hashers.
+    ///
+    /// ```rust,no_run
+    /// # use libparted::{Device, Geometry};
+    /// # use std::io;
+    /// let device = Device::new("/dev/sda").unwrap();
+    /// let geom = Geometry::new(&device, 2048, 2048).unwrap();
+    /// let copied = io::copy(&mut geom.reader(), &mut io::sink()).unwrap();
+    /// ```
+    pub fn reader<'g>(&'g self) -> GeometryReader<'g, 'a> {
+        SectorReader::new(self)
+    }
+
+    /// Returns a writer into the region, such as for flashing a file system image into a
+    /// partition with `std::io::copy()`.
+    ///
+    /// Data is buffered until it fills a chunk of sectors, the writer is flushed, or it is
+    /// dropped. Bytes of partially written sectors which were not written to are preserved.
+    /// Flush the writer to observe errors, as those which occur while dropping are ignored.
+    pub fn writer<'g>(&'g mut self) -> GeometryWriter<'g, 'a> {
+        SectorWriter::new(self)
+    }
+
+    /// Returns a copy of the start, end, and length of the region.
+    pub fn view(&self) -> GeomView {
+        let geom = unsafe { *self.geometry };
+        GeomView {
+            start: geom.start,
+            end: geom.end,
+            length: geom.length,
+        }
+    }
+
+    /// Reads `count` sectors, starting `offset` sectors into the region, into the beginning
+    /// of `buffer`, which must be large enough to hold them.
+    ///
+    /// # Throws:
+    ///
+    /// Throws `PED_EXCEPTION_ERROR` when attempting to read sectors outside of partition.
+    pub fn read_into(&self, buffer: &mut [u8], offset: i64, count: i64) -> io::Result<()> {
         let sector_size = unsafe { (*(*self.geometry).dev).sector_size as usize };
-        buffer.reserve(count as usize * sector_size + 1);
+        BufferSizeError::check_read(buffer.len(), count, sector_size as u64)?;
+        let total_size = count as usize * sector_size;
 
-        // Then fire away with reading using a pointer to the buffer.
-        let buffer_ptr = buffer.as_mut_slice().as_mut_ptr() as *mut c_void;
-        cvt(unsafe { ped_geometry_read(self.geometry, buffer_ptr, offset, count) }).map(|_| ())
+        device::check_not_external(unsafe { (*self.geometry).dev })?;
+        let buffer_ptr = buffer.as_mut_ptr() as *mut c_void;
+        let geometry = self.geometry;
+        instrument::record("geometry_read", || {
+            cvt(unsafe { ped_geometry_read(geometry, buffer_ptr, offset, count) })
+        })?;
+        instrument::bytes_read("geometry_read", total_size as u64);
+        Ok(())
     }
 
     /// Assign a new `start` and `length`, where `end` will also be set implicitly from those
     /// values.
-    pub fn set(&mut self, start: i64, length: i64) -> io::Result<()> {
+    pub fn set<S, L>(&mut self, start: S, length: L) -> io::Result<()>
+    where
+        S: Into<Sector>,
+        L: Into<Sector>,
+    {
+        let (start, length) = (start.into().0, length.into().0);
         cvt(unsafe { ped_geometry_set(self.geometry, start, length) }).map(|_| ())
     }
 
     /// Assign a new end to `self` without changing `self->start` field.
     ///
     /// `self->length` will be updated accordingly.
-    pub fn set_end(&mut self, end: i64) -> io::Result<()> {
-        cvt(unsafe { ped_geometry_set_end(self.geometry, end) }).map(|_| ())
+    pub fn set_end<S: Into<Sector>>(&mut self, end: S) -> io::Result<()> {
+        cvt(unsafe { ped_geometry_set_end(self.geometry, end.into().0) }).map(|_| ())
     }
 
     /// Assign a new start to `self` witout changing `self->end`.
     ///
     /// `self->length` will be updated accordingly.
-    pub fn set_start(&mut self, start: i64) -> io::Result<()> {
-        cvt(unsafe { ped_geometry_set_start(self.geometry, start) }).map(|_| ())
+    pub fn set_start<S: Into<Sector>>(&mut self, start: S) -> io::Result<()> {
+        cvt(unsafe { ped_geometry_set_start(self.geometry, start.into().0) }).map(|_| ())
+    }
+
+    /// The size, in bytes, of the sectors of the device this region is on.
+    pub fn sector_size(&self) -> u64 {
+        unsafe { (*(*self.geometry).dev).sector_size as u64 }
     }
 
     pub fn start(&self) -> i64 {
         unsafe { (*self.geometry).start }
     }
 
+    /// The first sector of the region, as a **Sector**.
+    pub fn start_sector(&self) -> Sector {
+        Sector(self.start())
+    }
+
+    /// Discards the sectors of the region (the `BLKDISCARD` ioctl), so that an SSD or
+    /// thinly-provisioned device may reclaim them, such as before a new partition is used.
+    ///
+    /// # Note:
+    ///
+    /// Whether discarded sectors then read back as zeros depends on the device, so this is not
+    /// a substitute for `Geometry::zero()`. Devices which do not support discards return an
+    /// error.
+    ///
+    /// The device is opened for the duration if it is not already open.
+    #[cfg(feature = "discard")]
+    pub fn discard(&mut self) -> io::Result<()> {
+        {
+            // libparted can only sync a region of a device which is open.
+            let _handle = self.handle()?;
+            cvt(unsafe { ped_geometry_sync(self.geometry) })?;
+        }
+        let device = self.device();
+        let sector_size = device.sector_size();
+        let file = OpenOptions::new().write(true).open(device.path())?;
+        kernel::discard(
+            &file,
+            self.start() as u64 * sector_size,
+            self.length() as u64 * sector_size,
+        )
+    }
+
     /// Flushes the cache on `self`.
     ///
     /// This function flushses all write-behind caches that might be holding writes made by
@@ -214,27 +487,158 @@ impl<'a> Geometry<'a> {
         unsafe { ped_geometry_test_inside(self.geometry, other.geometry) == 1 }
     }
 
+    /// Tests if the `other` **Geometry** shares any sectors with `self`, as libparted
+    /// determines it. Equivalent to `overlaps()`.
+    pub fn test_overlap(&self, other: &Geometry) -> bool {
+        unsafe { ped_geometry_test_overlap(self.geometry, other.geometry) == 1 }
+    }
+
+    /// Returns the sector within the geometry which is closest to `sector` (ie: its start if
+    /// `sector` is before the geometry, or its end if `sector` is after it).
+    pub fn clamp_sector(&self, sector: i64) -> i64 {
+        sector.max(self.start()).min(self.end())
+    }
+
     /// Tests if `sector` is inside the geometry.
     pub fn test_sector_inside(&self, sector: i64) -> bool {
         debug_assert!(!self.geometry.is_null());
         sector >= self.start() && sector <= self.end()
     }
 
+    /// Tests if `sector` is inside the geometry. Equivalent to `test_sector_inside()`.
+    pub fn contains_sector(&self, sector: i64) -> bool {
+        self.test_sector_inside(sector)
+    }
+
+    /// Tests if `self` and `other` share any sectors of the same device.
+    pub fn overlaps(&self, other: &Geometry) -> bool {
+        self.same_device(other) && self.start() <= other.end() && other.start() <= self.end()
+    }
+
+    /// Returns the parts of `self` which are not within `other`, in order. There may be none,
+    /// one, or two such regions.
+    pub fn subtract(&self, other: &Geometry) -> io::Result<Vec<Geometry<'a>>> {
+        if !self.overlaps(other) {
+            return Ok(vec![self.duplicate()?]);
+        }
+
+        let mut regions = Vec::new();
+        if self.start() < other.start() {
+            regions.push(self.region(self.start(), other.start() - 1)?);
+        }
+
+        if other.end() < self.end() {
+            regions.push(self.region(other.end() + 1, self.end())?);
+        }
+
+        Ok(regions)
+    }
+
+    /// If `self` and `other` are adjacent or overlap, returns a region covering both.
+    pub fn union_if_adjacent(&self, other: &Geometry) -> io::Result<Option<Geometry<'a>>> {
+        if !self.same_device(other)
+            || self.start() > other.end() + 1
+            || other.start() > self.end() + 1
+        {
+            return Ok(None);
+        }
+
+        self.region(self.start().min(other.start()), self.end().max(other.end()))
+            .map(Some)
+    }
+
+    fn same_device(&self, other: &Geometry) -> bool {
+        unsafe { (*self.geometry).dev == (*other.geometry).dev }
+    }
+
+    /// Creates a region from `start` to `end`, inclusive, on the same device as `self`.
+    fn region(&self, start: i64, end: i64) -> io::Result<Geometry<'a>> {
+        cvt(unsafe { ped_geometry_new((*self.geometry).dev, start, end - start + 1) })
+            .map(Geometry::from_raw)
+    }
+
     /// Writes data into the region represented by `self`. The `offset` is the location
     /// from within the region, not from the start of the disk. `count` sectors are to be written.
     pub fn write_to_sectors(&mut self, buffer: &[u8], offset: i64, count: i64) -> io::Result<()> {
         let sector_size = unsafe { (*(*self.geometry).dev).sector_size as usize };
+        BufferSizeError::check_write(buffer.len(), count, sector_size as u64)?;
         let total_size = sector_size * count as usize;
-        if buffer.len() != total_size {
-            let mut new_buffer = Vec::with_capacity(total_size);
-            new_buffer.extend_from_slice(buffer);
-            new_buffer.extend((buffer.len()..total_size).map(|_| b'0'));
-            let buffer_ptr = new_buffer.as_slice().as_ptr() as *const c_void;
-            cvt(unsafe { ped_geometry_write(self.geometry, buffer_ptr, offset, count) }).map(|_| ())
-        } else {
-            let buffer_ptr = buffer.as_ptr() as *const c_void;
-            cvt(unsafe { ped_geometry_write(self.geometry, buffer_ptr, offset, count) }).map(|_| ())
+        device::check_not_external(unsafe { (*self.geometry).dev })?;
+        let geometry = self.geometry;
+        instrument::record("geometry_write", || {
+            if buffer.len() != total_size {
+                let mut new_buffer = Vec::with_capacity(total_size);
+                new_buffer.extend_from_slice(buffer);
+                new_buffer.extend((buffer.len()..total_size).map(|_| b'0'));
+                let buffer_ptr = new_buffer.as_slice().as_ptr() as *const c_void;
+                cvt(unsafe { ped_geometry_write(geometry, buffer_ptr, offset, count) })
+            } else {
+                let buffer_ptr = buffer.as_ptr() as *const c_void;
+                cvt(unsafe { ped_geometry_write(geometry, buffer_ptr, offset, count) })
+            }
+        })?;
+        instrument::bytes_written("geometry_write", total_size as u64);
+        Ok(())
+    }
+
+    /// Overwrites the entire region with zeros.
+    pub fn zero(&mut self) -> io::Result<()> {
+        self.wipe(WipePattern::Zeros, 1, None)
+    }
+
+    /// Overwrites the entire region with `pattern`, `passes` times over.
+    ///
+    /// The region is written in large chunks, and synced to the device once all passes are
+    /// complete. If `timer` is not `None`, it will be used as the progress meter.
+    pub fn wipe(
+        &mut self,
+        pattern: WipePattern,
+        passes: u32,
+        mut timer: Option<&mut Timer>,
+    ) -> io::Result<()> {
+        // libparted can only write to a region of a device which is open. The handle is
+        // obtained from the device itself, so that the region may still be written while it
+        // is held.
+        let _handle = unsafe { DeviceHandle::from_ped_device((*self.geometry).dev)? };
+        let sector_size = self.sector_size() as i64;
+        let chunk = (copy::CHUNK_SIZE / sector_size).max(1);
+        let mut buffer = vec![0u8; (chunk * sector_size) as usize];
+        let mut random = match pattern {
+            WipePattern::Random => Some(File::open("/dev/urandom")?),
+            _ => None,
+        };
+
+        match pattern {
+            WipePattern::Zeros | WipePattern::Random => (),
+            WipePattern::Ones => buffer.iter_mut().for_each(|byte| *byte = 0xFF),
+            WipePattern::Byte(value) => buffer.iter_mut().for_each(|byte| *byte = value),
+        }
+
+        if let Some(timer) = timer.as_mut() {
+            timer.reset();
+        }
+
+        let length = self.length();
+        let total = length * i64::from(passes);
+        for pass in 0..i64::from(passes) {
+            let mut offset = 0;
+            while offset < length {
+                let count = chunk.min(length - offset);
+                let buffer = &mut buffer[..(count * sector_size) as usize];
+                if let Some(random) = random.as_mut() {
+                    random.read_exact(buffer)?;
+                }
+
+                self.write_to_sectors(buffer, offset, count)?;
+                offset += count;
+
+                if let Some(timer) = timer.as_mut() {
+                    timer.update((pass * length + offset) as f32 / total as f32);
+                }
+            }
         }
+
+        self.sync()
     }
 
     /// Opens the file system stored in the given **Geometry**.
@@ -275,6 +679,14 @@ impl<'a> Geometry<'a> {
     }
 }
 
+impl<'a, 'b> PartialEq<Geometry<'b>> for Geometry<'a> {
+    fn eq(&self, other: &Geometry<'b>) -> bool {
+        self.test_equal(other)
+    }
+}
+
+impl<'a> Eq for Geometry<'a> {}
+
 impl<'a> Drop for Geometry<'a> {
     fn drop(&mut self) {
         if self.is_droppable {
@@ -282,3 +694,321 @@ impl<'a> Drop for Geometry<'a> {
         }
     }
 }
+
+/// Reads the contents of a **Geometry** as a stream of bytes, as returned by
+/// `Geometry::reader()`.
+pub type GeometryReader<'g, 'a> = SectorReader<'g, Geometry<'a>>;
+
+/// Writes a stream of bytes into a **Geometry**, as returned by `Geometry::writer()`.
+pub type GeometryWriter<'g, 'a> = SectorWriter<'g, Geometry<'a>>;
+
+/// Reads the sectors of any **SectorIo** as a stream of bytes.
+///
+/// Sectors are read from the device in chunks, so small reads do not each reach the device.
+pub struct SectorReader<'g, T: SectorIo + 'g> {
+    io: &'g T,
+    /// The offset of the next byte to be read, from the start of the region.
+    position: u64,
+    /// The sectors most recently read from the device.
+    buffer: Vec<u8>,
+    /// The offset of the first byte in `buffer`, from the start of the region.
+    buffer_start: u64,
+}
+
+impl<'g, T: SectorIo + 'g> SectorReader<'g, T> {
+    /// Creates a reader positioned at the first sector of `io`.
+    pub fn new(io: &'g T) -> SectorReader<'g, T> {
+        SectorReader {
+            io,
+            position: 0,
+            buffer: Vec::new(),
+            buffer_start: 0,
+        }
+    }
+
+    /// The length of the region in bytes.
+    fn len(&self) -> u64 {
+        self.io.length() as u64 * self.io.sector_size()
+    }
+}
+
+impl<'g, T: SectorIo + 'g> Read for SectorReader<'g, T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() || self.position >= self.len() {
+            return Ok(0);
+        }
+
+        let buffered = self.position >= self.buffer_start
+            && self.position < self.buffer_start + self.buffer.len() as u64;
+
+        if !buffered {
+            let sector_size = self.io.sector_size();
+            let sector = (self.position / sector_size) as i64;
+            let count = STREAM_CHUNK_SECTORS.min(self.io.length() - sector);
+            self.buffer.resize(count as usize * sector_size as usize, 0);
+            if let Err(why) = self.io.read_sectors(&mut self.buffer, sector, count) {
+                self.buffer.clear();
+                return Err(why);
+            }
+            self.buffer_start = sector as u64 * sector_size;
+        }
+
+        let offset = (self.position - self.buffer_start) as usize;
+        let available = &self.buffer[offset..];
+        let read = available.len().min(buf.len());
+        buf[..read].copy_from_slice(&available[..read]);
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl<'g, T: SectorIo + 'g> Seek for SectorReader<'g, T> {
+    /// Seeking beyond the end of the region is permitted, after which reads return nothing.
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.position = seek_position(pos, self.position, self.len())?;
+        Ok(self.position)
+    }
+}
+
+/// Writes a stream of bytes into the sectors of any **SectorIo**.
+///
+/// Writes are buffered in chunks of sectors, and partially written sectors are completed
+/// with their current contents. Buffered data is flushed when the writer is dropped, but
+/// errors are then ignored, so call `flush()` to observe them.
+pub struct SectorWriter<'g, T: SectorIo + 'g> {
+    io: &'g mut T,
+    /// The offset of the next byte to be written, from the start of the region.
+    position: u64,
+    /// The chunk of sectors currently being written to.
+    buffer: Vec<u8>,
+    /// The offset of the first byte in `buffer`, from the start of the region.
+    buffer_start: u64,
+    /// The range of bytes within `buffer` which have been written to, but not yet flushed.
+    dirty: Option<(usize, usize)>,
+}
+
+impl<'g, T: SectorIo + 'g> SectorWriter<'g, T> {
+    /// Creates a writer positioned at the first sector of `io`.
+    pub fn new(io: &'g mut T) -> SectorWriter<'g, T> {
+        SectorWriter {
+            io,
+            position: 0,
+            buffer: Vec::new(),
+            buffer_start: 0,
+            dirty: None,
+        }
+    }
+
+    /// The length of the region in bytes.
+    fn len(&self) -> u64 {
+        self.io.length() as u64 * self.io.sector_size()
+    }
+
+    /// Writes the dirty range of the buffer to the device, first filling the unwritten parts
+    /// of its first and last sectors with their current contents.
+    fn flush_buffer(&mut self) -> io::Result<()> {
+        let (low, high) = match self.dirty {
+            Some(range) => range,
+            None => return Ok(()),
+        };
+
+        let sector_size = self.io.sector_size() as usize;
+        let chunk_sector = (self.buffer_start / sector_size as u64) as i64;
+        let first = low / sector_size;
+        let last = (high - 1) / sector_size;
+
+        let mut original = vec![0; sector_size];
+        if low % sector_size != 0 {
+            self.io
+                .read_sectors(&mut original, chunk_sector + first as i64, 1)?;
+            let start = first * sector_size;
+            self.buffer[start..low].copy_from_slice(&original[..low - start]);
+        }
+
+        if high % sector_size != 0 {
+            self.io
+                .read_sectors(&mut original, chunk_sector + last as i64, 1)?;
+            let end = (last + 1) * sector_size;
+            self.buffer[high..end].copy_from_slice(&original[high % sector_size..]);
+        }
+
+        self.io.write_sectors(
+            &self.buffer[first * sector_size..(last + 1) * sector_size],
+            chunk_sector + first as i64,
+            (last - first + 1) as i64,
+        )?;
+        self.dirty = None;
+        Ok(())
+    }
+}
+
+impl<'g, T: SectorIo + 'g> Write for SectorWriter<'g, T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() || self.position >= self.len() {
+            return Ok(0);
+        }
+
+        let sector_size = self.io.sector_size();
+        let chunk_bytes = STREAM_CHUNK_SECTORS as u64 * sector_size;
+        let chunk_start = self.position / chunk_bytes * chunk_bytes;
+
+        if self.buffer.is_empty() || chunk_start != self.buffer_start {
+            self.flush_buffer()?;
+            let sector = (chunk_start / sector_size) as i64;
+            let count = STREAM_CHUNK_SECTORS.min(self.io.length() - sector);
+            self.buffer = vec![0; count as usize * sector_size as usize];
+            self.buffer_start = chunk_start;
+        }
+
+        let offset = (self.position - self.buffer_start) as usize;
+        let written = buf.len().min(self.buffer.len() - offset);
+
+        // The dirty range must stay contiguous, so that no unwritten bytes are flushed.
+        self.dirty = match self.dirty {
+            Some((low, high)) if offset <= high && offset + written >= low => {
+                Some((low.min(offset), high.max(offset + written)))
+            }
+            Some(_) => {
+                self.flush_buffer()?;
+                Some((offset, offset + written))
+            }
+            None => Some((offset, offset + written)),
+        };
+
+        self.buffer[offset..offset + written].copy_from_slice(&buf[..written]);
+        self.position += written as u64;
+        Ok(written)
+    }
+
+    /// Writes any buffered data to the device, and syncs the device.
+    fn flush(&mut self) -> io::Result<()> {
+        self.flush_buffer()?;
+        self.io.sync()
+    }
+}
+
+impl<'g, T: SectorIo + 'g> Seek for SectorWriter<'g, T> {
+    /// Seeking beyond the end of the region is permitted, after which writes are refused.
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.position = seek_position(pos, self.position, self.len())?;
+        Ok(self.position)
+    }
+}
+
+impl<'g, T: SectorIo + 'g> Drop for SectorWriter<'g, T> {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+/// Resolves `pos` against the current position and length of a stream.
+fn seek_position(pos: SeekFrom, current: u64, len: u64) -> io::Result<u64> {
+    let (base, offset) = match pos {
+        SeekFrom::Start(offset) => return Ok(offset),
+        SeekFrom::End(offset) => (len, offset),
+        SeekFrom::Current(offset) => (current, offset),
+    };
+
+    let position = if offset < 0 {
+        base.checked_sub(offset.wrapping_neg() as u64)
+    } else {
+        base.checked_add(offset as u64)
+    };
+
+    position.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "invalid seek to a negative or overflowing position",
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryDevice;
+
+    #[test]
+    fn writer_preserves_unwritten_bytes() {
+        let mut device = MemoryDevice::from_bytes(vec![0xAA; 4 * 512], 512).unwrap();
+        {
+            let mut writer = SectorWriter::new(&mut device);
+            writer.seek(SeekFrom::Start(510)).unwrap();
+            writer.write_all(b"span").unwrap();
+            writer.flush().unwrap();
+        }
+
+        let data = device.as_bytes();
+        assert_eq!(&data[508..516], b"\xAA\xAAspan\xAA\xAA");
+        assert!(data[516..].iter().all(|&byte| byte == 0xAA));
+    }
+
+    #[test]
+    fn writer_preserves_unwritten_bytes_of_4k_sectors() {
+        let mut device = MemoryDevice::from_bytes(vec![0xAA; 3 * 4096], 4096).unwrap();
+        {
+            let mut writer = SectorWriter::new(&mut device);
+            writer.seek(SeekFrom::Start(4094)).unwrap();
+            writer.write_all(&[0x42; 4100]).unwrap();
+            writer.flush().unwrap();
+        }
+
+        let data = device.as_bytes();
+        assert!(data[..4094].iter().all(|&byte| byte == 0xAA));
+        assert!(data[4094..8194].iter().all(|&byte| byte == 0x42));
+        assert!(data[8194..].iter().all(|&byte| byte == 0xAA));
+
+        let mut reader = SectorReader::new(&device);
+        reader.seek(SeekFrom::Start(8190)).unwrap();
+        let mut boundary = [0; 6];
+        reader.read_exact(&mut boundary).unwrap();
+        assert_eq!(boundary, [0x42, 0x42, 0x42, 0x42, 0xAA, 0xAA]);
+    }
+
+    #[test]
+    fn writer_flushes_when_dropped() {
+        let mut device = MemoryDevice::new(4, 512);
+        SectorWriter::new(&mut device)
+            .write_all(b"dropped")
+            .unwrap();
+        assert_eq!(&device.as_bytes()[..7], b"dropped");
+    }
+
+    #[test]
+    fn streams_across_chunks() {
+        let sectors = STREAM_CHUNK_SECTORS as u64 * 2 + 3;
+        let mut device = MemoryDevice::new(sectors, 512);
+        let data = (0..sectors * 512)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<_>>();
+
+        {
+            let mut writer = SectorWriter::new(&mut device);
+            for piece in data.chunks(1000) {
+                writer.write_all(piece).unwrap();
+            }
+            assert_eq!(writer.write(b"past the end").unwrap(), 0);
+        }
+
+        let mut read = Vec::new();
+        SectorReader::new(&device).read_to_end(&mut read).unwrap();
+        assert_eq!(read, data);
+    }
+
+    #[test]
+    fn reader_seeks_from_the_end() {
+        let mut device = MemoryDevice::new(2, 512);
+        {
+            let mut writer = SectorWriter::new(&mut device);
+            writer.seek(SeekFrom::End(-3)).unwrap();
+            writer.write_all(b"end").unwrap();
+        }
+
+        let mut reader = SectorReader::new(&device);
+        assert_eq!(reader.seek(SeekFrom::End(-3)).unwrap(), 1021);
+        let mut tail = Vec::new();
+        reader.read_to_end(&mut tail).unwrap();
+        assert_eq!(tail, b"end");
+        assert!(reader.seek(SeekFrom::Current(-2000)).is_err());
+    }
+}
@@ -0,0 +1,436 @@
+//! Well-known GPT partition type GUIDs.
+//!
+//! GUIDs are represented in RFC 4122 byte order, which is the order in which they are
+//! written as text, and the order used by `Partition::type_uuid()`. Note that GPT itself
+//! stores the first three fields of each GUID in little-endian order.
+
+use super::signatures::{le_u32, le_u64};
+use super::SectorIo;
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use std::str::FromStr;
+
+/// The type of a GPT partition, identified by its type GUID.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PartitionTypeGuid {
+    EfiSystem,
+    BiosBoot,
+    LinuxFilesystem,
+    LinuxSwap,
+    LinuxLvm,
+    LinuxRaid,
+    LinuxHome,
+    /// The root partition of an x86-64 Linux system, per the Discoverable Partitions
+    /// Specification.
+    LinuxRootX86_64,
+    /// The extended boot loader partition (`/boot`), per the Boot Loader Specification.
+    LinuxExtendedBoot,
+    MicrosoftReserved,
+    MicrosoftBasicData,
+    WindowsRecovery,
+    AppleHfsPlus,
+    AppleApfs,
+    /// A type GUID which is not in this table.
+    Unknown([u8; 16]),
+}
+
+const WELL_KNOWN: &[(PartitionTypeGuid, &str)] = &[
+    (
+        PartitionTypeGuid::EfiSystem,
+        "C12A7328-F81F-11D2-BA4B-00A0C93EC93B",
+    ),
+    (
+        PartitionTypeGuid::BiosBoot,
+        "21686148-6449-6E6F-744E-656564454649",
+    ),
+    (
+        PartitionTypeGuid::LinuxFilesystem,
+        "0FC63DAF-8483-4772-8E79-3D69D8477DE4",
+    ),
+    (
+        PartitionTypeGuid::LinuxSwap,
+        "0657FD6D-A4AB-43C4-84E5-0933C84B4F4F",
+    ),
+    (
+        PartitionTypeGuid::LinuxLvm,
+        "E6D6D379-F507-44C2-A23C-238F2A3DF928",
+    ),
+    (
+        PartitionTypeGuid::LinuxRaid,
+        "A19D880F-05FC-4D3B-A006-743F0F84911E",
+    ),
+    (
+        PartitionTypeGuid::LinuxHome,
+        "933AC7E1-2EB4-4F13-B844-0E14E2AEF915",
+    ),
+    (
+        PartitionTypeGuid::LinuxRootX86_64,
+        "4F68BCE3-E8CD-4DB1-96E7-FBCAF984B709",
+    ),
+    (
+        PartitionTypeGuid::LinuxExtendedBoot,
+        "BC13C2FF-59E6-4262-A352-B275FD6F7172",
+    ),
+    (
+        PartitionTypeGuid::MicrosoftReserved,
+        "E3C9E316-0B5C-4DB8-817D-F92DF00215AE",
+    ),
+    (
+        PartitionTypeGuid::MicrosoftBasicData,
+        "EBD0A0A2-B9E5-4433-87C0-68B6B72699C7",
+    ),
+    (
+        PartitionTypeGuid::WindowsRecovery,
+        "DE94BBA4-06D1-4D40-A16A-BFD50179D6AC",
+    ),
+    (
+        PartitionTypeGuid::AppleHfsPlus,
+        "48465300-0000-11AA-AA11-00306543ECAC",
+    ),
+    (
+        PartitionTypeGuid::AppleApfs,
+        "7C3457EF-0000-11AA-AA11-00306543ECAC",
+    ),
+];
+
+impl PartitionTypeGuid {
+    /// Returns the well-known type with the given GUID, or `Unknown` if there is none.
+    pub fn from_bytes(bytes: [u8; 16]) -> PartitionTypeGuid {
+        WELL_KNOWN
+            .iter()
+            .find(|&&(_, guid)| parse_uuid(guid).ok() == Some(bytes))
+            .map_or(PartitionTypeGuid::Unknown(bytes), |&(kind, _)| kind)
+    }
+
+    /// Returns the GUID of this type.
+    pub fn to_bytes(&self) -> [u8; 16] {
+        match *self {
+            PartitionTypeGuid::Unknown(bytes) => bytes,
+            kind => {
+                let guid = WELL_KNOWN
+                    .iter()
+                    .find(|&&(known, _)| known == kind)
+                    .map(|&(_, guid)| guid)
+                    .expect("well-known GUID missing from table");
+                parse_uuid(guid).expect("invalid GUID in table")
+            }
+        }
+    }
+}
+
+impl Display for PartitionTypeGuid {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(&format_uuid(&self.to_bytes()))
+    }
+}
+
+impl FromStr for PartitionTypeGuid {
+    type Err = io::Error;
+
+    fn from_str(guid: &str) -> io::Result<PartitionTypeGuid> {
+        parse_uuid(guid).map(PartitionTypeGuid::from_bytes)
+    }
+}
+
+/// Formats a UUID given in RFC 4122 byte order, such as `C12A7328-F81F-11D2-BA4B-00A0C93EC93B`.
+pub(crate) fn format_uuid(uuid: &[u8; 16]) -> String {
+    let mut out = String::with_capacity(36);
+    for (i, byte) in uuid.iter().enumerate() {
+        if i == 4 || i == 6 || i == 8 || i == 10 {
+            out.push('-');
+        }
+        out.push_str(&format!("{:02X}", byte));
+    }
+    out
+}
+
+/// Parses a UUID written as 32 hexadecimal digits, optionally separated by dashes.
+pub(crate) fn parse_uuid(value: &str) -> io::Result<[u8; 16]> {
+    let hex = value.replace('-', "");
+    if hex.len() != 32 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("`{}` is not a valid UUID", value),
+        ));
+    }
+
+    let mut uuid = [0u8; 16];
+    for (i, byte) in uuid.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).unwrap();
+    }
+    Ok(uuid)
+}
+
+/// The largest partition entry accepted, which is far beyond any written in practice.
+const MAX_ENTRY_SIZE: usize = 4096;
+
+/// The largest number of partition entries accepted.
+const MAX_ENTRIES: usize = 4096;
+
+/// The partition array described by a GPT header.
+struct PartitionArray {
+    start: i64,
+    count: usize,
+    entry_size: usize,
+    crc: u32,
+}
+
+/// Reads the first sector and unique GUID of each partition in the GPT on `io`.
+///
+/// libparted does not expose unique partition GUIDs, so these are read from the device, and
+/// reflect the table as it was last written. The primary header and partition array are
+/// used unless either fails its checksum, in which case the backup is used instead.
+pub(crate) fn unique_guids<T: SectorIo>(io: &T) -> io::Result<Vec<(i64, [u8; 16])>> {
+    let primary = read_header(io, 1)?;
+    let backup = primary
+        .as_ref()
+        .map_or(io.length() - 1, |&(_, alternate)| alternate);
+
+    if let Some((array, _)) = primary {
+        if let Some(entries) = read_entries(io, &array)? {
+            return Ok(parse_guids(&entries, &array));
+        }
+    }
+
+    if let Some((array, _)) = read_header(io, backup)? {
+        if let Some(entries) = read_entries(io, &array)? {
+            return Ok(parse_guids(&entries, &array));
+        }
+    }
+
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "the device does not have a valid GPT header and partition array",
+    ))
+}
+
+/// Reads the GPT header at `lba`, returning the partition array it describes and the
+/// location of the other header, or `None` if the sector does not hold a valid header.
+fn read_header<T: SectorIo>(io: &T, lba: i64) -> io::Result<Option<(PartitionArray, i64)>> {
+    if lba < 1 || lba >= io.length() {
+        return Ok(None);
+    }
+
+    let sector_size = io.sector_size() as usize;
+    let mut header = vec![0; sector_size];
+    io.read_sectors(&mut header, lba, 1)?;
+    if &header[..8] != b"EFI PART" {
+        return Ok(None);
+    }
+
+    let header_size = le_u32(&header[12..16]) as usize;
+    if header_size < 92 || header_size > sector_size {
+        return Ok(None);
+    }
+
+    let crc = le_u32(&header[16..20]);
+    header[16..20].copy_from_slice(&[0; 4]);
+    if crc32(&header[..header_size]) != crc {
+        return Ok(None);
+    }
+
+    let array = PartitionArray {
+        start: le_u64(&header[72..80]) as i64,
+        count: le_u32(&header[80..84]) as usize,
+        entry_size: le_u32(&header[84..88]) as usize,
+        crc: le_u32(&header[88..92]),
+    };
+
+    if array.entry_size < 128
+        || array.entry_size > MAX_ENTRY_SIZE
+        || array.count > MAX_ENTRIES
+        || array.start < 1
+    {
+        return Ok(None);
+    }
+
+    Ok(Some((array, le_u64(&header[32..40]) as i64)))
+}
+
+/// Reads the partition array described by `array`, or `None` if it does not fit on the
+/// device or fails its checksum.
+fn read_entries<T: SectorIo>(io: &T, array: &PartitionArray) -> io::Result<Option<Vec<u8>>> {
+    let sector_size = io.sector_size() as usize;
+    let len = match array.count.checked_mul(array.entry_size) {
+        Some(len) => len,
+        None => return Ok(None),
+    };
+
+    let sectors = (len + sector_size - 1) / sector_size;
+    match array.start.checked_add(sectors as i64) {
+        Some(end) if end <= io.length() => (),
+        _ => return Ok(None),
+    }
+
+    let mut entries = vec![0; sectors * sector_size];
+    io.read_sectors(&mut entries, array.start, sectors as i64)?;
+    entries.truncate(len);
+    Ok(if crc32(&entries) == array.crc {
+        Some(entries)
+    } else {
+        None
+    })
+}
+
+fn parse_guids(entries: &[u8], array: &PartitionArray) -> Vec<(i64, [u8; 16])> {
+    let mut guids = Vec::new();
+    for entry in entries.chunks(array.entry_size).take(array.count) {
+        if entry[..16].iter().all(|&byte| byte == 0) {
+            continue;
+        }
+
+        // The first three fields are stored little-endian.
+        let mut guid = [0u8; 16];
+        guid.copy_from_slice(&entry[16..32]);
+        guid[..4].reverse();
+        guid[4..6].reverse();
+        guid[6..8].reverse();
+        guids.push((le_u64(&entry[32..40]) as i64, guid));
+    }
+
+    guids
+}
+
+/// The CRC-32 used by GPT, as by zlib.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Reads the revision of the primary GPT header on `io`, as `(major, minor)`, or `None` if
+/// there is no GPT header.
+pub(crate) fn header_revision<T: SectorIo>(io: &T) -> io::Result<Option<(u16, u16)>> {
+    let mut header = vec![0; io.sector_size() as usize];
+    io.read_sectors(&mut header, 1, 1)?;
+    if &header[..8] != b"EFI PART" {
+        return Ok(None);
+    }
+
+    let revision = le_u32(&header[8..12]);
+    Ok(Some(((revision >> 16) as u16, revision as u16)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryDevice;
+
+    const SECTORS: u64 = 128;
+    const ENTRIES: usize = 128;
+
+    const UNIQUE: [u8; 16] = [
+        0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B, 0x0C, 0x0D, 0x0E, 0x0F,
+        0x10,
+    ];
+
+    fn put_u32(buffer: &mut [u8], offset: usize, value: u32) {
+        buffer[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    fn put_u64(buffer: &mut [u8], offset: usize, value: u64) {
+        buffer[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+    }
+
+    /// Writes a GPT header at `lba`, describing an array of `ENTRIES` entries at `entries`.
+    fn write_header(
+        device: &mut MemoryDevice,
+        lba: u64,
+        alternate: u64,
+        entries: u64,
+        entry_size: u32,
+        entries_crc: u32,
+    ) {
+        let mut header = vec![0; device.sector_size() as usize];
+        header[..8].copy_from_slice(b"EFI PART");
+        put_u32(&mut header, 8, 0x0001_0000);
+        put_u32(&mut header, 12, 92);
+        put_u64(&mut header, 24, lba);
+        put_u64(&mut header, 32, alternate);
+        put_u64(&mut header, 72, entries);
+        put_u32(&mut header, 80, ENTRIES as u32);
+        put_u32(&mut header, 84, entry_size);
+        put_u32(&mut header, 88, entries_crc);
+        let crc = crc32(&header[..92]);
+        put_u32(&mut header, 16, crc);
+        device.write_sectors(&header, lba as i64, 1).unwrap();
+    }
+
+    /// Creates a device with a primary and backup GPT holding one partition at sector 2048.
+    fn gpt_device(sector_size: u64) -> (MemoryDevice, u32) {
+        let mut device = MemoryDevice::new(SECTORS, sector_size);
+        let mut entries = vec![0; ENTRIES * 128];
+        entries[..16].copy_from_slice(&[0xAA; 16]);
+        entries[16..32].copy_from_slice(&UNIQUE);
+        put_u64(&mut entries, 32, 2048);
+        let crc = crc32(&entries);
+
+        let sectors = entries.len() as u64 / sector_size;
+        device.write_sectors(&entries, 2, sectors as i64).unwrap();
+        let backup_entries = SECTORS - 1 - sectors;
+        device
+            .write_sectors(&entries, backup_entries as i64, sectors as i64)
+            .unwrap();
+
+        write_header(&mut device, 1, SECTORS - 1, 2, 128, crc);
+        write_header(&mut device, SECTORS - 1, 1, backup_entries, 128, crc);
+        (device, crc)
+    }
+
+    fn expected() -> Vec<(i64, [u8; 16])> {
+        let mut guid = UNIQUE;
+        guid[..4].reverse();
+        guid[4..6].reverse();
+        guid[6..8].reverse();
+        vec![(2048, guid)]
+    }
+
+    #[test]
+    fn crc32_matches_zlib() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn reads_unique_guids() {
+        for &sector_size in &[512, 4096] {
+            let (device, _) = gpt_device(sector_size);
+            assert_eq!(unique_guids(&device).unwrap(), expected());
+        }
+    }
+
+    #[test]
+    fn falls_back_to_the_backup_header() {
+        let (mut device, _) = gpt_device(512);
+        device.write_sectors(b"corrupt", 1, 1).unwrap();
+        assert_eq!(unique_guids(&device).unwrap(), expected());
+    }
+
+    #[test]
+    fn falls_back_when_the_primary_array_is_corrupt() {
+        let (mut device, _) = gpt_device(512);
+        device.write_sectors(&[0xFF; 512], 2, 1).unwrap();
+        assert_eq!(unique_guids(&device).unwrap(), expected());
+
+        let last = SECTORS as i64 - 2;
+        device.write_sectors(&[0xFF; 512], last, 1).unwrap();
+        assert!(unique_guids(&device).is_err());
+    }
+
+    #[test]
+    fn rejects_oversized_entries() {
+        let (mut device, crc) = gpt_device(512);
+        write_header(&mut device, 1, 0, 2, 0x8000_0000, crc);
+        write_header(&mut device, SECTORS - 1, 1, 2, u32::MAX, crc);
+        assert!(unique_guids(&device).is_err());
+    }
+}
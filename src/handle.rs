@@ -0,0 +1,99 @@
+use super::{cvt, DeviceRef};
+use libparted_sys::{ped_device_close, ped_device_get, ped_device_open, PedDevice};
+use std::cell::RefCell;
+use std::ffi::CString;
+use std::io::{Error, ErrorKind, Result};
+use std::marker::PhantomData;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::rc::{Rc, Weak};
+
+thread_local! {
+    /// The live handle of each device, so that every handle to a device shares one open.
+    static HANDLES: RefCell<Vec<(*mut PedDevice, Weak<Inner>)>> = RefCell::new(Vec::new());
+}
+
+struct Inner {
+    device: *mut PedDevice,
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        HANDLES.with(|handles| {
+            handles
+                .borrow_mut()
+                .retain(|&(device, _)| device != self.device)
+        });
+        unsafe { ped_device_close(self.device) };
+    }
+}
+
+/// A shared handle to an open device, as returned by `Device::handle()`, `Disk::handle()`,
+/// and friends.
+///
+/// Every handle to the same device on a thread refers to a single open of that device, which
+/// is closed once the last handle is dropped. Unlike wrapping the device in several
+/// **Device**s, this keeps libparted's open count balanced however the handles are obtained.
+///
+/// A handle borrows the **Device**, **Disk**, **Geometry**, or **Partition** it was obtained
+/// from, so that it cannot outlive the device and close it after it has been closed or
+/// freed.
+#[derive(Clone)]
+pub struct DeviceHandle<'a>(Rc<Inner>, PhantomData<&'a PedDevice>);
+
+impl DeviceHandle<'static> {
+    /// Obtains a handle to the device at `path`, opening it if no handle to it exists yet.
+    ///
+    /// # Note:
+    ///
+    /// The device is owned by libparted's cache, so `Device::free_all()` must not be called
+    /// while the handle exists.
+    pub fn get<P: AsRef<Path>>(path: P) -> Result<DeviceHandle<'static>> {
+        let path = CString::new(path.as_ref().as_os_str().as_bytes())
+            .map_err(|err| Error::new(ErrorKind::InvalidData, format!("Invalid data: {}", err)))?;
+        let device = cvt(unsafe { ped_device_get(path.as_ptr()) })?;
+        unsafe { DeviceHandle::from_ped_device(device) }
+    }
+}
+
+impl<'a> DeviceHandle<'a> {
+    /// Obtains a handle to `device`, opening it if no handle to it exists yet.
+    ///
+    /// # Safety
+    ///
+    /// `device` must be a valid device obtained from libparted, which is not destroyed for
+    /// the lifetime `'a`.
+    pub(crate) unsafe fn from_ped_device(device: *mut PedDevice) -> Result<DeviceHandle<'a>> {
+        let existing = HANDLES.with(|handles| {
+            handles
+                .borrow()
+                .iter()
+                .find(|&&(handle, _)| handle == device)
+                .and_then(|(_, inner)| inner.upgrade())
+        });
+
+        if let Some(inner) = existing {
+            return Ok(DeviceHandle(inner, PhantomData));
+        }
+
+        cvt(ped_device_open(device))?;
+        let inner = Rc::new(Inner { device });
+        HANDLES.with(|handles| handles.borrow_mut().push((device, Rc::downgrade(&inner))));
+        Ok(DeviceHandle(inner, PhantomData))
+    }
+
+    /// Returns a view of the device, which remains open for as long as the view exists.
+    pub fn device(&self) -> DeviceRef {
+        unsafe { DeviceRef::from_ped_device(self.0.device) }
+    }
+
+    /// Whether both handles refer to the same device.
+    pub fn same_device(&self, other: &DeviceHandle) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+
+    #[allow(clippy::missing_safety_doc)]
+    pub unsafe fn ped_device(&self) -> *mut PedDevice {
+        self.0.device
+    }
+}
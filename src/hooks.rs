@@ -0,0 +1,104 @@
+use super::Disk;
+use std::io::{Error, ErrorKind, Result};
+use std::process::Command;
+use std::time::Duration;
+
+type Hook<'h> = Box<dyn FnMut(&Disk) -> Result<()> + 'h>;
+
+/// Actions to run at each stage of `Disk::commit_with()`.
+///
+/// Hooks run in the order they were added. If a hook fails, the commit stops and its error
+/// is returned.
+///
+/// ```rust,no_run
+/// # use libparted::{CommitHooks, Device, Disk};
+/// # use std::time::Duration;
+/// let device = Device::new("/dev/sda").unwrap();
+/// let mut disk = Disk::new(&device).unwrap();
+/// let hooks = CommitHooks::new()
+///     .udev_settle(Duration::from_secs(10))
+///     .after_os(|disk| {
+///         println!("{} partitions committed", disk.partitions().count());
+///         Ok(())
+///     });
+/// disk.commit_with(hooks).unwrap();
+/// ```
+#[derive(Default)]
+pub struct CommitHooks<'h> {
+    pub(crate) after_dev: Vec<Hook<'h>>,
+    pub(crate) after_os: Vec<Hook<'h>>,
+}
+
+impl<'h> CommitHooks<'h> {
+    pub fn new() -> CommitHooks<'h> {
+        CommitHooks::default()
+    }
+
+    /// Runs `hook` after the partition table has been written to the device, but before the
+    /// operating system is informed of it.
+    pub fn after_dev<F>(mut self, hook: F) -> CommitHooks<'h>
+    where
+        F: FnMut(&Disk) -> Result<()> + 'h,
+    {
+        self.after_dev.push(Box::new(hook));
+        self
+    }
+
+    /// Runs `hook` after the operating system has been informed of the partition table.
+    pub fn after_os<F>(mut self, hook: F) -> CommitHooks<'h>
+    where
+        F: FnMut(&Disk) -> Result<()> + 'h,
+    {
+        self.after_os.push(Box::new(hook));
+        self
+    }
+
+    /// Waits for udev to finish processing the events caused by writing to the device before
+    /// the operating system is informed, so that udev does not hold the device open.
+    pub fn udev_settle(self, timeout: Duration) -> CommitHooks<'h> {
+        self.after_dev(move |_| udev_settle(timeout))
+    }
+}
+
+/// Controls how `Disk::commit_with_options()` writes the partition table.
+///
+/// The default is the safe behavior of `Disk::commit()`, refusing to change partitions which
+/// are in use.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CommitOptions {
+    /// Write the table even if it changes or removes partitions which are mounted or
+    /// otherwise in use, such as when growing a mounted partition for an online resize.
+    ///
+    /// The kernel will not be told about changes to the partitions which are in use, which
+    /// are ignored rather than failing the commit.
+    pub force: bool,
+    /// Inform the operating system of the new table after writing it to the device.
+    pub inform_kernel: bool,
+}
+
+impl Default for CommitOptions {
+    fn default() -> Self {
+        CommitOptions {
+            force: false,
+            inform_kernel: true,
+        }
+    }
+}
+
+/// Waits up to `timeout` for udev to finish processing its queued events, with
+/// `udevadm settle`.
+pub fn udev_settle(timeout: Duration) -> Result<()> {
+    let status = Command::new("udevadm")
+        .arg("settle")
+        .arg(format!("--timeout={}", timeout.as_secs()))
+        .status()?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(Error::new(
+            ErrorKind::TimedOut,
+            format!("udevadm settle failed: {}", status),
+        ))
+    }
+}
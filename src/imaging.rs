@@ -0,0 +1,335 @@
+//! Raw imaging of regions to and from files, used by `Device::dump_to()` and friends.
+//!
+//! Imaging is written against **SectorIo**, so any region or device may be imaged.
+
+use super::copy::CHUNK_SIZE;
+use super::{format_size, SectorIo, Standard, Timer};
+use std::alloc::{self, Layout};
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::ops::{Deref, DerefMut};
+use std::os::unix::fs::{FileExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+use std::slice;
+
+/// The alignment of buffers used for direct I/O, which satisfies every common block size.
+const DIRECT_ALIGN: usize = 4096;
+
+/// A zeroed heap buffer whose address is suitably aligned for direct I/O.
+struct AlignedBuffer {
+    ptr: *mut u8,
+    layout: Layout,
+}
+
+impl AlignedBuffer {
+    fn new(size: usize) -> AlignedBuffer {
+        let layout = Layout::from_size_align(size.max(1), DIRECT_ALIGN)
+            .expect("image buffer size overflows");
+        let ptr = unsafe { alloc::alloc_zeroed(layout) };
+        if ptr.is_null() {
+            alloc::handle_alloc_error(layout);
+        }
+
+        AlignedBuffer { ptr, layout }
+    }
+}
+
+impl Deref for AlignedBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr, self.layout.size()) }
+    }
+}
+
+impl DerefMut for AlignedBuffer {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.ptr, self.layout.size()) }
+    }
+}
+
+impl Drop for AlignedBuffer {
+    fn drop(&mut self) {
+        unsafe { alloc::dealloc(self.ptr, self.layout) }
+    }
+}
+
+/// An image file which is accessed with `O_DIRECT` where the file system permits it, so that
+/// imaging does not evict everything else from the page cache.
+///
+/// Should the file system reject direct I/O, the file is reopened for buffered I/O instead.
+struct ImageFile {
+    file: File,
+    path: PathBuf,
+    write: bool,
+    direct: bool,
+}
+
+impl ImageFile {
+    fn open(path: &Path, write: bool) -> io::Result<ImageFile> {
+        let mut options = OpenOptions::new();
+        if write {
+            options.write(true).create(true).truncate(true);
+        } else {
+            options.read(true);
+        }
+
+        let (file, direct) = match options.clone().custom_flags(libc::O_DIRECT).open(path) {
+            Ok(file) => (file, true),
+            Err(ref why) if why.raw_os_error() == Some(libc::EINVAL) => {
+                (options.open(path)?, false)
+            }
+            Err(why) => return Err(why),
+        };
+
+        Ok(ImageFile {
+            file,
+            path: path.to_owned(),
+            write,
+            direct,
+        })
+    }
+
+    /// Reopens the file without `O_DIRECT`, after the file system refused a direct transfer.
+    fn fall_back(&mut self) -> io::Result<()> {
+        self.file = OpenOptions::new()
+            .read(!self.write)
+            .write(self.write)
+            .open(&self.path)?;
+        self.direct = false;
+        Ok(())
+    }
+
+    /// Whether `result` failed because of `O_DIRECT`, in which case the file is reopened so
+    /// that the transfer may be retried.
+    fn should_retry<T>(&mut self, result: &io::Result<T>) -> io::Result<bool> {
+        match *result {
+            Err(ref why) if self.direct && why.raw_os_error() == Some(libc::EINVAL) => {
+                self.fall_back().map(|_| true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Fills as much of `buffer` as possible from `offset`, returning fewer bytes only at
+    /// the end of the file.
+    fn read_at(&mut self, buffer: &mut [u8], offset: u64) -> io::Result<usize> {
+        let mut filled = 0;
+        while filled < buffer.len() {
+            let result = self
+                .file
+                .read_at(&mut buffer[filled..], offset + filled as u64);
+            if self.should_retry(&result)? {
+                continue;
+            }
+
+            match result {
+                Ok(0) => break,
+                Ok(read) => filled += read,
+                Err(ref why) if why.kind() == io::ErrorKind::Interrupted => (),
+                Err(why) => return Err(why),
+            }
+        }
+
+        Ok(filled)
+    }
+
+    fn write_all_at(&mut self, buffer: &[u8], offset: u64) -> io::Result<()> {
+        let result = self.file.write_all_at(buffer, offset);
+        if self.should_retry(&result)? {
+            return self.file.write_all_at(buffer, offset);
+        }
+
+        result
+    }
+}
+
+/// The number of sectors transferred at a time.
+fn chunk_sectors(sector_size: u64) -> i64 {
+    (CHUNK_SIZE / sector_size as i64).max(1)
+}
+
+/// Writes the entire contents of `geom` to a new image file at `path`, returning the number
+/// of bytes written.
+pub(crate) fn dump<T: SectorIo>(
+    geom: &T,
+    path: &Path,
+    mut timer: Option<&mut Timer>,
+) -> io::Result<u64> {
+    let mut image = ImageFile::open(path, true)?;
+    let sector_size = geom.sector_size();
+    let chunk = chunk_sectors(sector_size);
+    let mut buffer = AlignedBuffer::new(chunk as usize * sector_size as usize);
+
+    if let Some(timer) = timer.as_mut() {
+        timer.reset();
+    }
+
+    let length = geom.length();
+    let mut offset = 0;
+    while offset < length {
+        let count = chunk.min(length - offset);
+        let buffer = &mut buffer[..count as usize * sector_size as usize];
+        geom.read_sectors(buffer, offset, count)?;
+        image.write_all_at(buffer, offset as u64 * sector_size)?;
+
+        offset += count;
+        if let Some(timer) = timer.as_mut() {
+            timer.update(offset as f32 / length as f32);
+        }
+    }
+
+    image.file.sync_all()?;
+    Ok(length as u64 * sector_size)
+}
+
+/// Writes the image file at `path` to the start of `geom`, returning the number of bytes
+/// restored.
+///
+/// If the image does not end on a sector boundary, the remainder of its last sector is
+/// zeroed. Sectors beyond the end of the image are left as they are.
+pub(crate) fn restore<T: SectorIo>(
+    geom: &mut T,
+    path: &Path,
+    mut timer: Option<&mut Timer>,
+) -> io::Result<u64> {
+    let mut image = ImageFile::open(path, false)?;
+    let sector_size = geom.sector_size();
+    let capacity = geom.length() as u64 * sector_size;
+    let total = image.file.metadata()?.len();
+    if total > capacity {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "image of {} does not fit in a region of {}",
+                format_size(total, Standard::IEC),
+                format_size(capacity, Standard::IEC)
+            ),
+        ));
+    }
+
+    let chunk = chunk_sectors(sector_size);
+    let mut buffer = AlignedBuffer::new(chunk as usize * sector_size as usize);
+
+    if let Some(timer) = timer.as_mut() {
+        timer.reset();
+    }
+
+    let mut restored = 0;
+    while restored < capacity {
+        let read = image.read_at(&mut buffer, restored)?;
+        if read == 0 {
+            break;
+        }
+
+        let count = (read as u64 + sector_size - 1) / sector_size;
+        let buffer = &mut buffer[..(count * sector_size) as usize];
+        for byte in &mut buffer[read..] {
+            *byte = 0;
+        }
+        geom.write_sectors(buffer, (restored / sector_size) as i64, count as i64)?;
+
+        restored += read as u64;
+        if let Some(timer) = timer.as_mut() {
+            timer.update(restored as f32 / total.max(1) as f32);
+        }
+    }
+
+    geom.sync()?;
+    Ok(restored)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryDevice;
+    use std::{env, fs, process};
+
+    fn image_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("libparted-{}-{}.img", name, process::id()))
+    }
+
+    fn patterned(sectors: u64, sector_size: u64) -> MemoryDevice {
+        let data = (0..sectors * sector_size)
+            .map(|i| (i % 253) as u8)
+            .collect::<Vec<_>>();
+        MemoryDevice::from_bytes(data, sector_size).unwrap()
+    }
+
+    #[test]
+    fn dump_and_restore_round_trip() {
+        let path = image_path("round-trip");
+        let source = patterned(chunk_sectors(512) as u64 + 5, 512);
+        let dumped = dump(&source, &path, None).unwrap();
+        assert_eq!(dumped, source.as_bytes().len() as u64);
+
+        let mut target = MemoryDevice::new(source.length() as u64, 512);
+        let restored = restore(&mut target, &path, None);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(restored.unwrap(), dumped);
+        assert_eq!(target, source);
+    }
+
+    #[test]
+    fn restore_zeroes_the_last_partial_sector() {
+        let path = image_path("partial");
+        fs::write(&path, vec![0x55; 700]).unwrap();
+
+        let mut target = MemoryDevice::from_bytes(vec![0xFF; 4 * 512], 512).unwrap();
+        let restored = restore(&mut target, &path, None);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(restored.unwrap(), 700);
+        let data = target.as_bytes();
+        assert!(data[..700].iter().all(|&byte| byte == 0x55));
+        assert!(data[700..1024].iter().all(|&byte| byte == 0));
+        assert!(data[1024..].iter().all(|&byte| byte == 0xFF));
+    }
+
+    #[test]
+    fn images_4k_native_sectors_with_direct_io() {
+        let path = image_path("4kn");
+        let source = patterned(chunk_sectors(4096) as u64 * 2 + 1, 4096).with_direct_io();
+        let dumped = dump(&source, &path, None).unwrap();
+        assert_eq!(dumped, source.as_bytes().len() as u64);
+
+        let mut target = MemoryDevice::new(source.length() as u64, 4096).with_direct_io();
+        let restored = restore(&mut target, &path, None);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(restored.unwrap(), dumped);
+        assert_eq!(target, source);
+    }
+
+    #[test]
+    fn restore_zeroes_the_last_partial_4k_sector() {
+        let path = image_path("partial-4kn");
+        fs::write(&path, vec![0x55; 5000]).unwrap();
+
+        let mut target = MemoryDevice::from_bytes(vec![0xFF; 3 * 4096], 4096)
+            .unwrap()
+            .with_direct_io();
+        let restored = restore(&mut target, &path, None);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(restored.unwrap(), 5000);
+        let data = target.as_bytes();
+        assert!(data[..5000].iter().all(|&byte| byte == 0x55));
+        assert!(data[5000..8192].iter().all(|&byte| byte == 0));
+        assert!(data[8192..].iter().all(|&byte| byte == 0xFF));
+    }
+
+    #[test]
+    fn restore_refuses_images_larger_than_the_region() {
+        let path = image_path("oversized");
+        fs::write(&path, vec![0; 3 * 512]).unwrap();
+
+        let mut target = MemoryDevice::new(2, 512);
+        let restored = restore(&mut target, &path, None);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(restored.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert!(target.as_bytes().iter().all(|&byte| byte == 0));
+    }
+}
@@ -0,0 +1,103 @@
+use super::signatures::{self, Signature};
+use super::{GeomView, Partition, PartitionFlag, PartitionType};
+use std::path::PathBuf;
+
+/// What was found within a partition, as reported by `PartitionInfo::content`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PartitionContent {
+    /// A file system, named as libparted names it (ie: `ext4`, `linux-swap(v1)`).
+    FileSystem(String),
+    /// A LUKS encrypted volume.
+    Luks,
+    /// An LVM2 physical volume.
+    LvmPv,
+    /// A member of a Linux software RAID array.
+    RaidMember,
+    /// A signature which belongs to none of the other kinds, such as a ZFS pool member.
+    Other(Signature),
+    /// Nothing was detected, so the partition is free to be reused.
+    Free,
+    /// The partition could not be read, so what it holds is unknown. It must not be assumed
+    /// to be free.
+    Unknown,
+}
+
+impl PartitionContent {
+    /// Identifies the content of `part`, preferring the signatures of containers over any
+    /// file system libparted found, as a RAID member or PV may also appear to hold one.
+    fn detect(part: &mut Partition) -> PartitionContent {
+        if part.kind() == PartitionType::PED_PARTITION_EXTENDED {
+            return PartitionContent::Free;
+        }
+
+        let found = match signatures::scan(&part.get_geom()) {
+            Ok(found) => found,
+            Err(_) => return PartitionContent::Unknown,
+        };
+        let signature = [Signature::Luks, Signature::LvmPv, Signature::MdRaid]
+            .iter()
+            .cloned()
+            .find(|signature| found.contains(signature))
+            .or_else(|| found.first().cloned());
+
+        match signature {
+            Some(Signature::Luks) => PartitionContent::Luks,
+            Some(Signature::LvmPv) => PartitionContent::LvmPv,
+            Some(Signature::MdRaid) => PartitionContent::RaidMember,
+            Some(Signature::Bcachefs) => PartitionContent::FileSystem("bcachefs".into()),
+            Some(signature) => PartitionContent::Other(signature),
+            None => match part.fs_type_name() {
+                Some(name) => PartitionContent::FileSystem(name.to_owned()),
+                None => PartitionContent::Free,
+            },
+        }
+    }
+}
+
+/// A light-weight, owned record of a partition, as yielded by `Disk::iter_layout()`.
+///
+/// It holds only plain data, so it may be kept after the disk it was read from is dropped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LayoutEntry {
+    pub num: u32,
+    /// The file system type libparted detected, as it names it (ie: `ext4`).
+    pub fs_type: Option<String>,
+    pub geom: GeomView,
+    pub flags: Vec<PartitionFlag>,
+}
+
+/// An owned copy of a partition's metadata, as yielded by `Disk::partitions_info()`.
+///
+/// Unlike **Partition**, this does not refer to any libparted memory, so it may be kept after
+/// the disk has been modified or dropped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PartitionInfo {
+    pub num: u32,
+    /// Either `PED_PARTITION_NORMAL`, `PED_PARTITION_LOGICAL`, or `PED_PARTITION_EXTENDED`.
+    pub part_type: PartitionType,
+    pub geom: GeomView,
+    pub fs_type: Option<String>,
+    /// The name of the partition, if the label supports names.
+    pub name: Option<String>,
+    pub flags: Vec<PartitionFlag>,
+    /// The path which the operating system uses for the partition.
+    pub path: Option<PathBuf>,
+    /// What was found within the partition. Extended partitions are always `Free`, as their
+    /// logical partitions are listed separately.
+    pub content: PartitionContent,
+}
+
+impl PartitionInfo {
+    pub(crate) fn new(part: &mut Partition, has_names: bool) -> PartitionInfo {
+        PartitionInfo {
+            num: part.num() as u32,
+            part_type: part.kind(),
+            geom: part.geom(),
+            fs_type: part.fs_type_name().map(|name| name.to_owned()),
+            name: if has_names { part.name() } else { None },
+            flags: part.flags(),
+            path: part.get_path().map(|path| path.to_owned()),
+            content: PartitionContent::detect(part),
+        }
+    }
+}
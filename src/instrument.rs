@@ -0,0 +1,72 @@
+//! Optional instrumentation of the I/O and commit layers.
+//!
+//! When the `metrics` feature is enabled, operations performed through this crate are reported
+//! to whichever recorder has been installed with the `metrics` facade crate. Without the
+//! feature, every function in this module compiles down to nothing.
+//!
+//! The feature requires Rust 1.56.1 or later, which is newer than the toolchain the rest of
+//! the crate supports, as the `metrics` crate requires it.
+//!
+//! The following metrics are emitted, each labeled with the name of the operation (`op`):
+//!
+//! - `libparted_operations_total`: counter of attempted operations.
+//! - `libparted_failures_total`: counter of operations which returned an error.
+//! - `libparted_operation_seconds`: histogram of the time taken by each operation.
+//! - `libparted_bytes_written_total`: counter of bytes written to devices.
+//! - `libparted_bytes_read_total`: counter of bytes read from devices.
+
+use std::io;
+
+/// Counter of attempted operations, labeled by `op`.
+pub const METRIC_OPERATIONS: &str = "libparted_operations_total";
+
+/// Counter of failed operations, labeled by `op`.
+pub const METRIC_FAILURES: &str = "libparted_failures_total";
+
+/// Histogram of operation durations in seconds, labeled by `op`.
+pub const METRIC_DURATION: &str = "libparted_operation_seconds";
+
+/// Counter of bytes written to devices, labeled by `op`.
+pub const METRIC_BYTES_WRITTEN: &str = "libparted_bytes_written_total";
+
+/// Counter of bytes read from devices, labeled by `op`.
+pub const METRIC_BYTES_READ: &str = "libparted_bytes_read_total";
+
+/// Runs `func`, recording the attempt, its duration, and whether it failed under `op`.
+#[cfg(feature = "metrics")]
+pub(crate) fn record<T, F: FnOnce() -> io::Result<T>>(op: &'static str, func: F) -> io::Result<T> {
+    let start = std::time::Instant::now();
+    let result = func();
+    counter!(METRIC_OPERATIONS, 1, "op" => op);
+    histogram!(METRIC_DURATION, start.elapsed(), "op" => op);
+    if result.is_err() {
+        counter!(METRIC_FAILURES, 1, "op" => op);
+    }
+    result
+}
+
+#[cfg(not(feature = "metrics"))]
+#[inline(always)]
+pub(crate) fn record<T, F: FnOnce() -> io::Result<T>>(_op: &'static str, func: F) -> io::Result<T> {
+    func()
+}
+
+/// Records that `bytes` were written to a device by `op`.
+#[cfg(feature = "metrics")]
+pub(crate) fn bytes_written(op: &'static str, bytes: u64) {
+    counter!(METRIC_BYTES_WRITTEN, bytes, "op" => op);
+}
+
+#[cfg(not(feature = "metrics"))]
+#[inline(always)]
+pub(crate) fn bytes_written(_op: &'static str, _bytes: u64) {}
+
+/// Records that `bytes` were read from a device by `op`.
+#[cfg(feature = "metrics")]
+pub(crate) fn bytes_read(op: &'static str, bytes: u64) {
+    counter!(METRIC_BYTES_READ, bytes, "op" => op);
+}
+
+#[cfg(not(feature = "metrics"))]
+#[inline(always)]
+pub(crate) fn bytes_read(_op: &'static str, _bytes: u64) {}
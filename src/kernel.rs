@@ -0,0 +1,178 @@
+//! Inspects and updates the kernel's view of a device's partitions directly, through sysfs
+//! and the `BLKPG` ioctl, for when libparted is unable to.
+
+use libc::ioctl;
+use std::fs::{self, File};
+use std::io;
+use std::os::raw::{c_int, c_ulong, c_void};
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+// From <linux/blkpg.h> and <linux/fs.h>.
+const BLKFLSBUF: c_ulong = 0x1261;
+const BLKPG: c_ulong = 0x1269;
+#[cfg(feature = "discard")]
+const BLKDISCARD: c_ulong = 0x1277;
+const BLKPG_ADD_PARTITION: c_int = 1;
+const BLKPG_DEL_PARTITION: c_int = 2;
+const BLKPG_RESIZE_PARTITION: c_int = 3;
+
+#[repr(C)]
+struct BlkpgIoctlArg {
+    op: c_int,
+    flags: c_int,
+    datalen: c_int,
+    data: *mut c_void,
+}
+
+#[repr(C)]
+struct BlkpgPartition {
+    start: i64,
+    length: i64,
+    pno: c_int,
+    devname: [u8; 64],
+    volname: [u8; 64],
+}
+
+/// A partition as the kernel currently sees it, in sectors of the device.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct KernelPartition {
+    pub num: u32,
+    pub start: i64,
+    pub length: i64,
+}
+
+/// The outcome of `Disk::commit_to_os_with_fallback()`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KernelSyncReport {
+    /// Each partition the kernel knows of after the update.
+    pub kernel: Vec<KernelPartition>,
+    /// The number of each partition where the kernel still disagrees with the partition
+    /// table, either because it is missing, has the wrong boundaries, or should not exist.
+    pub mismatched: Vec<u32>,
+}
+
+/// Reads the partitions of the block device at `device` from sysfs.
+pub(crate) fn partitions(device: &Path, sector_size: u64) -> io::Result<Vec<KernelPartition>> {
+    let device = fs::canonicalize(device)?;
+    let name = device.file_name().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "device path has no file name")
+    })?;
+
+    let read = |path: &Path| -> io::Result<i64> {
+        fs::read_to_string(path)?
+            .trim()
+            .parse()
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid sysfs value"))
+    };
+
+    // sysfs reports partition boundaries in 512-byte units, regardless of the sector size.
+    let scale = sector_size as i64 / 512;
+    let mut partitions = Vec::new();
+    for entry in fs::read_dir(Path::new("/sys/class/block").join(name))? {
+        let entry = entry?.path();
+        if !entry.join("partition").exists() {
+            continue;
+        }
+
+        partitions.push(KernelPartition {
+            num: read(&entry.join("partition"))? as u32,
+            start: read(&entry.join("start"))? / scale,
+            length: read(&entry.join("size"))? / scale,
+        });
+    }
+
+    partitions.sort_by_key(|part| part.num);
+    Ok(partitions)
+}
+
+/// Returns the number of each partition in `wanted`, given as its number, start, and length
+/// in bytes, which differs from or is missing in `kernel`, along with any partition the kernel
+/// has which should not exist.
+pub(crate) fn mismatched(
+    wanted: &[(u32, u64, u64)],
+    kernel: &[KernelPartition],
+    sector_size: u64,
+) -> Vec<u32> {
+    let bytes = |sectors: i64| sectors as u64 * sector_size;
+    let mut mismatched = wanted
+        .iter()
+        .filter(|&&(num, start, length)| {
+            !kernel.iter().any(|part| {
+                part.num == num && bytes(part.start) == start && bytes(part.length) == length
+            })
+        })
+        .map(|&(num, _, _)| num)
+        .collect::<Vec<_>>();
+    mismatched.extend(
+        kernel
+            .iter()
+            .filter(|part| wanted.iter().all(|&(num, _, _)| num != part.num))
+            .map(|part| part.num),
+    );
+    mismatched.sort();
+    mismatched
+}
+
+fn blkpg(file: &File, op: c_int, num: u32, start: i64, length: i64) -> io::Result<()> {
+    let mut part = BlkpgPartition {
+        start,
+        length,
+        pno: num as c_int,
+        devname: [0; 64],
+        volname: [0; 64],
+    };
+
+    let mut arg = BlkpgIoctlArg {
+        op,
+        flags: 0,
+        datalen: std::mem::size_of::<BlkpgPartition>() as c_int,
+        data: &mut part as *mut BlkpgPartition as *mut c_void,
+    };
+
+    match unsafe { ioctl(file.as_raw_fd(), BLKPG as _, &mut arg) } {
+        -1 => Err(io::Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
+
+/// Adds a partition to the kernel's view, with boundaries given in bytes.
+pub(crate) fn add(file: &File, num: u32, start: u64, length: u64) -> io::Result<()> {
+    blkpg(file, BLKPG_ADD_PARTITION, num, start as i64, length as i64)
+}
+
+/// Removes a partition from the kernel's view.
+pub(crate) fn remove(file: &File, num: u32) -> io::Result<()> {
+    blkpg(file, BLKPG_DEL_PARTITION, num, 0, 0)
+}
+
+/// Changes the length of a partition in the kernel's view, which is permitted even while the
+/// partition is in use. The start, in bytes, must not change.
+pub(crate) fn resize(file: &File, num: u32, start: u64, length: u64) -> io::Result<()> {
+    blkpg(
+        file,
+        BLKPG_RESIZE_PARTITION,
+        num,
+        start as i64,
+        length as i64,
+    )
+}
+
+/// Writes back and invalidates the kernel's buffer cache for the device.
+pub(crate) fn flush_buffers(file: &File) -> io::Result<()> {
+    match unsafe { ioctl(file.as_raw_fd(), BLKFLSBUF as _, 0) } {
+        -1 => Err(io::Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
+
+/// Tells the device that the bytes from `start` onwards no longer hold data, so that an SSD
+/// or thinly-provisioned device may reclaim them. `file` must be open for writing.
+#[cfg(feature = "discard")]
+pub(crate) fn discard(file: &File, start: u64, length: u64) -> io::Result<()> {
+    let range: [u64; 2] = [start, length];
+    match unsafe { ioctl(file.as_raw_fd(), BLKDISCARD as _, &range) } {
+        -1 => Err(io::Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,261 @@
+use super::gpt::PartitionTypeGuid;
+use super::{format_size, PartitionFlag, PartitionSpec, Standard};
+use std::fmt::{self, Display, Formatter};
+use std::path::Path;
+
+/// A problem with a proposed layout, as reported by `Disk::validate_layout()`.
+///
+/// The `index` of each issue is the position of the offending spec within the layout.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LayoutIssue {
+    /// The sectors given for the partition are not within the device.
+    OutsideDevice { index: usize },
+    /// No free region is large enough to hold the partition.
+    NoFreeSpace { index: usize },
+    /// The partition overlaps the existing partition `num`.
+    OverlapsPartition { index: usize, num: u32 },
+    /// The partition overlaps the partition proposed at `other`.
+    OverlapsSpec { index: usize, other: usize },
+    /// The partition overlaps sectors which the label reserves for its own metadata.
+    OverlapsMetadata { index: usize },
+    /// The partition does not start on an aligned sector, and would be moved when created.
+    Misaligned { index: usize },
+    /// The device is zoned and host-managed, and the partition does not begin and end on the
+    /// boundaries of its zones, which are `zone` sectors long.
+    NotZoneAligned { index: usize, zone: i64 },
+    /// The partition starts beyond the last sector the label can represent.
+    StartTooLarge { index: usize, max: i64 },
+    /// The partition is longer than the label can represent.
+    TooLong { index: usize, max: i64 },
+    /// The partition is smaller than the minimum size of its file system, in bytes.
+    TooSmall {
+        index: usize,
+        fs_type: String,
+        min: u64,
+    },
+    /// libparted does not know the file system type of the partition.
+    UnknownFileSystem { index: usize, fs_type: String },
+    /// The partition has a name, but the label does not support partition names.
+    NamesUnsupported { index: usize },
+    /// The partition is an extended partition, but the label does not support them.
+    ExtendedUnsupported { index: usize },
+    /// The partition is a second extended partition.
+    MultipleExtended { index: usize },
+    /// The partition is a logical partition which is not within an extended partition.
+    LogicalOutsideExtended { index: usize },
+    /// The layout would leave the disk with more partitions than the label supports.
+    TooManyPartitions { requested: u32, max: u32 },
+    /// The layout would leave the disk with more primary partitions than the label supports.
+    TooManyPrimary { requested: u32, max: u32 },
+    /// The system boots with UEFI, but the disk would not have an EFI system partition.
+    MissingEsp,
+}
+
+impl LayoutIssue {
+    /// The position of the spec which the issue concerns, if it concerns a single spec.
+    pub fn index(&self) -> Option<usize> {
+        match *self {
+            LayoutIssue::OutsideDevice { index }
+            | LayoutIssue::NoFreeSpace { index }
+            | LayoutIssue::OverlapsPartition { index, .. }
+            | LayoutIssue::OverlapsSpec { index, .. }
+            | LayoutIssue::OverlapsMetadata { index }
+            | LayoutIssue::Misaligned { index }
+            | LayoutIssue::NotZoneAligned { index, .. }
+            | LayoutIssue::StartTooLarge { index, .. }
+            | LayoutIssue::TooLong { index, .. }
+            | LayoutIssue::TooSmall { index, .. }
+            | LayoutIssue::UnknownFileSystem { index, .. }
+            | LayoutIssue::NamesUnsupported { index }
+            | LayoutIssue::ExtendedUnsupported { index }
+            | LayoutIssue::MultipleExtended { index }
+            | LayoutIssue::LogicalOutsideExtended { index } => Some(index),
+            LayoutIssue::TooManyPartitions { .. }
+            | LayoutIssue::TooManyPrimary { .. }
+            | LayoutIssue::MissingEsp => None,
+        }
+    }
+
+    /// Renumbers the specs which the issue concerns with `map`, such as to refer to the
+    /// layout as it was given after it was rewritten by `Disk::chain_logical()`.
+    pub(crate) fn map_index<F: Fn(usize) -> usize>(mut self, map: F) -> LayoutIssue {
+        match self {
+            LayoutIssue::OverlapsSpec {
+                ref mut index,
+                ref mut other,
+            } => {
+                *index = map(*index);
+                *other = map(*other);
+            }
+            LayoutIssue::OutsideDevice { ref mut index }
+            | LayoutIssue::NoFreeSpace { ref mut index }
+            | LayoutIssue::OverlapsPartition { ref mut index, .. }
+            | LayoutIssue::OverlapsMetadata { ref mut index }
+            | LayoutIssue::Misaligned { ref mut index }
+            | LayoutIssue::NotZoneAligned { ref mut index, .. }
+            | LayoutIssue::StartTooLarge { ref mut index, .. }
+            | LayoutIssue::TooLong { ref mut index, .. }
+            | LayoutIssue::TooSmall { ref mut index, .. }
+            | LayoutIssue::UnknownFileSystem { ref mut index, .. }
+            | LayoutIssue::NamesUnsupported { ref mut index }
+            | LayoutIssue::ExtendedUnsupported { ref mut index }
+            | LayoutIssue::MultipleExtended { ref mut index }
+            | LayoutIssue::LogicalOutsideExtended { ref mut index } => *index = map(*index),
+            LayoutIssue::TooManyPartitions { .. }
+            | LayoutIssue::TooManyPrimary { .. }
+            | LayoutIssue::MissingEsp => (),
+        }
+
+        self
+    }
+}
+
+impl Display for LayoutIssue {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            LayoutIssue::OutsideDevice { index } => {
+                write!(f, "partition {} is not within the device", index)
+            }
+            LayoutIssue::NoFreeSpace { index } => {
+                write!(f, "no free region is large enough for partition {}", index)
+            }
+            LayoutIssue::OverlapsPartition { index, num } => {
+                write!(f, "partition {} overlaps existing partition {}", index, num)
+            }
+            LayoutIssue::OverlapsSpec { index, other } => {
+                write!(f, "partition {} overlaps partition {}", index, other)
+            }
+            LayoutIssue::OverlapsMetadata { index } => {
+                write!(f, "partition {} overlaps the partition table", index)
+            }
+            LayoutIssue::Misaligned { index } => {
+                write!(f, "partition {} does not start on an aligned sector", index)
+            }
+            LayoutIssue::NotZoneAligned { index, zone } => write!(
+                f,
+                "partition {} is not aligned to the {}-sector zones of the device",
+                index, zone
+            ),
+            LayoutIssue::StartTooLarge { index, max } => write!(
+                f,
+                "partition {} starts beyond sector {}, the last the label supports",
+                index, max
+            ),
+            LayoutIssue::TooLong { index, max } => write!(
+                f,
+                "partition {} is longer than the label supports ({} sectors)",
+                index, max
+            ),
+            LayoutIssue::TooSmall {
+                index,
+                ref fs_type,
+                min,
+            } => write!(
+                f,
+                "partition {} is smaller than the minimum size of {} for {}",
+                index,
+                format_size(min, Standard::IEC),
+                fs_type
+            ),
+            LayoutIssue::UnknownFileSystem { index, ref fs_type } => write!(
+                f,
+                "partition {} has an unknown file system type: {}",
+                index, fs_type
+            ),
+            LayoutIssue::NamesUnsupported { index } => write!(
+                f,
+                "partition {} has a name, but the label does not support names",
+                index
+            ),
+            LayoutIssue::ExtendedUnsupported { index } => write!(
+                f,
+                "partition {} is extended, but the label does not support extended partitions",
+                index
+            ),
+            LayoutIssue::MultipleExtended { index } => {
+                write!(f, "partition {} is a second extended partition", index)
+            }
+            LayoutIssue::LogicalOutsideExtended { index } => write!(
+                f,
+                "partition {} is logical, but is not within an extended partition",
+                index
+            ),
+            LayoutIssue::TooManyPartitions { requested, max } => write!(
+                f,
+                "{} partitions were requested, but the label supports {}",
+                requested, max
+            ),
+            LayoutIssue::TooManyPrimary { requested, max } => write!(
+                f,
+                "{} primary partitions were requested, but the label supports {}",
+                requested, max
+            ),
+            LayoutIssue::MissingEsp => write!(f, "an EFI system partition is required"),
+        }
+    }
+}
+
+/// The smallest size in bytes of a file system of `fs_type`, as enforced by its mkfs tool.
+pub(crate) fn minimum_size(fs_type: &str) -> Option<u64> {
+    const KIB: u64 = 1024;
+    const MIB: u64 = 1024 * 1024;
+    match fs_type {
+        // FAT32 requires at least 65525 clusters, of at least one 512-byte sector each.
+        "fat32" => Some(65525 * 512),
+        "btrfs" => Some(109 * MIB),
+        "xfs" => Some(300 * MIB),
+        "ntfs" => Some(MIB),
+        // mkswap requires ten pages.
+        "linux-swap(v0)" | "linux-swap(v1)" => Some(40 * KIB),
+        _ => None,
+    }
+}
+
+/// Whether `spec` describes an EFI system partition.
+pub(crate) fn is_esp(spec: &PartitionSpec) -> bool {
+    spec.flags.contains(&PartitionFlag::PED_PARTITION_ESP)
+        || spec.type_guid == Some(PartitionTypeGuid::EfiSystem)
+}
+
+/// Whether the running system was booted with UEFI.
+pub(crate) fn booted_with_uefi() -> bool {
+    Path::new("/sys/firmware/efi").exists()
+}
+
+/// Removes the sectors from `start` to `end` from each of `regions`.
+pub(crate) fn subtract(regions: &mut Vec<(i64, i64)>, start: i64, end: i64) {
+    let mut remaining = Vec::with_capacity(regions.len() + 1);
+    for &(first, last) in regions.iter() {
+        if last < start || first > end {
+            remaining.push((first, last));
+            continue;
+        }
+
+        if first < start {
+            remaining.push((first, start - 1));
+        }
+        if last > end {
+            remaining.push((end + 1, last));
+        }
+    }
+
+    *regions = remaining;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::subtract;
+
+    #[test]
+    fn subtract_splits_and_removes_regions() {
+        let mut regions = vec![(0, 99), (200, 299)];
+        subtract(&mut regions, 10, 19);
+        assert_eq!(regions, vec![(0, 9), (20, 99), (200, 299)]);
+
+        subtract(&mut regions, 90, 250);
+        assert_eq!(regions, vec![(0, 9), (20, 89), (251, 299)]);
+
+        subtract(&mut regions, 0, 299);
+        assert!(regions.is_empty());
+    }
+}
@@ -1,31 +1,116 @@
 extern crate libc;
 extern crate libparted_sys;
+#[cfg(feature = "metrics")]
+#[macro_use]
+extern crate metrics;
 
 use std::io;
 
-pub use self::alignment::Alignment;
+pub use self::advisor::{label_advisor, LabelAdvice, LabelIssue};
+pub use self::alignment::{AlignPolicy, Alignment};
+pub use self::backend::{BufferSizeError, MemoryDevice, SectorIo};
+pub use self::batch::DiskOp;
+pub use self::builder::DiskBuilder;
+#[cfg(feature = "proc")]
+pub use self::busy::{BusyPartition, BusyReason};
+pub use self::check::{DiskCheckIssue, DiskCheckIssueKind};
 pub use self::constraint::Constraint;
-pub use self::device::{CHSGeometry, Device, DeviceExternalAccess, DeviceIter, DeviceType};
-pub use self::disk::{Disk, DiskFlag, DiskPartIter, DiskType, DiskTypeFeature, PartitionTableType};
+pub use self::copy::{copy_partition, copy_partition_throttled};
+pub use self::device::{
+    CHSGeometry, CheckOutcome, Device, DeviceExternalAccess, DeviceFilter, DeviceIter, DeviceRef,
+    DeviceType, PreflightReport,
+};
+pub use self::disk::{
+    ConvertOptions, Disk, DiskFlag, DiskFlagExt, DiskOps, DiskPartIter, DiskPartRefIter, DiskState,
+    DiskType, DiskTypeFeature, PartitionTableType, RemovedPartition, SnapOptions,
+};
+pub use self::dump::{PartitionDump, PartitionTableDump};
+pub use self::exception::{Exception, ExceptionOption, ExceptionType};
 pub use self::file_system::{
     FileSystem, FileSystemAlias, FileSystemAliasIter, FileSystemType, FileSystemTypeIter,
 };
-pub use self::geometry::Geometry;
-pub use self::misc::{round_down_to, round_to_nearest, round_up_to};
-pub use self::partition::{Partition, PartitionFlag, PartitionType};
+pub use self::geometry::{
+    GeomView, Geometry, GeometryReader, GeometryWriter, SectorReader, SectorWriter, WipePattern,
+};
+pub use self::handle::DeviceHandle;
+pub use self::hooks::{udev_settle, CommitHooks, CommitOptions};
+pub use self::info::{LayoutEntry, PartitionContent, PartitionInfo};
+pub use self::instrument::{
+    METRIC_BYTES_READ, METRIC_BYTES_WRITTEN, METRIC_DURATION, METRIC_FAILURES, METRIC_OPERATIONS,
+};
+pub use self::kernel::{KernelPartition, KernelSyncReport};
+pub use self::layout::LayoutIssue;
+pub use self::misc::{
+    checked_round_down_to, checked_round_to_nearest, checked_round_up_to, format_size, parse_size,
+    round_down_to, round_to_nearest, round_up_to, Roundable, Standard,
+};
+#[cfg(feature = "proc")]
+pub use self::mounts::MountPoint;
+pub use self::partition::{
+    predicted_path, Partition, PartitionChildren, PartitionFlag, PartitionFlagExt, PartitionRef,
+    PartitionType,
+};
+pub use self::resizer::FsResizer;
+pub use self::retry::{RetryError, RetryPolicy};
+pub use self::spec::{PartitionExtent, PartitionSpec, Position};
+#[cfg(feature = "sysfs")]
+pub use self::sysinfo::{SysfsInfo, Transport, ZoneModel, ZonedInfo};
 pub use self::timer::Timer;
+pub use self::topology::Topology;
+pub use self::transaction::DiskTransaction;
+pub use self::units::{Bytes, RoundMode, Sector, SectorRange, GIB, KIB, MIB, TIB};
+pub use self::version::{version, version_at_least};
 
 pub(crate) use self::constraint::ConstraintSource;
 
+mod advisor;
 mod alignment;
+mod backend;
+mod batch;
+mod builder;
+mod busy;
+mod check;
+pub mod compat;
 mod constraint;
+mod copy;
 mod device;
 mod disk;
+mod dump;
+pub mod events;
+mod exception;
 mod file_system;
+#[cfg(feature = "probe-extra")]
+mod fsid;
 mod geometry;
+pub mod gpt;
+mod handle;
+mod hooks;
+mod imaging;
+mod info;
+mod instrument;
+mod kernel;
+mod layout;
+#[cfg(feature = "loopback")]
+pub mod loopback;
+pub mod mbr;
 mod misc;
+#[cfg(feature = "proc")]
+mod mounts;
 mod partition;
+mod probe;
+pub mod recover;
+mod resizer;
+mod retry;
+pub mod signatures;
+mod snapping;
+mod spec;
+#[cfg(feature = "sysfs")]
+mod sysinfo;
 mod timer;
+mod topology;
+mod transaction;
+mod units;
+mod version;
 
 // pub(crate) const MOVE_NO: u8 = 0;
 pub(crate) const MOVE_STILL: u8 = 1;
@@ -36,12 +121,7 @@ pub(crate) const SECT_START: i32 = 0;
 pub(crate) const SECT_END: i32 = -1;
 
 pub fn snap(sector: &mut i64, new_sector: i64, range: &Geometry) -> bool {
-    debug_assert!(range.test_sector_inside(*sector));
-    if !range.test_sector_inside(new_sector) {
-        return false;
-    }
-    *sector = new_sector;
-    true
+    snapping::snap_view(sector, new_sector, range.view())
 }
 
 pub fn prefer_snap(
@@ -52,58 +132,7 @@ pub fn prefer_snap(
     part: &Partition,
     dist: &mut i64,
 ) -> u8 {
-    let (mut up_dist, mut down_dist) = (-1i64, -1i64);
-    let mut moves;
-
-    debug_assert!(what == SECT_START || what == SECT_END);
-
-    if *allow & (MOVE_UP | MOVE_DOWN) == 0 {
-        *dist = 0;
-        return MOVE_STILL;
-    }
-
-    if *allow & MOVE_UP != 0 {
-        let new_sect = part.geom_end() + 1 + what as i64;
-        if range.test_sector_inside(new_sect) {
-            up_dist = new_sect - s;
-        } else {
-            *allow &= !MOVE_UP;
-        }
-    }
-
-    if *allow & MOVE_DOWN != 0 {
-        let new_sect = part.geom_start() + what as i64;
-        if range.test_sector_inside(new_sect) {
-            down_dist = s - new_sect;
-        } else {
-            *allow &= !MOVE_DOWN;
-        }
-    }
-
-    moves = MOVE_STILL;
-    if *allow & MOVE_UP != 0 && *allow & MOVE_DOWN != 0 {
-        if down_dist < up_dist || (down_dist == up_dist && what == SECT_START) {
-            moves = MOVE_DOWN;
-        } else if up_dist < down_dist || (down_dist == up_dist && what == SECT_END) {
-            moves = MOVE_UP;
-        } else {
-            unreachable!();
-        }
-    } else if *allow & MOVE_UP != 0 {
-        moves = MOVE_UP;
-    } else if *allow & MOVE_DOWN != 0 {
-        moves = MOVE_DOWN;
-    }
-
-    *dist = if moves == MOVE_DOWN {
-        down_dist
-    } else if moves == MOVE_UP {
-        up_dist
-    } else {
-        0
-    };
-
-    moves
+    snapping::prefer_snap_view(s, what, range.view(), allow, part.geom(), dist)
 }
 
 pub(crate) fn get_optional<T>(data: *mut T) -> Option<*mut T> {
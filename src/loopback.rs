@@ -0,0 +1,127 @@
+//! Attaches image files to loop devices, so that they may be partitioned like any other disk.
+//!
+//! Requires the `loopback` feature, and permission to open `/dev/loop-control`.
+//!
+//! ```rust,no_run
+//! # use libparted::loopback::LoopDevice;
+//! # use libparted::{Disk, PartitionTableType};
+//! let loopdev = LoopDevice::attach("disk.img").unwrap();
+//! let device = loopdev.device().unwrap();
+//! let mut disk = Disk::new_with_partition_table(&device, PartitionTableType::GPT).unwrap();
+//! disk.commit().unwrap();
+//! ```
+
+use super::Device;
+use libc::ioctl;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::mem;
+use std::os::raw::{c_int, c_ulong};
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+
+// From <linux/loop.h>.
+const LOOP_SET_FD: c_ulong = 0x4C00;
+const LOOP_CLR_FD: c_ulong = 0x4C01;
+const LOOP_SET_STATUS64: c_ulong = 0x4C04;
+const LOOP_CTL_GET_FREE: c_ulong = 0x4C82;
+const LO_FLAGS_PARTSCAN: u32 = 8;
+
+#[repr(C)]
+struct LoopInfo64 {
+    lo_device: u64,
+    lo_inode: u64,
+    lo_rdevice: u64,
+    lo_offset: u64,
+    lo_sizelimit: u64,
+    lo_number: u32,
+    lo_encrypt_type: u32,
+    lo_encrypt_key_size: u32,
+    lo_flags: u32,
+    lo_file_name: [u8; 64],
+    lo_crypt_name: [u8; 64],
+    lo_encrypt_key: [u8; 32],
+    lo_init: [u64; 2],
+}
+
+/// A loop device backed by an image file, which is detached when dropped.
+pub struct LoopDevice {
+    path: PathBuf,
+    file: File,
+    attached: bool,
+}
+
+impl LoopDevice {
+    /// Attaches `image` to the first free loop device, with partition scanning enabled so that
+    /// the kernel creates a device node for each partition.
+    pub fn attach<P: AsRef<Path>>(image: P) -> io::Result<LoopDevice> {
+        let backing = OpenOptions::new().read(true).write(true).open(image)?;
+        let control = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open("/dev/loop-control")?;
+
+        // Another process may claim the free device between finding and attaching it.
+        let mut attempts = 0;
+        let (path, file) = loop {
+            let num = check(unsafe { ioctl(control.as_raw_fd(), LOOP_CTL_GET_FREE as _) })?;
+            let path = PathBuf::from(format!("/dev/loop{}", num));
+            let file = OpenOptions::new().read(true).write(true).open(&path)?;
+            match check(unsafe { ioctl(file.as_raw_fd(), LOOP_SET_FD as _, backing.as_raw_fd()) }) {
+                Ok(_) => break (path, file),
+                Err(ref why) if why.raw_os_error() == Some(libc::EBUSY) && attempts < 8 => {
+                    attempts += 1;
+                }
+                Err(why) => return Err(why),
+            }
+        };
+
+        let loopdev = LoopDevice {
+            path,
+            file,
+            attached: true,
+        };
+
+        let mut info: LoopInfo64 = unsafe { mem::zeroed() };
+        info.lo_flags = LO_FLAGS_PARTSCAN;
+        check(unsafe { ioctl(loopdev.file.as_raw_fd(), LOOP_SET_STATUS64 as _, &info) })?;
+
+        Ok(loopdev)
+    }
+
+    /// The path of the loop device, such as `/dev/loop0`.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Opens the loop device with libparted.
+    pub fn device(&self) -> io::Result<Device> {
+        Device::new(&self.path)
+    }
+
+    /// Detaches the loop device, reporting any error which occurs.
+    pub fn detach(mut self) -> io::Result<()> {
+        self.attached = false;
+        self.clear()
+    }
+
+    fn clear(&self) -> io::Result<()> {
+        check(unsafe { ioctl(self.file.as_raw_fd(), LOOP_CLR_FD as _, 0) }).map(|_| ())
+    }
+}
+
+impl Drop for LoopDevice {
+    fn drop(&mut self) {
+        if self.attached {
+            let _ = self.clear();
+        }
+    }
+}
+
+fn check(result: c_int) -> io::Result<c_int> {
+    if result == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(result)
+    }
+}
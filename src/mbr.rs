@@ -0,0 +1,176 @@
+//! Reads and writes the partition entries of the MBR in the first sector of a device, which
+//! on GPT disks is either a protective MBR or a hybrid MBR.
+//!
+//! A hybrid MBR mirrors up to three GPT partitions as MBR partitions, for firmware and
+//! operating systems which do not understand GPT (ie: the Raspberry Pi boot loader). The
+//! fourth entry is the `0xEE` protective partition, which covers the GPT headers.
+
+use super::Device;
+use std::io::{Error, ErrorKind, Result};
+
+/// The MBR partition type of the protective partition of a GPT disk.
+pub const PROTECTIVE_TYPE: u8 = 0xEE;
+
+const TABLE_OFFSET: usize = 446;
+const ENTRY_SIZE: usize = 16;
+const BOOT_SIGNATURE: [u8; 2] = [0x55, 0xAA];
+
+/// A single MBR partition entry, with its location in logical sectors.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct MbrEntry {
+    /// Whether the partition is marked as active, for BIOS boot loaders.
+    pub bootable: bool,
+    /// The MBR partition type (ie: `0x0C` for FAT32 with LBA addressing, `0x83` for Linux).
+    pub type_id: u8,
+    pub start: u32,
+    pub length: u32,
+}
+
+impl MbrEntry {
+    /// An entry is unused when its type is zero.
+    pub fn is_used(&self) -> bool {
+        self.type_id != 0
+    }
+
+    fn parse(raw: &[u8]) -> MbrEntry {
+        let le32 = |bytes: &[u8]| {
+            u32::from(bytes[0])
+                | u32::from(bytes[1]) << 8
+                | u32::from(bytes[2]) << 16
+                | u32::from(bytes[3]) << 24
+        };
+
+        MbrEntry {
+            bootable: raw[0] == 0x80,
+            type_id: raw[4],
+            start: le32(&raw[8..12]),
+            length: le32(&raw[12..16]),
+        }
+    }
+
+    fn write(&self, raw: &mut [u8]) {
+        raw[0] = if self.bootable { 0x80 } else { 0 };
+        // CHS addresses are ignored by anything which understands LBA, so the conventional
+        // "beyond CHS" values are written when the entry is used.
+        let chs = if self.is_used() {
+            [0xFE, 0xFF, 0xFF]
+        } else {
+            [0; 3]
+        };
+        raw[1..4].copy_from_slice(&chs);
+        raw[4] = self.type_id;
+        raw[5..8].copy_from_slice(&chs);
+        raw[8..12].copy_from_slice(&self.start.to_le_bytes());
+        raw[12..16].copy_from_slice(&self.length.to_le_bytes());
+    }
+}
+
+/// The four partition entries of an MBR.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Mbr {
+    pub entries: [MbrEntry; 4],
+}
+
+impl Mbr {
+    /// Whether this is the protective MBR of a GPT disk, with a single `0xEE` entry.
+    pub fn is_protective(&self) -> bool {
+        let used = self.used();
+        used.len() == 1 && used[0].type_id == PROTECTIVE_TYPE
+    }
+
+    /// Whether this is a hybrid MBR, with a `0xEE` entry alongside other partitions.
+    pub fn is_hybrid(&self) -> bool {
+        let used = self.used();
+        used.len() > 1 && used.iter().any(|entry| entry.type_id == PROTECTIVE_TYPE)
+    }
+
+    fn used(&self) -> Vec<&MbrEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.is_used())
+            .collect()
+    }
+
+    /// Builds a hybrid MBR which mirrors `partitions`, preceded by a protective entry which
+    /// covers the sectors from 1 up to the first of them.
+    pub fn hybrid(partitions: &[MbrEntry]) -> Result<Mbr> {
+        if partitions.is_empty() || partitions.len() > 3 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "a hybrid MBR must mirror between one and three partitions",
+            ));
+        }
+
+        if partitions
+            .iter()
+            .any(|part| part.type_id == 0 || part.type_id == PROTECTIVE_TYPE || part.length == 0)
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "mirrored partitions need a non-zero length and a type other than 0x00 or 0xEE",
+            ));
+        }
+
+        let first = partitions.iter().map(|part| part.start).min().unwrap_or(0);
+        if first < 2 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "mirrored partitions must not overlap the GPT header",
+            ));
+        }
+
+        let mut mbr = Mbr::default();
+        mbr.entries[0] = MbrEntry {
+            bootable: false,
+            type_id: PROTECTIVE_TYPE,
+            start: 1,
+            length: first - 1,
+        };
+        mbr.entries[1..=partitions.len()].copy_from_slice(partitions);
+        Ok(mbr)
+    }
+
+    /// Reads the MBR from the first sector of `device`, which is held open for the duration
+    /// of the read.
+    pub fn read(device: &Device) -> Result<Mbr> {
+        let _handle = device.handle()?;
+        let sector = read_sector(device)?;
+        if sector[510..512] != BOOT_SIGNATURE {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "the first sector does not have an MBR boot signature",
+            ));
+        }
+
+        let mut mbr = Mbr::default();
+        for (index, entry) in mbr.entries.iter_mut().enumerate() {
+            let offset = TABLE_OFFSET + index * ENTRY_SIZE;
+            *entry = MbrEntry::parse(&sector[offset..offset + ENTRY_SIZE]);
+        }
+
+        Ok(mbr)
+    }
+
+    /// Writes the partition entries to the first sector of `device`, preserving the boot code
+    /// and disk signature already there. The device is held open for the duration of the
+    /// write.
+    pub fn write(&self, device: &mut Device) -> Result<()> {
+        let handle = device.handle()?;
+        let mut device = unsafe { Device::borrowed(handle.ped_device()) };
+        let mut sector = read_sector(&device)?;
+        for (index, entry) in self.entries.iter().enumerate() {
+            let offset = TABLE_OFFSET + index * ENTRY_SIZE;
+            entry.write(&mut sector[offset..offset + ENTRY_SIZE]);
+        }
+
+        sector[510..512].copy_from_slice(&BOOT_SIGNATURE);
+        device.write_to_sectors(&sector, 0, 1)?;
+        device.sync()
+    }
+}
+
+fn read_sector(device: &Device) -> Result<Vec<u8>> {
+    let mut sector = vec![0; device.sector_size() as usize];
+    device.read_from_sectors(&mut sector, 0, 1)?;
+    Ok(sector)
+}
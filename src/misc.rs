@@ -1,33 +1,385 @@
 //! Implements some miscellanious functions from the libparted API. These aren't taken from
 //! the libparted bindings as it's trivial to write them ourselves.
 
-fn abs_mod(a: i64, b: i64) -> i64 {
-    if a < 0 {
-        a % b + b
+use super::IsZero;
+use std::io;
+
+/// Integer types which may be rounded to a multiple of a grain size, with `round_down_to()`
+/// and friends.
+pub trait Roundable: Copy + PartialOrd + IsZero {
+    /// The remainder of dividing by `grain_size`, which is never negative, or `None` if the
+    /// grain size is not positive.
+    fn grain_rem(self, grain_size: Self) -> Option<Self>;
+    fn checked_add(self, other: Self) -> Option<Self>;
+    fn checked_sub(self, other: Self) -> Option<Self>;
+    fn saturating_add(self, other: Self) -> Self;
+    fn saturating_sub(self, other: Self) -> Self;
+    /// Half of the value, rounded down.
+    fn half(self) -> Self;
+}
+
+macro_rules! impl_roundable {
+    ($($t:ty)*) => ($(
+        impl Roundable for $t {
+            #[allow(unused_comparisons)]
+            fn grain_rem(self, grain_size: $t) -> Option<$t> {
+                if grain_size <= 0 {
+                    return None;
+                }
+
+                let rem = self % grain_size;
+                Some(if rem < 0 { rem + grain_size } else { rem })
+            }
+
+            fn checked_add(self, other: $t) -> Option<$t> {
+                <$t>::checked_add(self, other)
+            }
+
+            fn checked_sub(self, other: $t) -> Option<$t> {
+                <$t>::checked_sub(self, other)
+            }
+
+            fn saturating_add(self, other: $t) -> $t {
+                <$t>::saturating_add(self, other)
+            }
+
+            fn saturating_sub(self, other: $t) -> $t {
+                <$t>::saturating_sub(self, other)
+            }
+
+            fn half(self) -> $t {
+                self / 2
+            }
+        }
+    )*)
+}
+
+impl_roundable! { i32 i64 isize u32 u64 usize }
+
+/// Rounds a number down to the closest number that is a multiple of the grain size, or
+/// returns `None` if the result is not representable or the grain size is not positive.
+pub fn checked_round_down_to<T: Roundable>(sector: T, grain_size: T) -> Option<T> {
+    sector.checked_sub(sector.grain_rem(grain_size)?)
+}
+
+/// Rounds a number up to the closest number that is a multiple of the grain size, or
+/// returns `None` if the result is not representable or the grain size is not positive.
+pub fn checked_round_up_to<T: Roundable>(sector: T, grain_size: T) -> Option<T> {
+    let rem = sector.grain_rem(grain_size)?;
+    if rem.is_zero() {
+        Some(sector)
     } else {
-        a % b
+        sector.checked_sub(rem)?.checked_add(grain_size)
+    }
+}
+
+/// Rounds a number to the closest number that is a multiple of the grain size, or returns
+/// `None` if the result is not representable or the grain size is not positive.
+pub fn checked_round_to_nearest<T: Roundable>(sector: T, grain_size: T) -> Option<T> {
+    if sector.grain_rem(grain_size)? > grain_size.half() {
+        checked_round_up_to(sector, grain_size)
+    } else {
+        checked_round_down_to(sector, grain_size)
     }
 }
 
 /// Rounds a number down to the closest number that is a multiple of the grain size.
-pub fn round_down_to(sector: i64, grain_size: i64) -> i64 {
-    sector - abs_mod(sector, grain_size)
+///
+/// # Note:
+///
+/// If the result is not representable, the smallest value of the type is returned instead,
+/// and a grain size which is not positive leaves the number as it is. Use
+/// `checked_round_down_to()` to detect these cases.
+pub fn round_down_to<T: Roundable>(sector: T, grain_size: T) -> T {
+    match sector.grain_rem(grain_size) {
+        Some(rem) => sector.saturating_sub(rem),
+        None => sector,
+    }
 }
 
 /// Rounds a number up to the closest number that is a multiple of the grain size.
-pub fn round_up_to(sector: i64, grain_size: i64) -> i64 {
-    if sector % grain_size != 0 {
-        round_down_to(sector, grain_size) + grain_size
-    } else {
-        sector
+///
+/// # Note:
+///
+/// If the result is not representable (ie: near `i64::MAX`), the largest value of the type
+/// is returned instead, and a grain size which is not positive leaves the number as it is.
+/// Use `checked_round_up_to()` to detect these cases.
+pub fn round_up_to<T: Roundable>(sector: T, grain_size: T) -> T {
+    match sector.grain_rem(grain_size) {
+        Some(rem) if !rem.is_zero() => sector.saturating_add(grain_size.saturating_sub(rem)),
+        _ => sector,
     }
 }
 
 /// Rounds a number to the closest number that is a multiple of the grain_size.
-pub fn round_to_nearest(sector: i64, grain_size: i64) -> i64 {
-    if sector % grain_size > grain_size / 2 {
-        round_up_to(sector, grain_size)
-    } else {
-        round_down_to(sector, grain_size)
+///
+/// # Note:
+///
+/// If the result is not representable, it saturates at the bounds of the type, and a grain
+/// size which is not positive leaves the number as it is. Use `checked_round_to_nearest()`
+/// to detect these cases.
+pub fn round_to_nearest<T: Roundable>(sector: T, grain_size: T) -> T {
+    match sector.grain_rem(grain_size) {
+        Some(rem) if rem > grain_size.half() => round_up_to(sector, grain_size),
+        _ => round_down_to(sector, grain_size),
+    }
+}
+
+/// The multiples of a byte used by `format_size()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Standard {
+    /// Powers of 1024, written as KiB, MiB, GiB, and so on.
+    IEC,
+    /// Powers of 1000, written as kB, MB, GB, and so on.
+    SI,
+}
+
+const IEC_UNITS: [&str; 7] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+const SI_UNITS: [&str; 7] = ["B", "kB", "MB", "GB", "TB", "PB", "EB"];
+
+/// Formats a number of bytes with the largest unit of `standard` that keeps the number at
+/// least one, to at most two decimal places (ie: `1.5 GiB`, `512 B`).
+///
+/// The result may be parsed again with `parse_size()`, though not exactly, as it is rounded.
+pub fn format_size(bytes: u64, standard: Standard) -> String {
+    let (base, units) = match standard {
+        Standard::IEC => (1024f64, &IEC_UNITS),
+        Standard::SI => (1000f64, &SI_UNITS),
+    };
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while unit + 1 < units.len() && value >= base {
+        value /= base;
+        unit += 1;
+    }
+
+    let mut number = format!("{:.2}", value);
+    // Rounding may carry the number up to the next unit (ie: 1023.999 KiB).
+    if unit + 1 < units.len() && number.parse::<f64>().map_or(false, |value| value >= base) {
+        value /= base;
+        unit += 1;
+        number = format!("{:.2}", value);
+    }
+
+    let number = number.trim_end_matches('0').trim_end_matches('.');
+    format!("{} {}", number, units[unit])
+}
+
+/// Parses a size such as `1.5GiB`, `500 MB`, or `4096`, returning the number of bytes.
+///
+/// IEC units (`KiB`, `MiB`, ...) are powers of 1024, while SI units (`kB`, `MB`, ...) and
+/// bare prefixes (`K`, `M`, ...) are powers of 1000, as with parted. Units are not case
+/// sensitive, and a number without a unit is a count of bytes. Fractional sizes are rounded
+/// down to a whole byte.
+pub fn parse_size(input: &str) -> io::Result<u64> {
+    let invalid = || {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("`{}` is not a valid size", input),
+        )
+    };
+
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(input.len());
+    let (number, unit) = (&input[..split], input[split..].trim());
+
+    let multiplier: u64 = match unit.to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" => 1000,
+        "m" | "mb" => 1000u64.pow(2),
+        "g" | "gb" => 1000u64.pow(3),
+        "t" | "tb" => 1000u64.pow(4),
+        "p" | "pb" => 1000u64.pow(5),
+        "e" | "eb" => 1000u64.pow(6),
+        "kib" => 1 << 10,
+        "mib" => 1 << 20,
+        "gib" => 1 << 30,
+        "tib" => 1 << 40,
+        "pib" => 1 << 50,
+        "eib" => 1 << 60,
+        _ => return Err(invalid()),
+    };
+
+    let (whole, fraction) = match number.find('.') {
+        Some(pos) => (&number[..pos], &number[pos + 1..]),
+        None => (number, ""),
+    };
+
+    if whole.is_empty() && fraction.is_empty() || fraction.contains('.') {
+        return Err(invalid());
+    }
+
+    let parse = |digits: &str| -> io::Result<u128> {
+        if digits.is_empty() {
+            Ok(0)
+        } else {
+            digits.parse::<u128>().map_err(|_| invalid())
+        }
+    };
+
+    // Whole bytes are computed exactly, so that sizes such as `1.5GiB` are not rounded.
+    let scale = 10u128
+        .checked_pow(fraction.len() as u32)
+        .ok_or_else(invalid)?;
+    let bytes = parse(whole)?
+        .checked_mul(u128::from(multiplier))
+        .and_then(|whole| {
+            parse(fraction)
+                .ok()?
+                .checked_mul(u128::from(multiplier))
+                .map(|fraction| whole + fraction / scale)
+        })
+        .ok_or_else(invalid)?;
+
+    if bytes > u128::from(u64::MAX) {
+        return Err(invalid());
+    }
+
+    Ok(bytes as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rounds_to_multiples_of_the_grain() {
+        assert_eq!(round_down_to(4095i64, 2048), 2048);
+        assert_eq!(round_up_to(2049i64, 2048), 4096);
+        assert_eq!(round_up_to(4096i64, 2048), 4096);
+        assert_eq!(round_to_nearest(3072i64, 2048), 2048);
+        assert_eq!(round_to_nearest(3073i64, 2048), 4096);
+        assert_eq!(round_down_to(-1i64, 2048), -2048);
+        assert_eq!(round_up_to(-1i64, 2048), 0);
+    }
+
+    #[test]
+    fn rounding_saturates_instead_of_panicking() {
+        assert_eq!(round_up_to(i64::MAX - 1, 2048), i64::MAX);
+        assert_eq!(checked_round_up_to(i64::MAX - 1, 2048), None);
+        assert_eq!(round_down_to(i64::MIN + 1, 3000), i64::MIN);
+        assert_eq!(checked_round_down_to(i64::MIN + 1, 3000), None);
+        assert_eq!(round_to_nearest(u64::MAX, 2048), u64::MAX);
+        assert_eq!(round_to_nearest(u64::MAX - 2047, 2048), u64::MAX - 2047);
+        assert_eq!(round_up_to(u64::MAX, 4096), u64::MAX);
+    }
+
+    #[test]
+    fn invalid_grains_leave_the_number_alone() {
+        assert_eq!(round_down_to(4095i64, 0), 4095);
+        assert_eq!(round_up_to(4095i64, -2048), 4095);
+        assert_eq!(round_to_nearest(4095u32, 0), 4095);
+        assert_eq!(checked_round_to_nearest(4095u32, 0), None);
+    }
+
+    #[test]
+    fn parses_sizes() {
+        let cases: &[(&str, u64)] = &[
+            ("4096", 4096),
+            ("0", 0),
+            ("1B", 1),
+            ("1 b", 1),
+            ("1k", 1000),
+            ("1K", 1000),
+            ("1kB", 1000),
+            ("1KiB", 1024),
+            ("1kib", 1024),
+            ("500 MB", 500_000_000),
+            ("500M", 500_000_000),
+            ("1.5GiB", 1_610_612_736),
+            ("  2 gib ", 2 << 30),
+            ("1.5TB", 1_500_000_000_000),
+            (".5K", 500),
+            ("1.", 1),
+            ("1.0005K", 1000),
+            ("0.1B", 0),
+            ("1.5EiB", 3 << 59),
+            ("15EiB", 15 << 60),
+            ("18446744073709551615", u64::MAX),
+        ];
+
+        for &(input, bytes) in cases {
+            assert_eq!(parse_size(input).unwrap(), bytes, "parsing {:?}", input);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_sizes() {
+        let cases = [
+            "",
+            " ",
+            ".",
+            "K",
+            "GiB",
+            "abc",
+            "-1",
+            "1e3",
+            "1.2.3",
+            "1 XB",
+            "1 .5",
+            "1KiBs",
+            "16EiB",
+            "18446744073709551616",
+        ];
+
+        for &input in &cases {
+            let err = parse_size(input).unwrap_err();
+            assert_eq!(
+                err.kind(),
+                io::ErrorKind::InvalidData,
+                "parsing {:?}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn formats_sizes() {
+        let cases: &[(u64, Standard, &str)] = &[
+            (0, Standard::IEC, "0 B"),
+            (512, Standard::IEC, "512 B"),
+            (1023, Standard::IEC, "1023 B"),
+            (1024, Standard::IEC, "1 KiB"),
+            (1536, Standard::IEC, "1.5 KiB"),
+            (1_610_612_736, Standard::IEC, "1.5 GiB"),
+            (1_048_575, Standard::IEC, "1 MiB"),
+            (u64::MAX, Standard::IEC, "16 EiB"),
+            (999, Standard::SI, "999 B"),
+            (1000, Standard::SI, "1 kB"),
+            (1_500_000_000, Standard::SI, "1.5 GB"),
+            (1_234_567, Standard::SI, "1.23 MB"),
+            (999_999, Standard::SI, "1 MB"),
+            (u64::MAX, Standard::SI, "18.45 EB"),
+        ];
+
+        for &(bytes, standard, formatted) in cases {
+            assert_eq!(
+                format_size(bytes, standard),
+                formatted,
+                "formatting {}",
+                bytes
+            );
+        }
+    }
+
+    #[test]
+    fn formatted_sizes_parse_again() {
+        for &bytes in &[0, 512, 1536, 4 << 20, 3 << 29, 7 << 40] {
+            for &standard in &[Standard::IEC, Standard::SI] {
+                let formatted = format_size(bytes, standard);
+                let parsed = parse_size(&formatted).unwrap();
+                let tolerance = bytes / 100;
+                assert!(
+                    parsed + tolerance >= bytes && parsed <= bytes + tolerance,
+                    "{} was formatted as {:?} and parsed as {}",
+                    bytes,
+                    formatted,
+                    parsed
+                );
+            }
+        }
     }
 }
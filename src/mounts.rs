@@ -0,0 +1,148 @@
+//! Finds where block devices are mounted or used as swap, from `/proc/self/mountinfo` and
+//! `/proc/swaps`.
+
+use super::device::{major, minor};
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::os::unix::ffi::OsStringExt;
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::path::{Path, PathBuf};
+
+/// Where a partition is mounted, as returned by `Partition::mount_points()`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MountPoint {
+    /// The directory the file system is mounted on.
+    pub path: PathBuf,
+    /// The directory within the file system which is mounted, which is `/` unless it is a
+    /// bind mount or a btrfs subvolume.
+    pub root: PathBuf,
+    /// The type of the mounted file system, such as `ext4`.
+    pub fs_type: String,
+    /// The per-mount options, such as `rw,relatime`.
+    pub options: String,
+}
+
+/// Returns each mount of the block device at `device`.
+pub(crate) fn mount_points(device: &Path) -> io::Result<Vec<MountPoint>> {
+    let rdev = fs::metadata(device)?.rdev();
+    let device_id = format!("{}:{}", major(rdev), minor(rdev));
+
+    let mountinfo = fs::read_to_string("/proc/self/mountinfo")?;
+    let mounts = mountinfo
+        .lines()
+        .filter_map(|line| {
+            // The optional fields are terminated by a lone `-`.
+            let mut halves = line.splitn(2, " - ");
+            let fields = halves.next()?.split(' ').collect::<Vec<_>>();
+            let fs_type = halves.next()?.split(' ').next()?;
+
+            if fields.len() < 6 || fields[2] != device_id {
+                return None;
+            }
+
+            Some(MountPoint {
+                path: unescape(fields[4]),
+                root: unescape(fields[3]),
+                fs_type: fs_type.to_owned(),
+                options: fields[5].to_owned(),
+            })
+        })
+        .collect();
+
+    Ok(mounts)
+}
+
+/// Returns the path of each block device which is in use as swap, which is either the device
+/// at `device` itself, or one of its partitions.
+pub(crate) fn active_swaps(device: &Path) -> io::Result<Vec<PathBuf>> {
+    let rdev = fs::metadata(device)?.rdev();
+    let device_id = format!("{}:{}", major(rdev), minor(rdev));
+
+    let swaps = swap_devices()?
+        .into_iter()
+        .filter(|&(_, swap_rdev)| {
+            swap_rdev == rdev || parent_id(swap_rdev).map_or(false, |parent| parent == device_id)
+        })
+        .map(|(swap, _)| swap)
+        .collect();
+
+    Ok(swaps)
+}
+
+/// Whether the block device at `path` is mounted. Errors are treated as not being mounted.
+pub(crate) fn is_mounted(path: &Path) -> bool {
+    mount_points(path).map_or(false, |mounts| !mounts.is_empty())
+}
+
+/// Whether the block device at `path` itself is in use as swap. Errors are treated as not
+/// being in use.
+pub(crate) fn is_swap(path: &Path) -> bool {
+    let rdev = match fs::metadata(path) {
+        Ok(metadata) => metadata.rdev(),
+        Err(_) => return false,
+    };
+
+    swap_devices().map_or(false, |swaps| {
+        swaps.iter().any(|&(_, swap_rdev)| swap_rdev == rdev)
+    })
+}
+
+/// Returns the path and device number of each block device listed in `/proc/swaps`. Swap
+/// files are skipped.
+fn swap_devices() -> io::Result<Vec<(PathBuf, u64)>> {
+    let swaps = fs::read_to_string("/proc/swaps")?;
+    let swaps = swaps
+        .lines()
+        .skip(1)
+        .filter_map(|line| line.split_whitespace().next())
+        .map(unescape)
+        .filter_map(|swap| match fs::metadata(&swap) {
+            Ok(ref metadata) if metadata.file_type().is_block_device() => {
+                Some((swap, metadata.rdev()))
+            }
+            _ => None,
+        })
+        .collect();
+
+    Ok(swaps)
+}
+
+/// Returns the `major:minor` of the device containing the partition `rdev`, if it is one.
+fn parent_id(rdev: u64) -> Option<String> {
+    let sysfs = Path::new("/sys/dev/block").join(format!("{}:{}", major(rdev), minor(rdev)));
+    if !sysfs.join("partition").exists() {
+        return None;
+    }
+
+    let parent = fs::canonicalize(sysfs).ok()?.join("../dev");
+    fs::read_to_string(parent)
+        .ok()
+        .map(|id| id.trim().to_owned())
+}
+
+/// Reverses the octal escaping of spaces, tabs, newlines, and backslashes in mountinfo paths.
+fn unescape(field: &str) -> PathBuf {
+    let bytes = field.as_bytes();
+    let mut path = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let octal = bytes.get(i + 1..i + 4).and_then(|digits| {
+            let digits = std::str::from_utf8(digits).ok()?;
+            u8::from_str_radix(digits, 8).ok()
+        });
+
+        match octal {
+            Some(byte) if bytes[i] == b'\\' => {
+                path.push(byte);
+                i += 4;
+            }
+            _ => {
+                path.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    PathBuf::from(OsString::from_vec(path))
+}
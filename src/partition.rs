@@ -1,22 +1,138 @@
-use super::{cvt, Disk, FileSystemType, Geometry};
-use std::ffi::{CStr, CString, OsStr};
+use super::exception::{self, ExceptionOption};
+use super::{
+    cvt, device, disk, signatures, DeviceHandle, DeviceRef, Disk, FileSystemType, GeomView,
+    Geometry, Sector,
+};
+use std::ffi::{CStr, CString, OsStr, OsString};
 use std::io;
 use std::marker::PhantomData;
+use std::mem;
+use std::ops::Deref;
+use std::os::raw::c_char;
 use std::os::unix::ffi::OsStrExt;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::ptr;
 use std::str;
 
+#[cfg(feature = "probe-extra")]
+use super::fsid;
+#[cfg(feature = "parted-3-5")]
+use super::version;
+#[cfg(feature = "parted-3-5")]
+use super::{gpt::PartitionTypeGuid, DiskTypeFeature};
+#[cfg(feature = "proc")]
+use super::{mounts, MountPoint};
+#[cfg(feature = "parted-3-5")]
+use libparted_sys::ped_disk_type_check_feature;
 use libparted_sys::{
-    ped_partition_destroy, ped_partition_get_flag, ped_partition_get_name, ped_partition_get_path,
+    ped_disk_extended_partition, ped_partition_destroy, ped_partition_flag_get_name,
+    ped_partition_get_flag, ped_partition_get_name, ped_partition_get_path,
     ped_partition_is_active, ped_partition_is_busy, ped_partition_is_flag_available,
     ped_partition_new, ped_partition_set_flag, ped_partition_set_name, ped_partition_set_system,
     ped_partition_type_get_name, PedFileSystemType, PedGeometry, PedPartition,
 };
+#[cfg(feature = "parted-3-5")]
+use std::os::raw::c_int;
 
 pub use libparted_sys::PedPartitionFlag as PartitionFlag;
 pub use libparted_sys::PedPartitionType as PartitionType;
 
+/// The maximum length of a GPT partition name, in UTF-16 code units.
+const GPT_NAME_MAX_LEN: usize = 36;
+
+// Added in libparted 3.5, and so looked up at runtime with `version::function()`.
+#[cfg(feature = "parted-3-5")]
+type GetTypeId = unsafe extern "C" fn(*const PedPartition) -> u8;
+#[cfg(feature = "parted-3-5")]
+type SetTypeId = unsafe extern "C" fn(*mut PedPartition, u8) -> c_int;
+#[cfg(feature = "parted-3-5")]
+type GetTypeUuid = unsafe extern "C" fn(*const PedPartition) -> *mut u8;
+#[cfg(feature = "parted-3-5")]
+type SetTypeUuid = unsafe extern "C" fn(*mut PedPartition, *const u8) -> c_int;
+
+// Declared with integer types, as these return `0` when there is no such flag, which is not
+// a valid `PartitionFlag`.
+extern "C" {
+    #[link_name = "ped_partition_flag_next"]
+    fn partition_flag_next(flag: u32) -> u32;
+    #[link_name = "ped_partition_flag_get_by_name"]
+    fn partition_flag_get_by_name(name: *const c_char) -> u32;
+}
+
+/// Predicts the path of partition `num` on the device at `device`, without asking libparted.
+///
+/// As the kernel names them, a `p` separates the partition number from device names which
+/// end with a digit (ie: `/dev/nvme0n1p1`, `/dev/mmcblk0p1`, `/dev/loop0p1`), and the number
+/// is appended directly to other names (ie: `/dev/sda1`).
+pub fn predicted_path<P: AsRef<Path>>(device: P, num: u32) -> PathBuf {
+    let mut path = device.as_ref().as_os_str().to_owned();
+    if path.as_bytes().last().map_or(false, u8::is_ascii_digit) {
+        path.push("p");
+    }
+    path.push(num.to_string());
+    PathBuf::from(path)
+}
+
+/// Whether `flag` is an attribute of the type of a partition, rather than determining it.
+fn is_attribute_flag(flag: PartitionFlag) -> bool {
+    matches!(
+        flag,
+        PartitionFlag::PED_PARTITION_BOOT
+            | PartitionFlag::PED_PARTITION_HIDDEN
+            | PartitionFlag::PED_PARTITION_LBA
+            | PartitionFlag::PED_PARTITION_LEGACY_BOOT
+    )
+}
+
+/// Name lookup and enumeration for **PartitionFlag**s.
+pub trait PartitionFlagExt: Sized {
+    /// Every flag known to libparted, in order.
+    fn all() -> Vec<Self>;
+
+    /// Returns the flag with the given `name`, such as `"boot"` or `"esp"`.
+    fn from_name(name: &str) -> Option<Self>;
+
+    /// Returns the name libparted uses for the flag.
+    fn name(&self) -> &'static str;
+}
+
+/// Converts a flag returned by libparted, which may be newer than the flags that
+/// **PartitionFlag** knows of (ie: `chromeos_kernel` and `bls_boot`), or `0` for no flag.
+fn partition_flag(flag: u32) -> Option<PartitionFlag> {
+    if flag >= 1 && flag <= PartitionFlag::PED_PARTITION_ESP as u32 {
+        Some(unsafe { mem::transmute::<u32, PartitionFlag>(flag) })
+    } else {
+        None
+    }
+}
+
+impl PartitionFlagExt for PartitionFlag {
+    /// Flags which are newer than **PartitionFlag** are skipped.
+    fn all() -> Vec<PartitionFlag> {
+        let mut flags = Vec::new();
+        let mut flag = unsafe { partition_flag_next(0) };
+        while flag != 0 {
+            flags.extend(partition_flag(flag));
+            flag = unsafe { partition_flag_next(flag) };
+        }
+
+        flags
+    }
+
+    /// Returns `None` for flags which are newer than **PartitionFlag**.
+    fn from_name(name: &str) -> Option<PartitionFlag> {
+        let name = CString::new(name).ok()?;
+        partition_flag(unsafe { partition_flag_get_by_name(name.as_ptr()) })
+    }
+
+    fn name(&self) -> &'static str {
+        unsafe {
+            let cstr = CStr::from_ptr(ped_partition_flag_get_name(*self));
+            str::from_utf8_unchecked(cstr.to_bytes())
+        }
+    }
+}
+
 #[derive(PartialEq)]
 pub struct Partition<'a> {
     pub(crate) part: *mut PedPartition,
@@ -24,6 +140,10 @@ pub struct Partition<'a> {
     pub(crate) is_droppable: bool,
 }
 
+/// Takes ownership of `part`, which will be destroyed when the **Partition** is dropped.
+///
+/// Only use this for partitions which have not been added to a disk. A partition which belongs
+/// to a disk is destroyed along with it; use `Disk::partition()` to obtain a view of one.
 impl<'a> From<*mut PedPartition> for Partition<'a> {
     fn from(part: *mut PedPartition) -> Self {
         Partition {
@@ -35,6 +155,19 @@ impl<'a> From<*mut PedPartition> for Partition<'a> {
 }
 
 impl<'a> Partition<'a> {
+    /// Wraps a partition which is owned by its disk, and so is never destroyed when dropped.
+    ///
+    /// # Safety
+    ///
+    /// `part` must belong to a disk which outlives `'a`.
+    pub(crate) unsafe fn borrowed(part: *mut PedPartition) -> Partition<'a> {
+        Partition {
+            part,
+            phantom: PhantomData,
+            is_droppable: false,
+        }
+    }
+
     /// Create a new **Partition** on `disk`.
     ///
     /// # Note:
@@ -46,13 +179,18 @@ impl<'a> Partition<'a> {
     ///
     /// Throws `PED_EXCEPTION_ERROR` if `type` is `EXTENDED` or `LOGICAL` but the label
     /// does not support this concept.
-    pub fn new(
+    pub fn new<S, E>(
         disk: &Disk,
         type_: PartitionType,
         fs_type: Option<&FileSystemType>,
-        start: i64,
-        end: i64,
-    ) -> io::Result<Partition<'a>> {
+        start: S,
+        end: E,
+    ) -> io::Result<Partition<'a>>
+    where
+        S: Into<Sector>,
+        E: Into<Sector>,
+    {
+        let (start, end) = (start.into().0, end.into().0);
         let fs_type = fs_type.map_or(ptr::null_mut() as *mut PedFileSystemType, |f| f.fs);
         cvt(unsafe { ped_partition_new(disk.disk, type_, fs_type, start, end) })
             .map(Partition::from)
@@ -86,23 +224,81 @@ impl<'a> Partition<'a> {
         }
     }
 
+    /// The raw type bits of the partition, which may combine several `PartitionType`s (ie: free
+    /// space within an extended partition is both `PED_PARTITION_LOGICAL` and `PED_PARTITION_FREE`).
+    pub(crate) fn type_bits(&self) -> u32 {
+        unsafe { *(&(*self.part).type_ as *const PartitionType as *const u32) }
+    }
+
+    /// The type of an active partition, ignoring any other bits which are set.
+    pub(crate) fn kind(&self) -> PartitionType {
+        let bits = self.type_bits();
+        if bits & PartitionType::PED_PARTITION_EXTENDED as u32 != 0 {
+            PartitionType::PED_PARTITION_EXTENDED
+        } else if bits & PartitionType::PED_PARTITION_LOGICAL as u32 != 0 {
+            PartitionType::PED_PARTITION_LOGICAL
+        } else {
+            PartitionType::PED_PARTITION_NORMAL
+        }
+    }
+
+    /// Returns a copy of the start, end, and length of the partition's region.
+    pub fn geom(&self) -> GeomView {
+        let geom = unsafe { (*self.part).geom };
+        GeomView {
+            start: geom.start,
+            end: geom.end,
+            length: geom.length,
+        }
+    }
+
+    #[cfg_attr(
+        feature = "v2-api",
+        deprecated(since = "0.1.6", note = "use `geom().start` instead")
+    )]
     pub fn geom_start(&'a self) -> i64 {
         unsafe { (*self.part).geom.start }
     }
 
+    #[cfg_attr(
+        feature = "v2-api",
+        deprecated(since = "0.1.6", note = "use `geom().length` instead")
+    )]
     pub fn geom_length(&'a self) -> i64 {
         unsafe { (*self.part).geom.length }
     }
 
+    #[cfg_attr(
+        feature = "v2-api",
+        deprecated(since = "0.1.6", note = "use `geom().end` instead")
+    )]
     pub fn geom_end(&'a self) -> i64 {
         unsafe { (*self.part).geom.end }
     }
 
+    /// Returns every flag which is available on the partition and currently set.
+    pub fn flags(&self) -> Vec<PartitionFlag> {
+        PartitionFlag::all()
+            .into_iter()
+            .filter(|&flag| self.is_flag_available(flag) && self.get_flag(flag))
+            .collect()
+    }
+
     /// Get the state of a flag on the disk.
     pub fn get_flag(&self, flag: PartitionFlag) -> bool {
         unsafe { ped_partition_get_flag(self.part, flag) == 1 }
     }
 
+    /// Returns a view of the device on which the partition lies.
+    pub fn device(&self) -> DeviceRef {
+        unsafe { DeviceRef::from_ped_device((*(*self.part).disk).dev) }
+    }
+
+    /// Obtains a shared handle to the device on which the partition lies.
+    pub fn handle(&self) -> io::Result<DeviceHandle> {
+        unsafe { DeviceHandle::from_ped_device((*(*self.part).disk).dev) }
+    }
+
     /// Return a path that can be used to address the partition in the operating system.
     pub fn get_path(&self) -> Option<&Path> {
         if self.is_active() {
@@ -115,6 +311,12 @@ impl<'a> Partition<'a> {
         }
     }
 
+    /// The major and minor numbers of the partition's device node, if the operating system
+    /// has created one (ie: after the partition table has been committed).
+    pub fn devno(&self) -> Option<(u32, u32)> {
+        self.get_path().and_then(device::devno)
+    }
+
     /// Returns whether or not the partition is _active_.
     ///
     /// A partition is active if the type is neither `PED_PARTITION_METADATA` nor
@@ -131,24 +333,161 @@ impl<'a> Partition<'a> {
         unsafe { ped_partition_is_busy(self.part) != 0 }
     }
 
+    /// The UUID of the file system on the partition, as `blkid` reports it (ie: for a
+    /// `UUID=` entry in `/etc/fstab`), or `None` if no supported file system is found.
+    ///
+    /// ext2/3/4, XFS, swap, NTFS, and FAT are supported. The superblock is read from the
+    /// device, so the partition table must have been committed if the partition is new.
+    #[cfg(feature = "probe-extra")]
+    pub fn fs_uuid(&self) -> io::Result<Option<String>> {
+        self.fs_identity()
+            .map(|found| found.and_then(|found| found.uuid))
+    }
+
+    /// The label of the file system on the partition, as `blkid` reports it, or `None` if it
+    /// has none or no supported file system is found. See `Partition::fs_uuid()`.
+    #[cfg(feature = "probe-extra")]
+    pub fn fs_label(&self) -> io::Result<Option<String>> {
+        self.fs_identity()
+            .map(|found| found.and_then(|found| found.label))
+    }
+
+    #[cfg(feature = "probe-extra")]
+    fn fs_identity(&self) -> io::Result<Option<fsid::FsIdentity>> {
+        if self.kind() == PartitionType::PED_PARTITION_EXTENDED {
+            return Ok(None);
+        }
+
+        let geom = unsafe {
+            let mut geom = Geometry::from_raw(&mut (*self.part).geom as *mut PedGeometry);
+            geom.is_droppable = false;
+            geom
+        };
+        fsid::identify(&geom)
+    }
+
+    /// Returns each place the partition is mounted, which explains why `is_busy()` may be
+    /// true, so that callers may ask for it to be unmounted.
+    #[cfg(feature = "proc")]
+    pub fn mount_points(&self) -> io::Result<Vec<MountPoint>> {
+        match self.get_path() {
+            Some(path) => mounts::mount_points(path),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Returns whether the partition is in use as swap, which must be disabled with
+    /// `swapoff` before the partition may be changed.
+    #[cfg(feature = "proc")]
+    pub fn is_active_swap(&self) -> io::Result<bool> {
+        match self.get_path() {
+            Some(path) => mounts::active_swaps(path).map(|swaps| !swaps.is_empty()),
+            None => Ok(false),
+        }
+    }
+
+    /// Erases the signatures of any file systems, encrypted volumes, or RAID and LVM members
+    /// within the partition, so that it may be reused without being misdetected. Returns the
+    /// offset of each signature erased, in bytes from the start of the partition.
+    ///
+    /// Only the magic numbers of each signature are overwritten, and the changes are written
+    /// to the device immediately.
+    pub fn wipe_signatures(&mut self) -> io::Result<Vec<u64>> {
+        if self.is_busy() {
+            return Err(io::Error::from_raw_os_error(libc::EBUSY));
+        }
+
+        // Reads and writes of a device which is not open fail, so it is held open throughout.
+        let mut geom = self.get_geom();
+        let _handle = self.handle()?;
+        signatures::wipe(&mut geom)
+    }
+
     /// Check whether a given flag is available on a disk.
     pub fn is_flag_available(&self, flag: PartitionFlag) -> bool {
         unsafe { ped_partition_is_flag_available(self.part, flag) == 1 }
     }
 
     /// Returns the name of a partition `part`. This will only work if the disk label supports it.
+    ///
+    /// # Note:
+    ///
+    /// Returns `None` if the name is not valid UTF-8. See `Partition::name_os()`.
     pub fn name(&self) -> Option<String> {
-        if self.is_active() {
-            unsafe {
-                let name = ped_partition_get_name(self.part);
-                if name.is_null() {
-                    None
-                } else {
-                    CStr::from_ptr(name).to_str().ok().map(|s| s.to_string())
-                }
-            }
+        self.name_bytes()
+            .and_then(|name| String::from_utf8(name.to_owned()).ok())
+    }
+
+    /// Returns the name of the partition, converted to UTF-8 on a best-effort basis.
+    ///
+    /// # Note:
+    ///
+    /// Names which are valid UTF-8 are returned as they are. Otherwise, libparted stores the
+    /// names of most labels as raw bytes, in whatever encoding the tool which wrote them used:
+    ///
+    /// - mac names are decoded as MacRoman, which is what the classic Mac OS wrote.
+    /// - pc98 names are usually Shift JIS, which is not decoded, and other labels have no
+    ///   defined encoding; invalid UTF-8 sequences in these are replaced with `U+FFFD`.
+    ///
+    /// Use `Partition::name_raw()` where the exact bytes are needed.
+    pub fn name_lossy(&self) -> Option<String> {
+        let name = self.name_bytes()?;
+        if let Ok(name) = str::from_utf8(name) {
+            return Some(name.to_owned());
+        }
+
+        Some(if self.label_name() == Some("mac") {
+            name.iter().map(|&byte| mac_roman_char(byte)).collect()
         } else {
-            None
+            String::from_utf8_lossy(name).into_owned()
+        })
+    }
+
+    /// Returns the bytes of the partition's name exactly as libparted reports them, without
+    /// assuming any encoding.
+    ///
+    /// These may be passed to `Partition::set_name_raw()` to put the name back unchanged.
+    pub fn name_raw(&self) -> Option<&[u8]> {
+        self.name_bytes()
+    }
+
+    /// Returns the name of the partition exactly as libparted reports it.
+    ///
+    /// # Note:
+    ///
+    /// Labels which store names as UTF-16, such as GPT, have their names converted by
+    /// libparted into the character set of the current locale. This will be UTF-8 on most
+    /// systems, but only ASCII if the program has not called `setlocale()`.
+    pub fn name_os(&self) -> Option<OsString> {
+        self.name_bytes()
+            .map(|name| OsStr::from_bytes(name).to_owned())
+    }
+
+    fn name_bytes(&self) -> Option<&[u8]> {
+        if !self.is_active() {
+            return None;
+        }
+
+        unsafe {
+            let name = ped_partition_get_name(self.part);
+            if name.is_null() {
+                None
+            } else {
+                Some(CStr::from_ptr(name).to_bytes())
+            }
+        }
+    }
+
+    /// The name of the type of label the partition belongs to, if it belongs to a disk.
+    pub(crate) fn label_name(&self) -> Option<&str> {
+        unsafe {
+            let disk = (*self.part).disk;
+            if disk.is_null() || (*disk).type_.is_null() {
+                return None;
+            }
+
+            let name = CStr::from_ptr((*(*disk).type_).name);
+            Some(str::from_utf8_unchecked(name.to_bytes()))
         }
     }
 
@@ -171,7 +510,35 @@ impl<'a> Partition<'a> {
     /// Throws `PED_EXCEPTION_ERROR` if the requested flag is not available for this label.
     pub fn set_flag(&mut self, flag: PartitionFlag, state: bool) -> io::Result<()> {
         let state = if state { 1 } else { 0 };
-        cvt(unsafe { ped_partition_set_flag(self.part, flag, state) }).map(|_| ())
+        cvt(unsafe { ped_partition_set_flag(self.part, flag, state) })?;
+        self.mark_dirty();
+        Ok(())
+    }
+
+    /// Enables each of `flags`, checking beforehand that every flag is available, so that
+    /// either all of the flags are set or none are.
+    ///
+    /// Flags which determine the type of the partition, such as `PED_PARTITION_LVM`, are set
+    /// before flags which are attributes of that type, such as `PED_PARTITION_HIDDEN` and
+    /// `PED_PARTITION_LBA`, as some labels derive the attributes from the type. The file
+    /// system type should already be set, as `set_system()` recomputes the type.
+    pub fn set_flags(&mut self, flags: &[PartitionFlag]) -> io::Result<()> {
+        if let Some(flag) = flags.iter().find(|&&flag| !self.is_flag_available(flag)) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "the {} flag is not available on this partition",
+                    flag.name()
+                ),
+            ));
+        }
+
+        let mut ordered = flags.to_vec();
+        ordered.sort_by_key(|&flag| is_attribute_flag(flag));
+        ordered.dedup();
+        ordered
+            .into_iter()
+            .try_for_each(|flag| self.set_flag(flag, true))
     }
 
     /// Sets the name of a partition.
@@ -193,11 +560,71 @@ impl<'a> Partition<'a> {
     /// `name` will not be modified by libparted. It can be freed by the caller immediately
     /// after `Partition::set_name()` is called.
     pub fn set_name(&mut self, name: &str) -> io::Result<()> {
-        let name_cstring = CString::new(name).map_err(|err| {
+        self.set_name_os(OsStr::new(name))
+    }
+
+    /// Sets the name of a partition from bytes in the character set of the current locale.
+    ///
+    /// # Note:
+    ///
+    /// On GPT disks, names are stored as UTF-16 and may be at most 36 code units long. Names
+    /// which are too long are rejected rather than truncated, and names which libparted
+    /// cannot convert to UTF-16 are rejected with the reason that libparted gives.
+    pub fn set_name_os(&mut self, name: &OsStr) -> io::Result<()> {
+        if self.label_name() == Some("gpt") {
+            if let Some(name) = name.to_str() {
+                let units = name.encode_utf16().count();
+                if units > GPT_NAME_MAX_LEN {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!(
+                            "partition name is {} UTF-16 code units long, but GPT allows at most {}",
+                            units, GPT_NAME_MAX_LEN
+                        ),
+                    ));
+                }
+            }
+        }
+
+        let name_cstring = CString::new(name.as_bytes()).map_err(|err| {
             io::Error::new(io::ErrorKind::InvalidData, format!("Inavlid data: {}", err))
         })?;
         let name_ptr = name_cstring.as_ptr();
-        cvt(unsafe { ped_partition_set_name(self.part, name_ptr) }).map(|_| ())
+        let part = self.part;
+
+        // Conversion failures are only reported as a warning, after which the name is left
+        // unchanged or partially converted.
+        let mut warning = None;
+        let result = exception::with_handler(
+            |ex| {
+                warning = Some(ex.message.clone());
+                if ex.allows(ExceptionOption::PED_EXCEPTION_IGNORE) {
+                    ExceptionOption::PED_EXCEPTION_IGNORE
+                } else {
+                    ExceptionOption::PED_EXCEPTION_UNHANDLED
+                }
+            },
+            || cvt(unsafe { ped_partition_set_name(part, name_ptr) }),
+        );
+
+        match warning {
+            Some(message) => Err(io::Error::new(io::ErrorKind::InvalidData, message)),
+            None => result.map(|_| self.mark_dirty()),
+        }
+    }
+
+    /// Sets the name of a partition from its raw bytes, such as those returned by
+    /// `Partition::name_raw()`, so that names in encodings other than UTF-8 survive a round
+    /// trip.
+    ///
+    /// # Note:
+    ///
+    /// libparted copies the bytes into mac and pc98 labels as they are, truncated to the
+    /// length the label allows (32 bytes on mac, 16 on pc98). On GPT disks, the bytes are
+    /// converted from the character set of the current locale, as with
+    /// `Partition::set_name_os()`.
+    pub fn set_name_raw(&mut self, name: &[u8]) -> io::Result<()> {
+        self.set_name_os(OsStr::from_bytes(name))
     }
 
     /// Sets the system type on the partition to `fs_type`.
@@ -207,7 +634,139 @@ impl<'a> Partition<'a> {
     /// The file system may be opened, to get more information about the file system, such as
     /// to determine if it is FAT16 or FAT32.
     pub fn set_system(&mut self, fs_type: &FileSystemType) -> io::Result<()> {
-        cvt(unsafe { ped_partition_set_system(self.part, fs_type.fs) }).map(|_| ())
+        cvt(unsafe { ped_partition_set_system(self.part, fs_type.fs) })?;
+        self.mark_dirty();
+        Ok(())
+    }
+
+    /// Returns the partition type ID, which is the system byte of msdos partitions (ie: `0x83`
+    /// for Linux, or `0xEF` for an EFI system partition).
+    ///
+    /// # Note:
+    ///
+    /// Requires libparted 3.5 or later. Labels without type IDs, such as GPT, return an error.
+    #[cfg(feature = "parted-3-5")]
+    pub fn sys_type_id(&self) -> io::Result<u8> {
+        let get: GetTypeId = unsafe { version::function(b"ped_partition_get_type_id\0", "3.5")? };
+        self.require_feature(DiskTypeFeature::PED_DISK_TYPE_PARTITION_TYPE_ID, "type IDs")?;
+        Ok(unsafe { get(self.part) })
+    }
+
+    /// Sets the partition type ID, overriding the ID chosen from the file system type.
+    ///
+    /// # Note:
+    ///
+    /// Requires libparted 3.5 or later. Labels without type IDs, such as GPT, return an error.
+    #[cfg(feature = "parted-3-5")]
+    pub fn set_sys_type_id(&mut self, id: u8) -> io::Result<()> {
+        let set: SetTypeId = unsafe { version::function(b"ped_partition_set_type_id\0", "3.5")? };
+        self.require_feature(DiskTypeFeature::PED_DISK_TYPE_PARTITION_TYPE_ID, "type IDs")?;
+        cvt(unsafe { set(self.part, id) })?;
+        self.mark_dirty();
+        Ok(())
+    }
+
+    /// Records that the disk of the partition, if it has one, has uncommitted changes.
+    fn mark_dirty(&self) {
+        let disk = unsafe { (*self.part).disk };
+        if !disk.is_null() {
+            disk::set_dirty(disk, true);
+        }
+    }
+
+    #[cfg(feature = "parted-3-5")]
+    fn require_feature(&self, feature: DiskTypeFeature, what: &str) -> io::Result<()> {
+        let supported = unsafe {
+            let disk = (*self.part).disk;
+            !disk.is_null() && ped_disk_type_check_feature((*disk).type_, feature) != 0
+        };
+
+        if supported {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "partition {} are not supported by the {} label",
+                    what,
+                    self.label_name().unwrap_or("unknown")
+                ),
+            ))
+        }
+    }
+
+    /// Returns the partition type UUID, for labels which support them (ie: the type GUID on
+    /// GPT disks), in RFC 4122 byte order.
+    ///
+    /// # Note:
+    ///
+    /// Requires libparted 3.5 or later, and returns `None` with older releases.
+    #[cfg(feature = "parted-3-5")]
+    pub fn type_uuid(&self) -> Option<[u8; 16]> {
+        unsafe {
+            let get: GetTypeUuid =
+                version::function(b"ped_partition_get_type_uuid\0", "3.5").ok()?;
+            let uuid = get(self.part);
+            if uuid.is_null() {
+                return None;
+            }
+
+            let mut bytes = [0u8; 16];
+            ptr::copy_nonoverlapping(uuid, bytes.as_mut_ptr(), 16);
+            libc::free(uuid as *mut libc::c_void);
+            Some(bytes)
+        }
+    }
+
+    /// Sets the partition type UUID, for labels which support them.
+    ///
+    /// # Note:
+    ///
+    /// Requires libparted 3.5 or later.
+    #[cfg(feature = "parted-3-5")]
+    pub fn set_type_uuid(&mut self, uuid: &[u8; 16]) -> io::Result<()> {
+        let set: SetTypeUuid =
+            unsafe { version::function(b"ped_partition_set_type_uuid\0", "3.5")? };
+        cvt(unsafe { set(self.part, uuid.as_ptr()) })?;
+        self.mark_dirty();
+        Ok(())
+    }
+
+    /// Returns the type GUID of a GPT partition.
+    ///
+    /// # Note:
+    ///
+    /// Requires libparted 3.5 or later.
+    #[cfg(feature = "parted-3-5")]
+    pub fn type_guid(&self) -> io::Result<PartitionTypeGuid> {
+        // Checked here, as `type_uuid()` does not say why it returned `None`.
+        let _: GetTypeUuid = unsafe { version::function(b"ped_partition_get_type_uuid\0", "3.5")? };
+        self.require_feature(
+            DiskTypeFeature::PED_DISK_TYPE_PARTITION_TYPE_UUID,
+            "type GUIDs",
+        )?;
+        self.type_uuid()
+            .map(PartitionTypeGuid::from_bytes)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::Other,
+                    "libparted did not return a type GUID for the partition",
+                )
+            })
+    }
+
+    /// Sets the type GUID of a GPT partition.
+    ///
+    /// # Note:
+    ///
+    /// Requires libparted 3.5 or later.
+    #[cfg(feature = "parted-3-5")]
+    pub fn set_type_guid(&mut self, guid: PartitionTypeGuid) -> io::Result<()> {
+        self.require_feature(
+            DiskTypeFeature::PED_DISK_TYPE_PARTITION_TYPE_UUID,
+            "type GUIDs",
+        )?;
+        self.set_type_uuid(&guid.to_bytes())
     }
 
     /// Returns a name that seems mildly appropriate for a partition type `type`.
@@ -217,6 +776,82 @@ impl<'a> Partition<'a> {
             str::from_utf8_unchecked(cstr.to_bytes())
         }
     }
+
+    /// Returns the extended partition which contains this partition, if it is a logical
+    /// partition.
+    pub fn parent(&self) -> Option<Partition<'a>> {
+        if self.type_bits() & PartitionType::PED_PARTITION_LOGICAL as u32 == 0 {
+            return None;
+        }
+
+        let disk = unsafe { (*self.part).disk };
+        if disk.is_null() {
+            return None;
+        }
+
+        let extended = unsafe { ped_disk_extended_partition(disk) };
+        if extended.is_null() {
+            None
+        } else {
+            Some(unsafe { Partition::borrowed(extended) })
+        }
+    }
+
+    /// Iterates the logical partitions within this partition, if it is an extended partition.
+    ///
+    /// Free space and metadata within the extended partition are skipped.
+    pub fn children(&self) -> PartitionChildren<'a> {
+        let first = if self.type_bits() & PartitionType::PED_PARTITION_EXTENDED as u32 != 0 {
+            unsafe { (*self.part).part_list }
+        } else {
+            ptr::null_mut()
+        };
+
+        PartitionChildren(first, PhantomData)
+    }
+}
+
+/// A view of a partition which belongs to a disk, as returned by `Disk::partitions()` and
+/// `Disk::partition()`.
+///
+/// The partition is owned by the disk, and is never destroyed when the view is dropped. Only
+/// access through `&Partition` is permitted, as the disk is borrowed immutably.
+pub struct PartitionRef<'a>(pub(crate) Partition<'a>);
+
+impl<'a> PartitionRef<'a> {
+    /// # Safety
+    ///
+    /// `part` must belong to a disk which outlives `'a`.
+    pub(crate) unsafe fn from_ped_partition(part: *mut PedPartition) -> PartitionRef<'a> {
+        PartitionRef(Partition::borrowed(part))
+    }
+}
+
+impl<'a> Deref for PartitionRef<'a> {
+    type Target = Partition<'a>;
+
+    fn deref(&self) -> &Partition<'a> {
+        &self.0
+    }
+}
+
+/// Iterates the logical partitions within an extended partition, as returned by
+/// `Partition::children()`.
+pub struct PartitionChildren<'a>(*mut PedPartition, PhantomData<&'a PedPartition>);
+
+impl<'a> Iterator for PartitionChildren<'a> {
+    type Item = Partition<'a>;
+    fn next(&mut self) -> Option<Partition<'a>> {
+        while !self.0.is_null() {
+            let part = self.0;
+            self.0 = unsafe { (*part).next };
+            if unsafe { ped_partition_is_active(part) } != 0 {
+                return Some(unsafe { Partition::borrowed(part) });
+            }
+        }
+
+        None
+    }
 }
 
 impl<'a> Drop for Partition<'a> {
@@ -226,3 +861,25 @@ impl<'a> Drop for Partition<'a> {
         }
     }
 }
+
+/// The characters of the upper half of MacRoman, from `0x80` onwards.
+#[rustfmt::skip]
+const MAC_ROMAN: [char; 128] = [
+    'Ä', 'Å', 'Ç', 'É', 'Ñ', 'Ö', 'Ü', 'á', 'à', 'â', 'ä', 'ã', 'å', 'ç', 'é', 'è',
+    'ê', 'ë', 'í', 'ì', 'î', 'ï', 'ñ', 'ó', 'ò', 'ô', 'ö', 'õ', 'ú', 'ù', 'û', 'ü',
+    '†', '°', '¢', '£', '§', '•', '¶', 'ß', '®', '©', '™', '´', '¨', '≠', 'Æ', 'Ø',
+    '∞', '±', '≤', '≥', '¥', 'µ', '∂', '∑', '∏', 'π', '∫', 'ª', 'º', 'Ω', 'æ', 'ø',
+    '¿', '¡', '¬', '√', 'ƒ', '≈', '∆', '«', '»', '…', '\u{A0}', 'À', 'Ã', 'Õ', 'Œ', 'œ',
+    '–', '—', '“', '”', '‘', '’', '÷', '◊', 'ÿ', 'Ÿ', '⁄', '€', '‹', '›', 'ﬁ', 'ﬂ',
+    '‡', '·', '‚', '„', '‰', 'Â', 'Ê', 'Á', 'Ë', 'È', 'Í', 'Î', 'Ï', 'Ì', 'Ó', 'Ô',
+    '\u{F8FF}', 'Ò', 'Ú', 'Û', 'Ù', 'ı', 'ˆ', '˜', '¯', '˘', '˙', '˚', '¸', '˝', '˛', 'ˇ',
+];
+
+/// Decodes a byte of MacRoman.
+fn mac_roman_char(byte: u8) -> char {
+    if byte < 0x80 {
+        byte as char
+    } else {
+        MAC_ROMAN[byte as usize - 0x80]
+    }
+}
@@ -0,0 +1,67 @@
+//! Bridges file system probes written in Rust to libparted.
+//!
+//! libparted passes only the geometry being probed to a probe callback, so each registered
+//! probe is given its own trampoline, which finds the closure in a slot of its own.
+
+use super::Geometry;
+use libparted_sys::{ped_geometry_duplicate, PedGeometry};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+pub(crate) type Probe = Box<dyn Fn(&Geometry) -> bool + Send + Sync>;
+pub(crate) type Trampoline = unsafe extern "C" fn(*mut PedGeometry) -> *mut PedGeometry;
+
+unsafe fn run(slot: &AtomicPtr<Probe>, geom: *mut PedGeometry) -> *mut PedGeometry {
+    let probe = slot.load(Ordering::SeqCst);
+    if probe.is_null() {
+        return ptr::null_mut();
+    }
+
+    let mut geometry = Geometry::from_raw(geom);
+    geometry.is_droppable = false;
+
+    // Unwinding into libparted is undefined behavior, so a panic is treated as no match.
+    let found = panic::catch_unwind(AssertUnwindSafe(|| (*probe)(&geometry))).unwrap_or(false);
+    if found {
+        ped_geometry_duplicate(geom)
+    } else {
+        ptr::null_mut()
+    }
+}
+
+macro_rules! slots {
+    ($($slot:ident $trampoline:ident),*) => {
+        $(
+            static $slot: AtomicPtr<Probe> = AtomicPtr::new(ptr::null_mut());
+
+            unsafe extern "C" fn $trampoline(geom: *mut PedGeometry) -> *mut PedGeometry {
+                run(&$slot, geom)
+            }
+        )*
+
+        static SLOTS: &[(&AtomicPtr<Probe>, Trampoline)] = &[$((&$slot, $trampoline)),*];
+    };
+}
+
+slots!(
+    SLOT_0 probe_0, SLOT_1 probe_1, SLOT_2 probe_2, SLOT_3 probe_3,
+    SLOT_4 probe_4, SLOT_5 probe_5, SLOT_6 probe_6, SLOT_7 probe_7
+);
+
+/// Stores `probe` in a free slot, returning the trampoline which calls it, or `None` if
+/// every slot is in use.
+pub(crate) fn install(probe: Probe) -> Option<Trampoline> {
+    let probe = Box::into_raw(Box::new(probe));
+    for &(slot, trampoline) in SLOTS {
+        if slot
+            .compare_exchange(ptr::null_mut(), probe, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+        {
+            return Some(trampoline);
+        }
+    }
+
+    drop(unsafe { Box::from_raw(probe) });
+    None
+}
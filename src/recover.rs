@@ -0,0 +1,179 @@
+//! Searches for file systems left behind by lost or deleted partitions.
+//!
+//! Candidate start sectors are probed with libparted's file system probes. `scan()` and
+//! `scan_disk()` probe at intervals across a whole region, to find file systems whose location
+//! is unknown, while `rescue()` probes every sector near a remembered start and accepts only
+//! file systems which end near a remembered end, as parted's `rescue` command does.
+//!
+//! Each file system which is found is reported along with the region it occupies, so that it
+//! may be re-added to the partition table with `Disk::add_partition()` or
+//! `Disk::create_partition()`.
+
+use super::{round_up_to, Device, Disk, Geometry, PartitionSpec, Sector, Timer};
+use std::io;
+
+/// A file system which was found by a scan.
+pub struct Candidate<'a> {
+    /// The name of the file system type which was detected.
+    pub fs_type: String,
+    /// The region occupied by the file system.
+    pub geometry: Geometry<'a>,
+}
+
+impl<'a> Candidate<'a> {
+    /// A spec which recreates a partition occupying exactly this candidate's region.
+    pub fn to_spec(&self) -> PartitionSpec {
+        PartitionSpec::with_sectors(self.geometry.start(), self.geometry.end())
+            .fs_type(self.fs_type.as_str())
+    }
+}
+
+/// Scans `range` on `device` for file systems, probing at every multiple of the device's
+/// optimum alignment grain (or every 2048 sectors, if the device does not report one).
+///
+/// If `timer` is not `None`, it will be used as the progress meter.
+pub fn scan<'a>(
+    device: &Device,
+    range: &Geometry,
+    timer: Option<&mut Timer>,
+) -> io::Result<Vec<Candidate<'a>>> {
+    let granularity = device
+        .get_optimum_alignment()
+        .map_or(2048, |alignment| alignment.grain_size())
+        .max(1);
+    scan_with_granularity(device, range, granularity, timer)
+}
+
+/// Scans `range` on `device` for file systems, probing for one at every multiple of
+/// `granularity` sectors.
+///
+/// A smaller granularity finds file systems which were not aligned, such as those created
+/// with the legacy 63-sector offset, at the cost of a much slower scan.
+pub fn scan_with_granularity<'a>(
+    device: &Device,
+    range: &Geometry,
+    granularity: i64,
+    mut timer: Option<&mut Timer>,
+) -> io::Result<Vec<Candidate<'a>>> {
+    if granularity < 1 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "granularity must be at least one sector",
+        ));
+    }
+
+    if let Some(timer) = timer.as_mut() {
+        timer.reset();
+    }
+
+    let mut candidates = Vec::new();
+    let mut sector = round_up_to(range.start(), granularity);
+    while sector <= range.end() {
+        if let Some(timer) = timer.as_mut() {
+            timer.update((sector - range.start()) as f32 / range.length() as f32);
+        }
+
+        match probe(device, sector, range.end())? {
+            Some((fs_type, start, length)) => {
+                candidates.push(Candidate {
+                    fs_type,
+                    geometry: Geometry::new(device, Sector(start), Sector(length))?,
+                });
+                sector = round_up_to(start + length, granularity).max(sector + granularity);
+            }
+            None => sector += granularity,
+        }
+    }
+
+    if let Some(timer) = timer {
+        timer.update(1.0);
+    }
+
+    Ok(candidates)
+}
+
+/// Searches for a file system which starts within `start` and ends within `end`, as parted's
+/// `rescue` command does, such as to recover a partition whose boundaries were noted down
+/// before it was deleted.
+///
+/// Every sector of `start` is probed, so the ranges should only span the uncertainty in the
+/// remembered boundaries (ie: half a unit either side of a start given in MB). File systems
+/// which are found but end outside of `end` are ignored.
+///
+/// If `timer` is not `None`, it will be used as the progress meter.
+pub fn rescue<'a>(
+    device: &Device,
+    start: &Geometry,
+    end: &Geometry,
+    mut timer: Option<&mut Timer>,
+) -> io::Result<Vec<Candidate<'a>>> {
+    if start.start() > end.end() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "the start range lies after the end range",
+        ));
+    }
+
+    if let Some(timer) = timer.as_mut() {
+        timer.reset();
+    }
+
+    let mut candidates = Vec::new();
+    for sector in start.start()..=start.end().min(end.end()) {
+        if let Some(timer) = timer.as_mut() {
+            timer.update((sector - start.start()) as f32 / start.length() as f32);
+        }
+
+        if let Some((fs_type, found, length)) = probe(device, sector, end.end())? {
+            let last = found + length - 1;
+            if last >= end.start() && last <= end.end() {
+                candidates.push(Candidate {
+                    fs_type,
+                    geometry: Geometry::new(device, Sector(found), Sector(length))?,
+                });
+            }
+        }
+    }
+
+    if let Some(timer) = timer {
+        timer.update(1.0);
+    }
+
+    Ok(candidates)
+}
+
+/// Probes for a file system which starts at `sector` and ends no later than `last`, returning
+/// its type, start, and length.
+fn probe(device: &Device, sector: i64, last: i64) -> io::Result<Option<(String, i64, i64)>> {
+    let region = Geometry::new(device, Sector(sector), Sector(last - sector + 1))?;
+    Ok(match region.probe_fs() {
+        Ok(fs_type) => region
+            .probe_specific_fs(&fs_type)
+            .map(|geom| (fs_type.name().to_owned(), geom.start(), geom.length())),
+        Err(_) => None,
+    })
+}
+
+/// Scans each region of free space on `disk` for file systems, using the same granularity
+/// as `scan()`.
+pub fn scan_disk<'a>(disk: &Disk, mut timer: Option<&mut Timer>) -> io::Result<Vec<Candidate<'a>>> {
+    let device = disk.raw_device();
+    let regions = disk.free_regions()?;
+
+    let total = regions.iter().map(|region| region.length()).sum::<i64>();
+    if let Some(timer) = timer.as_mut() {
+        timer.reset();
+    }
+
+    let mut candidates = Vec::new();
+    let mut scanned = 0;
+    for range in regions {
+        candidates.extend(scan(&device, &range, None)?);
+        scanned += range.length();
+        if let Some(timer) = timer.as_mut() {
+            timer.update(scanned as f32 / total as f32);
+        }
+    }
+
+    Ok(candidates)
+}
@@ -0,0 +1,128 @@
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+use std::process::{Command, ExitStatus};
+
+type ResizeFn = Box<dyn Fn(&Path, u64) -> Result<()>>;
+
+/// Resizes file systems with external tools, for use with `Disk::resize_partition_and_fs()`.
+///
+/// Each file system type, as named by libparted, is resized by a closure which is given the
+/// path of the partition and the size in bytes that its file system should be resized to.
+///
+/// ```rust,no_run
+/// # use libparted::FsResizer;
+/// let resizer = FsResizer::with_defaults()
+///     .command("btrfs", "btrfs-resize-helper", &["{path}", "{size}"]);
+/// ```
+#[derive(Default)]
+pub struct FsResizer {
+    resizers: Vec<(String, ResizeFn)>,
+}
+
+impl FsResizer {
+    /// Creates a registry without any resizers.
+    pub fn new() -> FsResizer {
+        FsResizer::default()
+    }
+
+    /// Creates a registry which resizes ext2, ext3, and ext4 with `resize2fs`, and NTFS with
+    /// `ntfsresize`.
+    ///
+    /// ext file systems are checked with `e2fsck -f -p` first, as `resize2fs` refuses to
+    /// resize one which has not been checked since it was last mounted. The resize fails if
+    /// the check finds errors which it cannot fix by itself.
+    ///
+    /// btrfs is not included, as it may only be resized while mounted.
+    pub fn with_defaults() -> FsResizer {
+        let ntfs = &["--force", "--no-progress-bar", "--size", "{size}", "{path}"];
+        FsResizer::new()
+            .register("ext2", resize_ext)
+            .register("ext3", resize_ext)
+            .register("ext4", resize_ext)
+            .command("ntfs", "ntfsresize", ntfs)
+    }
+
+    /// Resizes file systems of `fs_type` with `resize`, which is given the path of the
+    /// partition and the new size of the file system in bytes. Replaces any resizer which was
+    /// previously registered for the type.
+    pub fn register<F>(mut self, fs_type: &str, resize: F) -> FsResizer
+    where
+        F: Fn(&Path, u64) -> Result<()> + 'static,
+    {
+        self.resizers.retain(|(name, _)| name != fs_type);
+        self.resizers.push((fs_type.to_owned(), Box::new(resize)));
+        self
+    }
+
+    /// Resizes file systems of `fs_type` by running `program` with `args`, in which `{path}`
+    /// is replaced by the path of the partition, `{size}` by the new size in bytes, and `{kib}`
+    /// by the new size in KiB, rounded down.
+    pub fn command(self, fs_type: &str, program: &str, args: &[&str]) -> FsResizer {
+        let program = program.to_owned();
+        let args = args.iter().map(|&arg| arg.to_owned()).collect::<Vec<_>>();
+        self.register(fs_type, move |path, size| {
+            let path = path.to_string_lossy();
+            let status = Command::new(&program)
+                .args(args.iter().map(|arg| {
+                    arg.replace("{path}", &path)
+                        .replace("{size}", &size.to_string())
+                        .replace("{kib}", &(size / 1024).to_string())
+                }))
+                .status()?;
+
+            if status.success() {
+                Ok(())
+            } else {
+                Err(failed(&program, status))
+            }
+        })
+    }
+
+    /// Whether a resizer is registered for `fs_type`.
+    pub fn supports(&self, fs_type: &str) -> bool {
+        self.resizers.iter().any(|(name, _)| name == fs_type)
+    }
+
+    /// Resizes the `fs_type` file system on the partition at `path` to `size` bytes.
+    pub(crate) fn resize(&self, fs_type: &str, path: &Path, size: u64) -> Result<()> {
+        match self.resizers.iter().find(|(name, _)| name == fs_type) {
+            Some((_, resize)) => resize(path, size),
+            None => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("no resizer is registered for {} file systems", fs_type),
+            )),
+        }
+    }
+}
+
+/// Checks the ext file system at `path` with `e2fsck -f -p`, then resizes it to `size` bytes
+/// with `resize2fs`.
+fn resize_ext(path: &Path, size: u64) -> Result<()> {
+    let status = Command::new("e2fsck")
+        .arg("-f")
+        .arg("-p")
+        .arg(path)
+        .status()?;
+
+    // e2fsck exits with 1 or 2 when it has corrected errors, and 4 or above when errors remain
+    // or the check could not be completed.
+    match status.code() {
+        Some(0) | Some(1) | Some(2) => (),
+        _ => return Err(failed("e2fsck", status)),
+    }
+
+    let status = Command::new("resize2fs")
+        .arg(path)
+        .arg(format!("{}K", size / 1024))
+        .status()?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(failed("resize2fs", status))
+    }
+}
+
+fn failed(program: &str, status: ExitStatus) -> Error {
+    Error::new(ErrorKind::Other, format!("{} failed: {}", program, status))
+}
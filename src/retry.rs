@@ -0,0 +1,131 @@
+use std::error::Error as StdError;
+use std::fmt::{self, Display, Formatter};
+use std::io::{Error, Result};
+use std::thread;
+use std::time::Duration;
+
+/// How an operation which fails because the device is busy should be retried.
+///
+/// Devices are frequently busy for a moment after a commit, while udev processes the events
+/// which the commit caused. Each retry waits `delay`, multiplied by `backoff` after every
+/// attempt, up to `max_delay`.
+///
+/// ```rust,no_run
+/// # use libparted::{Device, Disk, RetryPolicy};
+/// # use std::time::Duration;
+/// let policy = RetryPolicy::new()
+///     .attempts(10)
+///     .delay(Duration::from_millis(100))
+///     .backoff(2)
+///     .max_delay(Duration::from_secs(2));
+///
+/// let device = Device::new("/dev/sda").unwrap();
+/// let mut disk = Disk::new(&device).unwrap();
+/// disk.commit_with_retry(&policy).unwrap();
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    attempts: u32,
+    delay: Duration,
+    max_delay: Duration,
+    backoff: u32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            attempts: 5,
+            delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(2),
+            backoff: 2,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Makes up to 5 attempts, waiting 200ms before the first retry and doubling the delay
+    /// after each one, up to 2 seconds.
+    pub fn new() -> RetryPolicy {
+        RetryPolicy::default()
+    }
+
+    /// The total number of attempts to make, including the first.
+    pub fn attempts(mut self, attempts: u32) -> RetryPolicy {
+        self.attempts = attempts.max(1);
+        self
+    }
+
+    /// How long to wait before the first retry.
+    pub fn delay(mut self, delay: Duration) -> RetryPolicy {
+        self.delay = delay;
+        self
+    }
+
+    /// The longest to wait between any two attempts.
+    pub fn max_delay(mut self, max_delay: Duration) -> RetryPolicy {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// The factor the delay is multiplied by after each retry.
+    pub fn backoff(mut self, backoff: u32) -> RetryPolicy {
+        self.backoff = backoff.max(1);
+        self
+    }
+
+    /// Runs `func` until it succeeds, fails with an error other than `EBUSY`, or the
+    /// attempts are exhausted.
+    ///
+    /// When every attempt fails, the error is a **RetryError** wrapping the last failure.
+    pub(crate) fn run<T, F: FnMut() -> Result<T>>(&self, mut func: F) -> Result<T> {
+        let mut delay = self.delay;
+        let mut attempt = 1;
+        loop {
+            match func() {
+                Err(ref why) if is_busy(why) && attempt < self.attempts => {
+                    thread::sleep(delay);
+                    delay = (delay * self.backoff).min(self.max_delay);
+                    attempt += 1;
+                }
+                Err(why) if attempt > 1 => {
+                    return Err(Error::new(
+                        why.kind(),
+                        RetryError {
+                            attempts: attempt,
+                            last: why,
+                        },
+                    ))
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+fn is_busy(why: &Error) -> bool {
+    why.raw_os_error() == Some(libc::EBUSY)
+}
+
+/// The error of an operation which was retried by a **RetryPolicy**, and failed on every
+/// attempt.
+///
+/// It is the inner error of the `io::Error` returned by the operation.
+#[derive(Debug)]
+pub struct RetryError {
+    /// The number of attempts which were made.
+    pub attempts: u32,
+    /// The error of the final attempt.
+    pub last: Error,
+}
+
+impl Display for RetryError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{} (after {} attempts)", self.last, self.attempts)
+    }
+}
+
+impl StdError for RetryError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(&self.last)
+    }
+}
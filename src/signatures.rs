@@ -0,0 +1,450 @@
+//! Identifies containers and file systems which libparted is unable to probe, by reading their
+//! magic numbers directly from a region.
+//!
+//! Installers should warn before overwriting a region with any of these signatures, as
+//! `Geometry::probe_fs()` will report it as empty.
+
+use super::SectorIo;
+use std::fmt::{self, Display, Formatter};
+use std::io;
+
+/// A container or file system found by `scan()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Signature {
+    /// A LUKS encrypted volume, of either version.
+    Luks,
+    /// An LVM2 physical volume.
+    LvmPv,
+    /// A member of a Linux software RAID array, with any superblock version.
+    MdRaid,
+    Bcachefs,
+    /// A member of a ZFS pool.
+    Zfs,
+}
+
+impl Signature {
+    /// The name `blkid` uses for the signature's type.
+    pub fn name(self) -> &'static str {
+        match self {
+            Signature::Luks => "crypto_LUKS",
+            Signature::LvmPv => "LVM2_member",
+            Signature::MdRaid => "linux_raid_member",
+            Signature::Bcachefs => "bcachefs",
+            Signature::Zfs => "zfs_member",
+        }
+    }
+}
+
+impl Display for Signature {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Where a magic number is found, given the length of the region in bytes.
+type Locate = fn(u64) -> Option<u64>;
+
+/// A magic number which identifies a signature, or a file system which libparted already
+/// recognizes when `signature` is `None`.
+pub(crate) struct Magic {
+    pub(crate) signature: Option<Signature>,
+    pub(crate) locate: Locate,
+    pub(crate) bytes: &'static [u8],
+}
+
+const LUKS_MAGIC: &[u8] = b"LUKS\xba\xbe";
+const MD_MAGIC: &[u8] = &[0xfc, 0x4e, 0x2b, 0xa9];
+const BCACHEFS_MAGIC: &[u8] = &[
+    0xc6, 0x85, 0x73, 0xf6, 0x66, 0xce, 0x90, 0xa9, 0xd9, 0x6a, 0x60, 0xcf, 0x80, 0x3d, 0xf7, 0xef,
+];
+const BCACHE_MAGIC: &[u8] = &[
+    0xc6, 0x85, 0x73, 0xf6, 0x4e, 0x1a, 0x45, 0xca, 0x82, 0x65, 0xf5, 0x7f, 0x48, 0xba, 0x6d, 0x81,
+];
+
+/// The offset of the bcachefs superblock, and of the magic number within it.
+const BCACHEFS_SB: u64 = 4096;
+const BCACHEFS_MAGIC_OFFSET: u64 = 24;
+
+/// The first superblock version of bcachefs, which initially shared its magic with bcache.
+const BCACHEFS_MIN_VERSION: u16 = 9;
+
+/// The size of a ZFS vdev label.
+const ZFS_LABEL_SIZE: u64 = 256 * 1024;
+
+/// The offset of the uberblock array within a ZFS vdev label, and the size of its slots.
+const ZFS_UBERBLOCKS: u64 = 128 * 1024;
+const ZFS_UBERBLOCK_SIZE: u64 = 1024;
+const ZFS_UBERBLOCK_SLOTS: u64 = 128;
+const ZFS_MAGIC: u64 = 0x00ba_b10c;
+
+pub(crate) static MAGICS: &[Magic] = &[
+    Magic {
+        signature: Some(Signature::Luks),
+        locate: |_| Some(0),
+        bytes: LUKS_MAGIC,
+    },
+    // The secondary LUKS2 header, which remains if only the first was overwritten.
+    Magic {
+        signature: Some(Signature::Luks),
+        locate: |_| Some(0x4000),
+        bytes: b"SKUL\xba\xbe",
+    },
+    // Versions 0.90 and 1.0 are stored near the end of the device, and 1.1 and 1.2 at the
+    // start.
+    Magic {
+        signature: Some(Signature::MdRaid),
+        locate: |len| (len & !0xffff).checked_sub(0x10000),
+        bytes: MD_MAGIC,
+    },
+    Magic {
+        signature: Some(Signature::MdRaid),
+        locate: |len| len.checked_sub(0x2000).map(|offset| offset & !0xfff),
+        bytes: MD_MAGIC,
+    },
+    Magic {
+        signature: Some(Signature::MdRaid),
+        locate: |_| Some(0),
+        bytes: MD_MAGIC,
+    },
+    Magic {
+        signature: Some(Signature::MdRaid),
+        locate: |_| Some(0x1000),
+        bytes: MD_MAGIC,
+    },
+    Magic {
+        signature: Some(Signature::Bcachefs),
+        locate: |_| Some(BCACHEFS_SB + BCACHEFS_MAGIC_OFFSET),
+        bytes: BCACHEFS_MAGIC,
+    },
+    Magic {
+        signature: None,
+        locate: |_| Some(1080),
+        bytes: &[0x53, 0xef],
+    },
+    Magic {
+        signature: None,
+        locate: |_| Some(0),
+        bytes: b"XFSB",
+    },
+    Magic {
+        signature: None,
+        locate: |_| Some(0x10040),
+        bytes: b"_BHRfS_M",
+    },
+    Magic {
+        signature: None,
+        locate: |_| Some(1024),
+        bytes: &[0x10, 0x20, 0xf5, 0xf2],
+    },
+    Magic {
+        signature: None,
+        locate: |_| Some(3),
+        bytes: b"NTFS    ",
+    },
+    Magic {
+        signature: None,
+        locate: |_| Some(82),
+        bytes: b"FAT32   ",
+    },
+    Magic {
+        signature: None,
+        locate: |_| Some(54),
+        bytes: b"FAT16   ",
+    },
+    Magic {
+        signature: None,
+        locate: |_| Some(54),
+        bytes: b"FAT12   ",
+    },
+    // Swap is signed at the end of its first page, which depends on the page size.
+    Magic {
+        signature: None,
+        locate: |_| Some(4096 - 10),
+        bytes: b"SWAPSPACE2",
+    },
+    Magic {
+        signature: None,
+        locate: |_| Some(65536 - 10),
+        bytes: b"SWAPSPACE2",
+    },
+    Magic {
+        signature: None,
+        locate: |_| Some(32769),
+        bytes: b"CD001",
+    },
+];
+
+/// Reads `len` bytes starting `offset` bytes into `geom`, or `None` if they are beyond its
+/// end.
+pub(crate) fn read_bytes<T: SectorIo>(
+    geom: &T,
+    offset: u64,
+    len: u64,
+) -> io::Result<Option<Vec<u8>>> {
+    let sector_size = geom.sector_size();
+    let total = geom.length() as u64 * sector_size;
+    match offset.checked_add(len) {
+        Some(end) if end <= total => (),
+        _ => return Ok(None),
+    }
+
+    let first = offset / sector_size;
+    let count = (offset + len - 1) / sector_size - first + 1;
+    let mut data = vec![0; (count * sector_size) as usize];
+    geom.read_sectors(&mut data, first as i64, count as i64)?;
+
+    let start = (offset - first * sector_size) as usize;
+    Ok(Some(data[start..start + len as usize].to_vec()))
+}
+
+pub(crate) fn le_u16(bytes: &[u8]) -> u16 {
+    u16::from(bytes[0]) | u16::from(bytes[1]) << 8
+}
+
+pub(crate) fn le_u32(bytes: &[u8]) -> u32 {
+    let mut value = [0; 4];
+    value.copy_from_slice(&bytes[..4]);
+    u32::from_le_bytes(value)
+}
+
+pub(crate) fn le_u64(bytes: &[u8]) -> u64 {
+    let mut value = [0; 8];
+    value.copy_from_slice(&bytes[..8]);
+    u64::from_le_bytes(value)
+}
+
+fn matches<T: SectorIo>(geom: &T, offset: u64, bytes: &[u8]) -> io::Result<bool> {
+    Ok(read_bytes(geom, offset, bytes.len() as u64)?.map_or(false, |found| found == bytes))
+}
+
+/// Scans the region for the signatures of containers and file systems which libparted does
+/// not recognize, returning each that is found.
+///
+/// More than one signature may be found when an old one was not erased.
+pub fn scan<T: SectorIo>(geom: &T) -> io::Result<Vec<Signature>> {
+    let len = geom.length() as u64 * geom.sector_size();
+    let mut found = Vec::new();
+    let mut push = |signature| {
+        if !found.contains(&signature) {
+            found.push(signature);
+        }
+    };
+
+    for magic in MAGICS {
+        if let (Some(signature), Some(offset)) = (magic.signature, (magic.locate)(len)) {
+            if matches(geom, offset, magic.bytes)? {
+                push(signature);
+            }
+        }
+    }
+
+    if is_bcachefs_with_bcache_magic(geom)? {
+        push(Signature::Bcachefs);
+    }
+
+    if is_lvm_pv(geom)? {
+        push(Signature::LvmPv);
+    }
+
+    if is_zfs(geom)? {
+        push(Signature::Zfs);
+    }
+
+    Ok(found)
+}
+
+/// Early versions of bcachefs used the magic of bcache, and are told apart by their version.
+fn is_bcachefs_with_bcache_magic<T: SectorIo>(geom: &T) -> io::Result<bool> {
+    if !matches(geom, BCACHEFS_SB + BCACHEFS_MAGIC_OFFSET, BCACHE_MAGIC)? {
+        return Ok(false);
+    }
+
+    Ok(read_bytes(geom, BCACHEFS_SB + 16, 2)?
+        .map_or(false, |version| le_u16(&version) >= BCACHEFS_MIN_VERSION))
+}
+
+/// The LVM2 label may be in any of the first four 512-byte sectors.
+fn is_lvm_pv<T: SectorIo>(geom: &T) -> io::Result<bool> {
+    for sector in 0..4 {
+        let label = match read_bytes(geom, sector * 512, 32)? {
+            Some(label) => label,
+            None => return Ok(false),
+        };
+
+        if &label[..8] == b"LABELONE" && &label[24..32] == b"LVM2 001" {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Erases every known signature which is present in the region, by zeroing its magic number,
+/// and returns the offset of each that was erased, in bytes.
+///
+/// Only the magic numbers are overwritten, as `wipefs` does, so the rest of the data is left
+/// intact.
+pub(crate) fn wipe<T: SectorIo>(geom: &mut T) -> io::Result<Vec<u64>> {
+    let len = geom.length() as u64 * geom.sector_size();
+    let mut erased = Vec::new();
+
+    for magic in MAGICS {
+        if let Some(offset) = (magic.locate)(len) {
+            if matches(geom, offset, magic.bytes)? {
+                erase(geom, offset, magic.bytes.len() as u64)?;
+                erased.push(offset);
+            }
+        }
+    }
+
+    for sector in 0..4 {
+        if matches(geom, sector * 512, b"LABELONE")? {
+            erase(geom, sector * 512, 8)?;
+            erased.push(sector * 512);
+        }
+    }
+
+    // Each of the labels at the start of a ZFS vdev holds a copy of the uberblocks.
+    for &label in &[0, ZFS_LABEL_SIZE] {
+        for slot in 0..ZFS_UBERBLOCK_SLOTS {
+            let offset = label + ZFS_UBERBLOCKS + slot * ZFS_UBERBLOCK_SIZE;
+            let magic = match read_bytes(geom, offset, 8)? {
+                Some(magic) => magic,
+                None => break,
+            };
+
+            if is_zfs_magic(&magic) {
+                erase(geom, offset, 8)?;
+                erased.push(offset);
+            }
+        }
+    }
+
+    geom.sync()?;
+    Ok(erased)
+}
+
+/// Zeroes `len` bytes starting `offset` bytes into `geom`.
+fn erase<T: SectorIo>(geom: &mut T, offset: u64, len: u64) -> io::Result<()> {
+    let sector_size = geom.sector_size();
+    let first = offset / sector_size;
+    let count = (offset + len - 1) / sector_size - first + 1;
+    let mut data = vec![0; (count * sector_size) as usize];
+    geom.read_sectors(&mut data, first as i64, count as i64)?;
+
+    let start = (offset - first * sector_size) as usize;
+    data[start..start + len as usize]
+        .iter_mut()
+        .for_each(|byte| *byte = 0);
+    geom.write_sectors(&data, first as i64, count as i64)
+}
+
+fn is_zfs_magic(bytes: &[u8]) -> bool {
+    let mut magic = [0; 8];
+    magic.copy_from_slice(&bytes[..8]);
+    u64::from_le_bytes(magic) == ZFS_MAGIC || u64::from_be_bytes(magic) == ZFS_MAGIC
+}
+
+/// Searches the uberblocks of the first vdev label for the uberblock magic, in either byte
+/// order.
+fn is_zfs<T: SectorIo>(geom: &T) -> io::Result<bool> {
+    let uberblocks = match read_bytes(
+        geom,
+        ZFS_UBERBLOCKS,
+        ZFS_UBERBLOCK_SIZE * ZFS_UBERBLOCK_SLOTS,
+    )? {
+        Some(uberblocks) => uberblocks,
+        None => return Ok(false),
+    };
+
+    Ok(uberblocks
+        .chunks(ZFS_UBERBLOCK_SIZE as usize)
+        .any(is_zfs_magic))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryDevice;
+
+    const LEN: u64 = 8 * 1024 * 1024;
+
+    fn put(data: &mut [u8], offset: u64, bytes: &[u8]) {
+        let offset = offset as usize;
+        data[offset..offset + bytes.len()].copy_from_slice(bytes);
+    }
+
+    fn signed_device(sector_size: u64) -> MemoryDevice {
+        let mut data = vec![0; LEN as usize];
+        put(&mut data, 0, LUKS_MAGIC);
+        put(
+            &mut data,
+            512,
+            b"LABELONE\x01\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0LVM2 001",
+        );
+        put(&mut data, 1080, &[0x53, 0xef, 0x77]);
+        put(&mut data, LEN - 0x10000, MD_MAGIC);
+        put(&mut data, 4096 - 10, b"SWAPSPACE2");
+        put(
+            &mut data,
+            ZFS_UBERBLOCKS + 3 * ZFS_UBERBLOCK_SIZE,
+            &ZFS_MAGIC.to_le_bytes(),
+        );
+        MemoryDevice::from_bytes(data, sector_size).unwrap()
+    }
+
+    fn wipes_every_signature(sector_size: u64) {
+        let mut device = signed_device(sector_size);
+        assert_eq!(
+            scan(&device).unwrap(),
+            vec![
+                Signature::Luks,
+                Signature::MdRaid,
+                Signature::LvmPv,
+                Signature::Zfs
+            ]
+        );
+
+        let mut erased = wipe(&mut device).unwrap();
+        erased.sort();
+        assert_eq!(
+            erased,
+            vec![
+                0,
+                512,
+                1080,
+                4096 - 10,
+                ZFS_UBERBLOCKS + 3 * ZFS_UBERBLOCK_SIZE,
+                LEN - 0x10000
+            ]
+        );
+
+        assert!(scan(&device).unwrap().is_empty());
+        assert!(wipe(&mut device).unwrap().is_empty());
+
+        // Only the magic numbers are erased.
+        let data = device.as_bytes();
+        assert_eq!(&data[1080..1083], &[0, 0, 0x77]);
+        assert_eq!(&data[520..521], b"\x01");
+        assert_eq!(&data[536..544], b"LVM2 001");
+    }
+
+    #[test]
+    fn wipes_device_with_512_byte_sectors() {
+        wipes_every_signature(512);
+    }
+
+    #[test]
+    fn wipes_device_with_4096_byte_sectors() {
+        wipes_every_signature(4096);
+    }
+
+    #[test]
+    fn ignores_signatures_beyond_the_region() {
+        let mut data = vec![0; 2048];
+        put(&mut data, 0, MD_MAGIC);
+        let mut device = MemoryDevice::from_bytes(data, 512).unwrap();
+        assert_eq!(scan(&device).unwrap(), vec![Signature::MdRaid]);
+        assert_eq!(wipe(&mut device).unwrap(), vec![0]);
+    }
+}
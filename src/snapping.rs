@@ -0,0 +1,271 @@
+//! Snapping of partition boundaries to those of neighbouring partitions and free space.
+//!
+//! This follows `snap_to_boundaries()` in parted, but works on plain **GeomView** ranges
+//! rather than libparted geometries, so that it may be tested without a device.
+
+use super::{GeomView, MOVE_DOWN, MOVE_STILL, MOVE_UP, SECT_END, SECT_START};
+
+/// Moves `sector` to `new_sector` if it lies within `range`, returning whether it moved.
+pub(crate) fn snap_view(sector: &mut i64, new_sector: i64, range: GeomView) -> bool {
+    debug_assert!(range.contains_sector(*sector));
+    if !range.contains_sector(new_sector) {
+        return false;
+    }
+    *sector = new_sector;
+    true
+}
+
+pub(crate) fn prefer_snap_view(
+    s: i64,
+    what: i32,
+    range: GeomView,
+    allow: &mut u8,
+    part: GeomView,
+    dist: &mut i64,
+) -> u8 {
+    let (mut up_dist, mut down_dist) = (-1i64, -1i64);
+    let mut moves;
+
+    debug_assert!(what == SECT_START || what == SECT_END);
+
+    if *allow & (MOVE_UP | MOVE_DOWN) == 0 {
+        *dist = 0;
+        return MOVE_STILL;
+    }
+
+    if *allow & MOVE_UP != 0 {
+        let new_sect = part.end + 1 + what as i64;
+        if range.contains_sector(new_sect) {
+            up_dist = new_sect - s;
+        } else {
+            *allow &= !MOVE_UP;
+        }
+    }
+
+    if *allow & MOVE_DOWN != 0 {
+        let new_sect = part.start + what as i64;
+        if range.contains_sector(new_sect) {
+            down_dist = s - new_sect;
+        } else {
+            *allow &= !MOVE_DOWN;
+        }
+    }
+
+    moves = MOVE_STILL;
+    if *allow & MOVE_UP != 0 && *allow & MOVE_DOWN != 0 {
+        if down_dist < up_dist || (down_dist == up_dist && what == SECT_START) {
+            moves = MOVE_DOWN;
+        } else if up_dist < down_dist || (down_dist == up_dist && what == SECT_END) {
+            moves = MOVE_UP;
+        } else {
+            unreachable!();
+        }
+    } else if *allow & MOVE_UP != 0 {
+        moves = MOVE_UP;
+    } else if *allow & MOVE_DOWN != 0 {
+        moves = MOVE_DOWN;
+    }
+
+    *dist = if moves == MOVE_DOWN {
+        down_dist
+    } else if moves == MOVE_UP {
+        up_dist
+    } else {
+        0
+    };
+
+    moves
+}
+
+/// Snaps the start and end of `new` to the boundaries of `start_geom` and `end_geom`, the
+/// partitions or free regions holding them, without leaving `start_range` and `end_range`.
+///
+/// Boundaries are kept where they were in `old`, if given, so long as that is within range.
+pub(crate) fn snap_bounds(
+    new: GeomView,
+    old: Option<GeomView>,
+    start_range: GeomView,
+    end_range: GeomView,
+    start_geom: Option<GeomView>,
+    end_geom: Option<GeomView>,
+) -> (i64, i64) {
+    let (mut start_dist, mut end_dist) = (-1, -1);
+    let mut start = new.start;
+    let mut end = new.end;
+
+    let mut start_allow = match start_geom {
+        Some(_) => MOVE_STILL | MOVE_UP | MOVE_DOWN,
+        None => MOVE_STILL,
+    };
+    let mut end_allow = match end_geom {
+        Some(_) => MOVE_STILL | MOVE_UP | MOVE_DOWN,
+        None => MOVE_STILL,
+    };
+
+    let adjacent = match (start_geom, end_geom) {
+        (Some(start_geom), Some(end_geom)) => start_geom.end + 1 == end_geom.start,
+        _ => false,
+    };
+
+    if let Some(old) = old {
+        if snap_view(&mut start, old.start, start_range) {
+            start_allow = MOVE_STILL;
+        }
+
+        if snap_view(&mut end, old.end, end_range) {
+            end_allow = MOVE_STILL;
+        }
+    }
+
+    if start_geom.is_some() && start_geom == end_geom {
+        start_allow &= !MOVE_UP;
+        end_allow &= !MOVE_DOWN;
+    }
+
+    let unused = GeomView {
+        start: 0,
+        end: 0,
+        length: 0,
+    };
+    let start_geom = start_geom.unwrap_or(unused);
+    let end_geom = end_geom.unwrap_or(unused);
+
+    let mut start_want = prefer_snap_view(
+        start,
+        SECT_START,
+        start_range,
+        &mut start_allow,
+        start_geom,
+        &mut start_dist,
+    );
+
+    let mut end_want = prefer_snap_view(
+        end,
+        SECT_END,
+        end_range,
+        &mut end_allow,
+        end_geom,
+        &mut end_dist,
+    );
+
+    debug_assert!(start_dist >= 0 && end_dist >= 0);
+
+    if adjacent && start_want == MOVE_UP && end_want == MOVE_DOWN {
+        if end_dist < start_dist {
+            start_allow &= !MOVE_UP;
+            start_want = prefer_snap_view(
+                start,
+                SECT_START,
+                start_range,
+                &mut start_allow,
+                start_geom,
+                &mut start_dist,
+            );
+            debug_assert!(start_dist >= 0);
+        } else {
+            end_allow &= !MOVE_DOWN;
+            end_want = prefer_snap_view(
+                end,
+                SECT_END,
+                end_range,
+                &mut end_allow,
+                end_geom,
+                &mut end_dist,
+            );
+            debug_assert!(end_dist >= 0);
+        }
+    }
+
+    start = match start_want {
+        MOVE_DOWN => start_geom.start,
+        MOVE_UP => start_geom.end + 1,
+        _ => start,
+    };
+
+    end = match end_want {
+        MOVE_DOWN => end_geom.start - 1,
+        MOVE_UP => end_geom.end,
+        _ => end,
+    };
+
+    debug_assert!(start_range.contains_sector(start));
+    debug_assert!(end_range.contains_sector(end));
+    debug_assert!(start <= end);
+    (start, end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn view(start: i64, end: i64) -> GeomView {
+        GeomView {
+            start,
+            end,
+            length: end - start + 1,
+        }
+    }
+
+    #[test]
+    fn snaps_to_the_free_region_holding_both_ends() {
+        let free = view(4096, 8191);
+        let snapped = snap_bounds(
+            view(4100, 8190),
+            None,
+            view(4000, 4200),
+            view(8100, 8300),
+            Some(free),
+            Some(free),
+        );
+        assert_eq!(snapped, (4096, 8191));
+    }
+
+    #[test]
+    fn keeps_the_old_boundaries_within_range() {
+        let free = view(4096, 8191);
+        let snapped = snap_bounds(
+            view(4110, 8180),
+            Some(view(4100, 8190)),
+            view(4000, 4200),
+            view(8100, 8300),
+            Some(free),
+            Some(free),
+        );
+        assert_eq!(snapped, (4100, 8190));
+    }
+
+    #[test]
+    fn adjacent_regions_move_the_nearest_boundary() {
+        let snapped = snap_bounds(
+            view(4000, 4100),
+            None,
+            view(3900, 4200),
+            view(3900, 4200),
+            Some(view(2048, 4095)),
+            Some(view(4096, 8191)),
+        );
+        assert_eq!(snapped, (4000, 4095));
+    }
+
+    #[test]
+    fn sectors_outside_any_region_stay_still() {
+        let snapped = snap_bounds(
+            view(100, 5000),
+            None,
+            view(0, 200),
+            view(4900, 5100),
+            None,
+            Some(view(4096, 8191)),
+        );
+        assert_eq!(snapped, (100, 5000));
+    }
+
+    #[test]
+    fn snap_view_refuses_sectors_out_of_range() {
+        let mut sector = 10;
+        assert!(!snap_view(&mut sector, 30, view(0, 20)));
+        assert_eq!(sector, 10);
+        assert!(snap_view(&mut sector, 20, view(0, 20)));
+        assert_eq!(sector, 20);
+    }
+}
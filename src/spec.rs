@@ -0,0 +1,285 @@
+use super::gpt::PartitionTypeGuid;
+use super::{PartitionFlag, PartitionType};
+
+/// Where a partition described by a **PartitionSpec** should be placed on the disk.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PartitionExtent {
+    /// Occupies the sectors from `start` to `end`, inclusive.
+    Sectors { start: i64, end: i64 },
+    /// Occupies the given number of sectors at the start of the first free region that can
+    /// hold it.
+    StartOfFree(i64),
+    /// Occupies the given number of sectors at the end of the last free region that can
+    /// hold it.
+    EndOfFree(i64),
+    /// Occupies the sectors between two positions, which are resolved against the usable
+    /// region of the disk and its free space when the partition is created.
+    ///
+    /// ```rust,no_run
+    /// # use libparted::{PartitionExtent, PartitionSpec, Position};
+    /// // Swap in the last 4 GiB of the disk, given 512-byte sectors.
+    /// let swap = PartitionSpec::new(PartitionExtent::Between {
+    ///     start: Position::End(-8_388_608),
+    ///     end: Position::End(0),
+    /// })
+    /// .linux_swap();
+    /// ```
+    Between { start: Position, end: Position },
+    /// Occupies the span between two percentages of the device, as with
+    /// `parted mkpart 0% 50%`.
+    ///
+    /// Each percentage is converted to a sector as parted does, so that adjacent spans such
+    /// as `0%-50%` and `50%-100%` do not overlap. `0%` refers to the first usable sector and
+    /// `100%` to the last, so that the label's metadata is left alone, and each boundary is
+    /// then snapped to the alignment of the device when the partition is created.
+    Percent { start: f64, end: f64 },
+}
+
+/// A boundary of a partition given by `PartitionExtent::Between`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Position {
+    /// The given sector, from the start of the device.
+    Sector(i64),
+    /// The given number of sectors from the end of the usable region of the disk, which is
+    /// usually negative. As a start, `End(-n)` places the first sector `n` sectors before
+    /// the end; as an end, `End(-n)` leaves `n` sectors after the partition, so that
+    /// `End(0)` is the last usable sector.
+    End(i64),
+    /// As far as the free region holding the other boundary extends. If both boundaries are
+    /// `Fill`, the partition occupies the largest free region.
+    Fill,
+}
+
+/// Converts the percentages of a `PartitionExtent::Percent` to sectors, given the length of
+/// the device and the first and last sectors of its usable region.
+///
+/// Returns `None` unless `0 <= start < end <= 100`.
+pub(crate) fn percent_sectors(
+    start: f64,
+    end: f64,
+    length: i64,
+    usable: (i64, i64),
+) -> Option<(i64, i64)> {
+    if !(0.0 <= start && start < end && end <= 100.0) {
+        return None;
+    }
+
+    let sector = |percent: f64| (percent * length as f64 / 100.0) as i64;
+    let first = sector(start).max(usable.0);
+    let last = if end >= 100.0 {
+        usable.1
+    } else {
+        (sector(end) - 1).min(usable.1)
+    };
+
+    Some((first, last))
+}
+
+impl Position {
+    /// Resolves `start` and `end` to the sectors they refer to, given the first and last
+    /// sectors of the usable region, and the free regions a partition may be placed in.
+    ///
+    /// Returns `None` if a `Fill` boundary is not next to any free region, or if the start
+    /// would come after the end.
+    pub(crate) fn resolve(
+        start: Position,
+        end: Position,
+        usable: (i64, i64),
+        free: &[(i64, i64)],
+    ) -> Option<(i64, i64)> {
+        let fixed = |position: Position, offset: i64| match position {
+            Position::Sector(sector) => Some(sector),
+            Position::End(sectors) => Some(usable.1 + offset + sectors),
+            Position::Fill => None,
+        };
+        let containing = |sector: i64| {
+            free.iter()
+                .find(|&&(first, last)| first <= sector && sector <= last)
+                .cloned()
+        };
+
+        let range = match (fixed(start, 1), fixed(end, 0)) {
+            (Some(start), Some(end)) => Some((start, end)),
+            (Some(start), None) => containing(start).map(|(_, last)| (start, last)),
+            (None, Some(end)) => containing(end).map(|(first, _)| (first, end)),
+            (None, None) => free
+                .iter()
+                .cloned()
+                .max_by_key(|&(first, last)| last - first),
+        };
+
+        range.filter(|&(start, end)| start <= end)
+    }
+}
+
+/// Describes a partition to be created with `Disk::create_partition()`.
+///
+/// A spec is built with `PartitionSpec::new()` or one of its shorthands and then the builder
+/// methods, rather than a struct literal, so that further options may be added without
+/// breaking callers.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use libparted::{Bytes, Device, PartitionExtent, PartitionSpec, RoundMode, GIB};
+/// let device = Device::new("/dev/sda").unwrap();
+/// // Extents are given in sectors, so sizes in bytes are converted with the device.
+/// let length = device.bytes_to_sectors(Bytes(20 * GIB), RoundMode::Up);
+/// let spec = PartitionSpec::new(PartitionExtent::StartOfFree(length))
+///     .fs_type("ext4")
+///     .name("root");
+/// ```
+#[derive(Clone, Debug)]
+pub struct PartitionSpec {
+    pub extent: PartitionExtent,
+    pub part_type: PartitionType,
+    pub fs_type: Option<String>,
+    pub name: Option<String>,
+    pub flags: Vec<PartitionFlag>,
+    /// The type GUID to assign, on labels which support partition type UUIDs. This is only
+    /// applied with the `parted-3-5` feature, though most flags imply a type GUID anyway.
+    pub(crate) type_guid: Option<PartitionTypeGuid>,
+}
+
+impl PartitionSpec {
+    /// Creates a spec for a normal partition occupying `extent`, without a file system type,
+    /// name, or flags.
+    pub fn new(extent: PartitionExtent) -> PartitionSpec {
+        PartitionSpec {
+            extent,
+            part_type: PartitionType::PED_PARTITION_NORMAL,
+            fs_type: None,
+            name: None,
+            flags: Vec::new(),
+            type_guid: None,
+        }
+    }
+
+    /// Creates a spec for a partition occupying the sectors from `start` to `end`, inclusive.
+    pub fn with_sectors(start: i64, end: i64) -> PartitionSpec {
+        PartitionSpec::new(PartitionExtent::Sectors { start, end })
+    }
+
+    /// Creates a spec for a partition spanning from `start` to `end` percent of the device.
+    pub fn with_percent(start: f64, end: f64) -> PartitionSpec {
+        PartitionSpec::new(PartitionExtent::Percent { start, end })
+    }
+
+    /// Sets the type of partition to create (normal, logical, or extended).
+    pub fn part_type(mut self, part_type: PartitionType) -> PartitionSpec {
+        self.part_type = part_type;
+        self
+    }
+
+    /// Sets the name of the file system type, as known by libparted (ie: `ext4`, `fat32`).
+    pub fn fs_type<S: Into<String>>(mut self, fs_type: S) -> PartitionSpec {
+        self.fs_type = Some(fs_type.into());
+        self
+    }
+
+    /// Sets the name of the partition, for labels which support partition names.
+    pub fn name<S: Into<String>>(mut self, name: S) -> PartitionSpec {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Adds a flag to be enabled on the partition once it has been created.
+    pub fn flag(mut self, flag: PartitionFlag) -> PartitionSpec {
+        self.flags.push(flag);
+        self
+    }
+
+    /// Adds each of `flags` to be enabled on the partition once it has been created.
+    ///
+    /// Flags are applied by `Partition::set_flags()`, so the order they are given in does
+    /// not matter.
+    pub fn flags<I: IntoIterator<Item = PartitionFlag>>(mut self, flags: I) -> PartitionSpec {
+        self.flags.extend(flags);
+        self
+    }
+
+    /// Sets the type GUID of the partition, for labels which support partition type UUIDs.
+    pub fn type_guid(mut self, guid: PartitionTypeGuid) -> PartitionSpec {
+        self.type_guid = Some(guid);
+        self
+    }
+
+    /// Makes the partition an EFI system partition, formatted as FAT32.
+    pub fn esp(self) -> PartitionSpec {
+        self.fs_type("fat32")
+            .flag(PartitionFlag::PED_PARTITION_BOOT)
+            .flag(PartitionFlag::PED_PARTITION_ESP)
+            .type_guid(PartitionTypeGuid::EfiSystem)
+    }
+
+    /// Makes the partition a BIOS boot partition, in which GRUB embeds itself on GPT disks.
+    pub fn bios_grub(self) -> PartitionSpec {
+        self.flag(PartitionFlag::PED_PARTITION_BIOS_GRUB)
+            .type_guid(PartitionTypeGuid::BiosBoot)
+    }
+
+    /// Makes the partition a Linux swap partition.
+    pub fn linux_swap(self) -> PartitionSpec {
+        self.fs_type("linux-swap(v1)")
+            .flag(PartitionFlag::PED_PARTITION_SWAP)
+            .type_guid(PartitionTypeGuid::LinuxSwap)
+    }
+
+    /// Makes the partition an LVM physical volume.
+    pub fn lvm(self) -> PartitionSpec {
+        self.flag(PartitionFlag::PED_PARTITION_LVM)
+            .type_guid(PartitionTypeGuid::LinuxLvm)
+    }
+
+    /// Makes the partition a member of a Linux software RAID array.
+    pub fn raid(self) -> PartitionSpec {
+        self.flag(PartitionFlag::PED_PARTITION_RAID)
+            .type_guid(PartitionTypeGuid::LinuxRaid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USABLE: (i64, i64) = (34, 966);
+
+    #[test]
+    fn percentages_split_without_overlapping() {
+        assert_eq!(percent_sectors(0.0, 50.0, 1000, USABLE), Some((34, 499)));
+        assert_eq!(percent_sectors(50.0, 100.0, 1000, USABLE), Some((500, 966)));
+        assert_eq!(percent_sectors(50.0, 50.0, 1000, USABLE), None);
+        assert_eq!(percent_sectors(-1.0, 50.0, 1000, USABLE), None);
+        assert_eq!(percent_sectors(0.0, 101.0, 1000, USABLE), None);
+    }
+
+    #[test]
+    fn positions_resolve_against_the_usable_region() {
+        let resolve = |start, end| Position::resolve(start, end, USABLE, &[]);
+        assert_eq!(
+            resolve(Position::End(-100), Position::End(0)),
+            Some((867, 966))
+        );
+        assert_eq!(
+            resolve(Position::Sector(34), Position::End(-66)),
+            Some((34, 900))
+        );
+        assert_eq!(resolve(Position::Sector(2048), Position::End(-66)), None);
+    }
+
+    #[test]
+    fn fill_extends_to_the_free_region() {
+        let free = [(34, 100), (200, 900)];
+        let resolve = |start, end| Position::resolve(start, end, USABLE, &free);
+        assert_eq!(resolve(Position::Fill, Position::Fill), Some((200, 900)));
+        assert_eq!(
+            resolve(Position::Sector(250), Position::Fill),
+            Some((250, 900))
+        );
+        assert_eq!(
+            resolve(Position::Fill, Position::Sector(50)),
+            Some((34, 50))
+        );
+        assert_eq!(resolve(Position::Fill, Position::Sector(150)), None);
+    }
+}
@@ -0,0 +1,179 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The bus through which a device is attached to the system.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Transport {
+    Usb,
+    Nvme,
+    Sata,
+    Scsi,
+    Virtio,
+    Mmc,
+    Unknown,
+}
+
+/// Details of a device which libparted does not report, as returned by `Device::sysfs_info()`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SysfsInfo {
+    /// Whether the device has rotating media, or `None` if the kernel does not say.
+    pub rotational: Option<bool>,
+    /// Whether the device reports removable media, such as a card reader.
+    ///
+    /// USB disks usually report themselves as fixed, so check the `transport` as well.
+    pub removable: Option<bool>,
+    pub model: Option<String>,
+    pub vendor: Option<String>,
+    pub serial: Option<String>,
+    pub transport: Transport,
+}
+
+/// How a zoned device constrains writes to its zones.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ZoneModel {
+    /// Zones should be written sequentially, but the device accepts random writes (ie:
+    /// drive-managed or host-aware SMR).
+    HostAware,
+    /// Zones must be written sequentially, and random writes are refused (ie: host-managed
+    /// SMR, or ZNS SSDs).
+    HostManaged,
+}
+
+/// The zones of a zoned block device, as returned by `Device::zoned_info()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ZonedInfo {
+    pub model: ZoneModel,
+    /// The size of each zone, in bytes.
+    pub zone_size: u64,
+    /// The number of zones, if the kernel reports it.
+    pub zones: Option<u64>,
+}
+
+impl ZonedInfo {
+    /// Reads the zone model of the block device at `device` from sysfs, returning `None` if
+    /// it is not zoned.
+    pub(crate) fn read(device: &Path) -> io::Result<Option<ZonedInfo>> {
+        let block = block_dir(device)?;
+        let model = match read_attr(&block.join("queue/zoned")).as_deref() {
+            Some("host-aware") => ZoneModel::HostAware,
+            Some("host-managed") => ZoneModel::HostManaged,
+            _ => return Ok(None),
+        };
+
+        // The zone size is reported in 512-byte sectors, whatever the device's sector size.
+        let zone_size = read_attr(&block.join("queue/chunk_sectors"))
+            .and_then(|sectors| sectors.parse::<u64>().ok())
+            .filter(|&sectors| sectors != 0)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "zoned device does not report its zone size",
+                )
+            })?;
+
+        Ok(Some(ZonedInfo {
+            model,
+            zone_size: zone_size * 512,
+            zones: read_attr(&block.join("queue/nr_zones")).and_then(|zones| zones.parse().ok()),
+        }))
+    }
+}
+
+impl SysfsInfo {
+    /// Reads the details of the block device at `device` from sysfs. Given a partition, the
+    /// details of the disk which holds it are returned instead.
+    pub(crate) fn read(device: &Path) -> io::Result<SysfsInfo> {
+        let block = block_dir(device)?;
+        let flag = |attr: &str| read_attr(&block.join(attr)).map(|value| value != "0");
+        let hardware = block.join("device");
+        let serial = read_attr(&hardware.join("serial"))
+            .or_else(|| read_attr(&block.join("serial")))
+            .or_else(|| unit_serial(&hardware.join("vpd_pg80")));
+
+        Ok(SysfsInfo {
+            rotational: flag("queue/rotational"),
+            removable: flag("removable"),
+            model: read_attr(&hardware.join("model")),
+            vendor: read_attr(&hardware.join("vendor")),
+            serial,
+            transport: transport(&block),
+        })
+    }
+
+    /// Whether the device is most likely a solid-state drive.
+    pub fn is_ssd(&self) -> bool {
+        self.rotational == Some(false)
+            && self.removable != Some(true)
+            && self.transport != Transport::Mmc
+    }
+}
+
+/// The sysfs directory of the block device at `device`, or of the disk which holds it if it
+/// is a partition.
+fn block_dir(device: &Path) -> io::Result<PathBuf> {
+    let device = fs::canonicalize(device)?;
+    let name = device.file_name().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "device path has no file name")
+    })?;
+
+    let mut block = fs::canonicalize(Path::new("/sys/class/block").join(name))?;
+    if block.join("partition").exists() {
+        block.pop();
+    }
+
+    Ok(block)
+}
+
+/// Reads a sysfs attribute, treating an empty value the same as a missing one.
+fn read_attr(path: &Path) -> Option<String> {
+    fs::read_to_string(path)
+        .ok()
+        .map(|value| value.trim().to_owned())
+        .filter(|value| !value.is_empty())
+}
+
+/// Extracts the serial number from the Unit Serial Number VPD page of a SCSI device.
+fn unit_serial(path: &Path) -> Option<String> {
+    let page = fs::read(path).ok()?;
+    if page.len() < 4 || page[1] != 0x80 {
+        return None;
+    }
+
+    let length = (page[2] as usize) << 8 | page[3] as usize;
+    let serial = page.get(4..4 + length)?;
+    Some(String::from_utf8_lossy(serial).trim().to_owned()).filter(|serial| !serial.is_empty())
+}
+
+/// Determines the transport from the path of the device within the sysfs device hierarchy,
+/// such as `/sys/devices/pci0000:00/0000:00:14.0/usb2/2-1/.../block/sdb`.
+fn transport(block: &Path) -> Transport {
+    let path = match fs::canonicalize(block.join("device")) {
+        Ok(path) => path,
+        Err(_) => PathBuf::from(block),
+    };
+
+    let has = |component: &str| {
+        path.components().any(|part| {
+            part.as_os_str()
+                .to_str()
+                .map_or(false, |part| part.starts_with(component))
+        })
+    };
+
+    if has("usb") {
+        Transport::Usb
+    } else if has("nvme") {
+        Transport::Nvme
+    } else if has("mmc") {
+        Transport::Mmc
+    } else if has("virtio") {
+        Transport::Virtio
+    } else if has("ata") {
+        Transport::Sata
+    } else if has("host") || has("target") {
+        Transport::Scsi
+    } else {
+        Transport::Unknown
+    }
+}
@@ -1,7 +1,19 @@
-use libparted_sys::PedTimer;
+use libparted_sys::{ped_timer_reset, ped_timer_update, PedTimer};
 use std::marker::PhantomData;
 
 pub struct Timer<'a> {
     pub(crate) timer: *mut PedTimer,
     pub phantom: PhantomData<&'a PedTimer>,
 }
+
+impl<'a> Timer<'a> {
+    /// Restarts the timer, as at the beginning of a new operation.
+    pub(crate) fn reset(&mut self) {
+        unsafe { ped_timer_reset(self.timer) }
+    }
+
+    /// Reports that the fraction `frac` of the operation has been completed.
+    pub(crate) fn update(&mut self, frac: f32) {
+        unsafe { ped_timer_update(self.timer, frac) }
+    }
+}
@@ -0,0 +1,86 @@
+use super::Device;
+#[cfg(feature = "sysfs")]
+use std::fs;
+#[cfg(feature = "sysfs")]
+use std::path::Path;
+
+/// The physical topology of a device, in bytes, as returned by `Device::topology()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Topology {
+    /// The smallest unit which the device can address.
+    pub logical_sector: u64,
+    /// The smallest unit which the device can write without a read-modify-write cycle.
+    pub physical_sector: u64,
+    /// The preferred minimum unit for I/O, which partitions must at least be aligned to.
+    pub minimum_io: u64,
+    /// The preferred unit for sustained I/O, which partitions are ideally aligned to.
+    pub optimal_io: u64,
+    /// How far the first naturally aligned sector is offset from the start of the device.
+    pub alignment_offset: u64,
+}
+
+impl Topology {
+    /// Derives the topology from the alignments libparted uses when placing partitions.
+    pub(crate) fn from_device(device: &Device) -> Topology {
+        let sector = device.sector_size();
+        let minimum = device.get_minimum_alignment();
+        let optimum = device.get_optimum_alignment();
+
+        #[allow(unused_mut)]
+        let mut topology = Topology {
+            logical_sector: sector,
+            physical_sector: device.phys_sector_size(),
+            minimum_io: minimum
+                .as_ref()
+                .map_or(sector, |align| align.grain_size() as u64 * sector),
+            optimal_io: optimum
+                .as_ref()
+                .map_or(0, |align| align.grain_size() as u64 * sector),
+            alignment_offset: optimum
+                .as_ref()
+                .or(minimum.as_ref())
+                .map_or(0, |align| align.offset() as u64 * sector),
+        };
+
+        #[cfg(feature = "sysfs")]
+        topology.apply_sysfs(device.path());
+
+        topology
+    }
+
+    /// Replaces the derived values with those the kernel reports for the block device.
+    ///
+    /// A device which does not report an I/O size keeps the value libparted chose in its place.
+    #[cfg(feature = "sysfs")]
+    fn apply_sysfs(&mut self, path: &Path) {
+        let queue = match fs::canonicalize(path)
+            .ok()
+            .and_then(|path| path.file_name().map(|name| name.to_owned()))
+        {
+            Some(name) => Path::new("/sys/class/block").join(name),
+            None => return,
+        };
+
+        let read = |attr: &str| -> Option<u64> {
+            fs::read_to_string(queue.join(attr))
+                .ok()
+                .and_then(|value| value.trim().parse().ok())
+        };
+
+        if let Some(size) = read("queue/physical_block_size").filter(|&size| size != 0) {
+            self.physical_sector = size;
+        }
+
+        if let Some(size) = read("queue/minimum_io_size").filter(|&size| size != 0) {
+            self.minimum_io = size;
+        }
+
+        if let Some(size) = read("queue/optimal_io_size").filter(|&size| size != 0) {
+            self.optimal_io = size;
+        }
+
+        if let Some(offset) = read("alignment_offset") {
+            self.alignment_offset = offset;
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use super::Disk;
+use std::io::Result;
+use std::ops::{Deref, DerefMut};
+
+/// Guards changes made to a **Disk**, as returned by `Disk::begin()`.
+///
+/// The disk may be modified through the transaction as usual. Unless the transaction is
+/// finished with `commit()` or `commit_to_dev_only()`, the in-memory changes are discarded
+/// when it is dropped, by re-reading the partition table from the device.
+///
+/// # Note:
+///
+/// A disk created with `Disk::new_fresh()` which has never been committed cannot be re-read,
+/// and is left as it is if the changes cannot be discarded.
+///
+/// ```rust,no_run
+/// # use libparted::{Device, Disk, PartitionSpec};
+/// let device = Device::new("/dev/sda").unwrap();
+/// let mut disk = Disk::new(&device).unwrap();
+/// let mut transaction = disk.begin();
+/// transaction.remove_partition_by_number_returning(2).unwrap();
+/// transaction.create_partition(&PartitionSpec::with_sectors(2048, 1050623)).unwrap();
+/// transaction.commit().unwrap();
+/// ```
+pub struct DiskTransaction<'a, 'b: 'a> {
+    disk: &'a mut Disk<'b>,
+    finished: bool,
+}
+
+impl<'a, 'b> DiskTransaction<'a, 'b> {
+    pub(crate) fn new(disk: &'a mut Disk<'b>) -> DiskTransaction<'a, 'b> {
+        DiskTransaction {
+            disk,
+            finished: false,
+        }
+    }
+
+    /// Writes the changes to the device, and informs the operating system of them.
+    ///
+    /// If the commit fails, the changes are discarded.
+    pub fn commit(mut self) -> Result<()> {
+        self.disk.commit()?;
+        self.finished = true;
+        Ok(())
+    }
+
+    /// Writes the changes to the device, without informing the operating system of them.
+    ///
+    /// If the commit fails, the changes are discarded.
+    pub fn commit_to_dev_only(mut self) -> Result<()> {
+        self.disk.commit_to_dev()?;
+        self.finished = true;
+        Ok(())
+    }
+
+    /// Discards the changes, reporting any error which occurs while re-reading the disk.
+    pub fn rollback(mut self) -> Result<()> {
+        self.finished = true;
+        self.disk.reread()
+    }
+}
+
+impl<'a, 'b> Deref for DiskTransaction<'a, 'b> {
+    type Target = Disk<'b>;
+
+    fn deref(&self) -> &Disk<'b> {
+        self.disk
+    }
+}
+
+impl<'a, 'b> DerefMut for DiskTransaction<'a, 'b> {
+    fn deref_mut(&mut self) -> &mut Disk<'b> {
+        self.disk
+    }
+}
+
+impl<'a, 'b> Drop for DiskTransaction<'a, 'b> {
+    fn drop(&mut self) {
+        if !self.finished {
+            let _ = self.disk.reread();
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use super::{Device, Geometry, IsZero, Roundable};
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use std::ops::{Add, Sub};
+
+/// One kibibyte, in bytes.
+pub const KIB: u64 = 1024;
+/// One mebibyte, in bytes.
+pub const MIB: u64 = 1024 * KIB;
+/// One gibibyte, in bytes.
+pub const GIB: u64 = 1024 * MIB;
+/// One tebibyte, in bytes.
+pub const TIB: u64 = 1024 * GIB;
+
+/// How a byte count which does not fall on a sector boundary is converted to sectors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RoundMode {
+    /// Rounds down to the sector containing the byte.
+    Down,
+    /// Rounds up to the next sector boundary.
+    Up,
+    /// Rounds to the closest sector boundary, rounding up when exactly halfway.
+    Nearest,
+}
+
+pub(crate) fn bytes_to_sectors(bytes: u64, sector_size: u64, mode: RoundMode) -> i64 {
+    let sectors = bytes / sector_size;
+    let remainder = bytes % sector_size;
+    let round_up = match mode {
+        RoundMode::Down => false,
+        RoundMode::Up => remainder != 0,
+        RoundMode::Nearest => remainder * 2 >= sector_size,
+    };
+
+    (sectors + round_up as u64) as i64
+}
+
+/// An inclusive range of sectors on a device.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SectorRange {
+    pub start: i64,
+    pub end: i64,
+}
+
+impl SectorRange {
+    /// The range of sectors from `start` to `end`, inclusive.
+    pub fn new(start: i64, end: i64) -> SectorRange {
+        SectorRange { start, end }
+    }
+
+    /// The smallest range of sectors on `device` which contains the `length` bytes beginning
+    /// at the byte `offset`.
+    ///
+    /// Bytes which do not fall on sector boundaries are not an error: the start is rounded
+    /// down and the end rounded up, so that the range covers every sector the bytes touch.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidInput` if `length` is zero, as no range of sectors is empty, or if the
+    /// bytes extend beyond the largest byte offset.
+    pub fn from_bytes<O, L>(device: &Device, offset: O, length: L) -> io::Result<SectorRange>
+    where
+        O: Into<Bytes>,
+        L: Into<Bytes>,
+    {
+        let (offset, length) = (offset.into(), length.into());
+        if length.0 == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "a range of sectors cannot hold zero bytes",
+            ));
+        }
+
+        let end = offset.0.checked_add(length.0).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the range extends beyond the largest byte offset",
+            )
+        })?;
+
+        Ok(SectorRange {
+            start: device.bytes_to_sectors(offset, RoundMode::Down),
+            end: device.bytes_to_sectors(Bytes(end), RoundMode::Up) - 1,
+        })
+    }
+
+    /// The number of sectors in the range.
+    pub fn length(&self) -> i64 {
+        self.end - self.start + 1
+    }
+
+    /// Creates a **Geometry** on `device` covering the range.
+    pub fn to_geometry<'a>(&self, device: &Device) -> io::Result<Geometry<'a>> {
+        Geometry::new(device, Sector(self.start), Sector(self.length()))
+    }
+}
+
+/// A sector address or count on a device, as opposed to a number of bytes.
+///
+/// APIs which take a sector, such as `Geometry::new()`, accept anything which converts into
+/// a **Sector**, including a plain `i64`, as those which predate **Sector** took.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Sector(pub i64);
+
+impl Sector {
+    /// The sector containing the byte at `bytes`, or the number of sectors needed to hold
+    /// `bytes`, depending on `mode`.
+    pub fn from_bytes(bytes: Bytes, sector_size: u64, mode: RoundMode) -> Sector {
+        Sector(bytes_to_sectors(bytes.0, sector_size, mode))
+    }
+
+    /// The number of bytes in this many sectors, or the byte offset of this sector.
+    pub fn to_bytes(self, sector_size: u64) -> Bytes {
+        Bytes(self.0 as u64 * sector_size)
+    }
+}
+
+impl From<i64> for Sector {
+    fn from(sector: i64) -> Sector {
+        Sector(sector)
+    }
+}
+
+impl From<Sector> for i64 {
+    fn from(sector: Sector) -> i64 {
+        sector.0
+    }
+}
+
+impl Display for Sector {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}s", self.0)
+    }
+}
+
+impl Add for Sector {
+    type Output = Sector;
+
+    fn add(self, other: Sector) -> Sector {
+        Sector(self.0 + other.0)
+    }
+}
+
+impl Sub for Sector {
+    type Output = Sector;
+
+    fn sub(self, other: Sector) -> Sector {
+        Sector(self.0 - other.0)
+    }
+}
+
+impl IsZero for Sector {
+    fn is_zero(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl Roundable for Sector {
+    fn grain_rem(self, grain_size: Sector) -> Option<Sector> {
+        self.0.grain_rem(grain_size.0).map(Sector)
+    }
+
+    fn checked_add(self, other: Sector) -> Option<Sector> {
+        self.0.checked_add(other.0).map(Sector)
+    }
+
+    fn checked_sub(self, other: Sector) -> Option<Sector> {
+        self.0.checked_sub(other.0).map(Sector)
+    }
+
+    fn saturating_add(self, other: Sector) -> Sector {
+        Sector(self.0.saturating_add(other.0))
+    }
+
+    fn saturating_sub(self, other: Sector) -> Sector {
+        Sector(self.0.saturating_sub(other.0))
+    }
+
+    fn half(self) -> Sector {
+        Sector(self.0 / 2)
+    }
+}
+
+/// A byte offset or count on a device, as opposed to a number of sectors.
+///
+/// Converting to sectors requires the sector size of the device, as with
+/// `Bytes::to_sectors()` and `Device::bytes_to_sectors()`. With the `int-units` feature,
+/// which is enabled by default, a plain `u64` is accepted wherever **Bytes** are expected.
+/// Disabling it lets the compiler catch places where a sector is passed in place of a byte
+/// offset.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Bytes(pub u64);
+
+impl Bytes {
+    /// The sector containing this byte, or the number of sectors needed to hold this many
+    /// bytes, depending on `mode`.
+    pub fn to_sectors(self, sector_size: u64, mode: RoundMode) -> Sector {
+        Sector::from_bytes(self, sector_size, mode)
+    }
+}
+
+#[cfg(feature = "int-units")]
+impl From<u64> for Bytes {
+    fn from(bytes: u64) -> Bytes {
+        Bytes(bytes)
+    }
+}
+
+impl From<Bytes> for u64 {
+    fn from(bytes: Bytes) -> u64 {
+        bytes.0
+    }
+}
+
+impl Display for Bytes {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{} B", self.0)
+    }
+}
+
+impl Add for Bytes {
+    type Output = Bytes;
+
+    fn add(self, other: Bytes) -> Bytes {
+        Bytes(self.0 + other.0)
+    }
+}
+
+impl Sub for Bytes {
+    type Output = Bytes;
+
+    fn sub(self, other: Bytes) -> Bytes {
+        Bytes(self.0 - other.0)
+    }
+}
@@ -0,0 +1,85 @@
+//! Detection of the version of libparted which is loaded at runtime.
+//!
+//! Functions which were added in later releases of libparted are looked up when they are
+//! called, rather than linked, so that the crate continues to load against older releases.
+//! Calling an API which needs such a function then returns an error, instead of the program
+//! failing to start.
+
+use libparted_sys::ped_get_version;
+use std::ffi::CStr;
+use std::io;
+use std::mem;
+use std::os::raw::{c_char, c_void};
+
+/// The version of libparted which is loaded, as `(major, minor, patch)`.
+///
+/// Components which libparted does not report are zero (ie: `3.4` is `(3, 4, 0)`), and
+/// `(0, 0, 0)` is returned if the version could not be determined.
+pub fn version() -> (u32, u32, u32) {
+    let version = unsafe {
+        let version = ped_get_version();
+        if version.is_null() {
+            return (0, 0, 0);
+        }
+
+        CStr::from_ptr(version).to_string_lossy().into_owned()
+    };
+
+    parse_version(&version)
+}
+
+/// Whether the loaded libparted is at least version `major`.`minor`.
+pub fn version_at_least(major: u32, minor: u32) -> bool {
+    let (have_major, have_minor, _) = version();
+    (have_major, have_minor) >= (major, minor)
+}
+
+/// Parses a version such as `3.6` or `3.4.64-a1b2`, ignoring anything after the numbers.
+fn parse_version(version: &str) -> (u32, u32, u32) {
+    let mut parts = version.trim().split('.').map(|part| {
+        let digits = part
+            .chars()
+            .take_while(|c| c.is_ascii_digit())
+            .collect::<String>();
+        digits.parse().unwrap_or(0)
+    });
+
+    (
+        parts.next().unwrap_or(0),
+        parts.next().unwrap_or(0),
+        parts.next().unwrap_or(0),
+    )
+}
+
+/// Looks up the libparted function `name`, which must be nul-terminated, as a function
+/// pointer of type `F`.
+///
+/// Returns an error naming `since`, the release which introduced the function, if the loaded
+/// libparted does not provide it.
+///
+/// # Safety
+///
+/// `F` must be an `extern "C"` function pointer type matching the function's signature.
+#[cfg_attr(not(feature = "parted-3-5"), allow(dead_code))]
+pub(crate) unsafe fn function<F: Copy>(name: &'static [u8], since: &str) -> io::Result<F> {
+    debug_assert_eq!(mem::size_of::<F>(), mem::size_of::<*mut c_void>());
+    debug_assert_eq!(name.last(), Some(&0));
+
+    let symbol = libc::dlsym(libc::RTLD_DEFAULT, name.as_ptr() as *const c_char);
+    if symbol.is_null() {
+        let (major, minor, patch) = version();
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!(
+                "unsupported: {} requires libparted {} or later, but {}.{}.{} is loaded",
+                String::from_utf8_lossy(&name[..name.len() - 1]),
+                since,
+                major,
+                minor,
+                patch
+            ),
+        ));
+    }
+
+    Ok(mem::transmute_copy(&symbol))
+}